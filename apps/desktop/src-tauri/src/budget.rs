@@ -0,0 +1,570 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashMap};
+use tauri::AppHandle;
+
+use crate::db;
+use crate::error::{AppError, AppResult};
+
+/// One purchase as it counts against the budget. `net` is what it costs
+/// after freebies and discounts; drawbacks cost nothing and add `credit`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct BudgetLine {
+    asset_id: String,
+    name: String,
+    asset_type: String,
+    gross: f64,
+    net: f64,
+    credit: f64,
+    discounted: bool,
+    freebie: bool,
+}
+
+/// `budget` is the jump's CP plus drawback credit.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct BudgetReport {
+    spent: f64,
+    budget: f64,
+    remaining: f64,
+    over_budget: bool,
+    breakdown: Vec<BudgetLine>,
+}
+
+/// `(id, name, asset_type, cost, quantity, discounted, freebie)` from
+/// `jump_assets`.
+pub(crate) type AssetRow = (String, String, String, i64, i64, bool, bool);
+
+/// Prices one asset the way the frontend's `computeBudget` does: negative
+/// costs count as zero, freebies are free and discounts halve the cost.
+/// Drawbacks are never discounted.
+fn price_asset(row: AssetRow) -> BudgetLine {
+    let (asset_id, name, asset_type, cost, quantity, discounted, freebie) = row;
+    let gross = (cost.max(0) * quantity.max(1)) as f64;
+    let (net, credit) = if asset_type == "drawback" {
+        (0.0, gross)
+    } else if freebie {
+        (0.0, 0.0)
+    } else if discounted {
+        (gross / 2.0, 0.0)
+    } else {
+        (gross, 0.0)
+    };
+    BudgetLine {
+        asset_id,
+        name,
+        asset_type,
+        gross,
+        net,
+        credit,
+        discounted,
+        freebie,
+    }
+}
+
+/// Net CP spent on `rows` and drawback credit earned from them.
+pub(crate) fn spend_and_credit(rows: impl IntoIterator<Item = AssetRow>) -> (f64, f64) {
+    rows.into_iter()
+        .map(price_asset)
+        .fold((0.0, 0.0), |(spent, credit), line| {
+            (spent + line.net, credit + line.credit)
+        })
+}
+
+pub(crate) async fn jump_budget(pool: &SqlitePool, jump_id: &str) -> AppResult<BudgetReport> {
+    let base: Option<i64> = sqlx::query_scalar(
+        "SELECT COALESCE(cp_budget, 0) FROM jumps WHERE id = ? AND deleted_at IS NULL",
+    )
+    .bind(jump_id)
+    .fetch_optional(pool)
+    .await?;
+    let base = base.ok_or_else(|| AppError::NotFound(format!("Jump {jump_id} not found")))?;
+
+    let rows: Vec<AssetRow> = sqlx::query_as(
+        "SELECT id, name, asset_type, COALESCE(cost, 0), COALESCE(quantity, 1),
+                COALESCE(discounted, 0) = 1, COALESCE(freebie, 0) = 1
+         FROM jump_assets WHERE jump_id = ? AND deleted_at IS NULL
+         ORDER BY sort_order, created_at",
+    )
+    .bind(jump_id)
+    .fetch_all(pool)
+    .await?;
+    let breakdown: Vec<BudgetLine> = rows.into_iter().map(price_asset).collect();
+
+    let spent: f64 = breakdown.iter().map(|line| line.net).sum();
+    let budget = base as f64 + breakdown.iter().map(|line| line.credit).sum::<f64>();
+    let remaining = budget - spent;
+    Ok(BudgetReport {
+        spent,
+        budget,
+        remaining,
+        over_budget: remaining < 0.0,
+        breakdown,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// One problem found by `audit_chain`. `code` is stable for the frontend
+/// to branch on; `message` is for display.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct ChainIssue {
+    severity: Severity,
+    code: &'static str,
+    jump_id: Option<String>,
+    asset_id: Option<String>,
+    message: String,
+}
+
+/// Recorded and recomputed spend may differ by rounding when a discount
+/// halves an odd cost.
+const SPENT_TOLERANCE: f64 = 0.5;
+
+/// Recomputes every live jump's budget and looks for rows that no longer
+/// fit together. Read-only; issues come back worst first.
+pub(crate) async fn audit(pool: &SqlitePool) -> AppResult<Vec<ChainIssue>> {
+    let mut issues = Vec::new();
+
+    let jumps: Vec<(String, String, f64, f64)> = sqlx::query_as(
+        "SELECT j.id, j.title, CAST(COALESCE(j.cp_spent, 0) AS REAL),
+                CAST(COALESCE((SELECT SUM(COALESCE(option_value, 0)) FROM companion_imports
+                               WHERE jump_id = j.id AND selected = 1), 0) AS REAL)
+         FROM jumps j WHERE j.deleted_at IS NULL ORDER BY j.sort_order, j.created_at",
+    )
+    .fetch_all(pool)
+    .await?;
+    for (jump_id, title, recorded, imports) in jumps {
+        let report = jump_budget(pool, &jump_id).await?;
+        // `cp_spent` also counts selected companion imports.
+        let expected = report.spent + imports;
+        if (recorded - expected).abs() > SPENT_TOLERANCE {
+            issues.push(ChainIssue {
+                severity: Severity::Warning,
+                code: "spent_mismatch",
+                jump_id: Some(jump_id.clone()),
+                asset_id: None,
+                message: format!(
+                    "{title} records {recorded} CP spent but its purchases add up to {expected}"
+                ),
+            });
+        }
+        if report.over_budget {
+            issues.push(ChainIssue {
+                severity: Severity::Warning,
+                code: "over_budget",
+                jump_id: Some(jump_id.clone()),
+                asset_id: None,
+                message: format!("{title} is {} CP over budget", -report.remaining),
+            });
+        }
+    }
+
+    // Foreign keys were not always enforced, so assets can outlive their
+    // jump entirely.
+    let assets: Vec<(String, String, String, String, Option<String>)> = sqlx::query_as(
+        "SELECT a.id, a.name, a.asset_type, a.jump_id, j.deleted_at
+         FROM jump_assets a LEFT JOIN jumps j ON j.id = a.jump_id
+         WHERE a.deleted_at IS NULL AND (j.id IS NULL OR j.deleted_at IS NOT NULL)
+         ORDER BY a.jump_id, a.sort_order",
+    )
+    .fetch_all(pool)
+    .await?;
+    for (asset_id, name, asset_type, jump_id, jump_deleted_at) in assets {
+        let issue = match jump_deleted_at {
+            None => ChainIssue {
+                severity: Severity::Error,
+                code: "unlinked_asset",
+                message: format!(
+                    "{asset_type} {name} belongs to jump {jump_id}, which does not exist"
+                ),
+                jump_id: Some(jump_id),
+                asset_id: Some(asset_id),
+            },
+            Some(_) => ChainIssue {
+                severity: Severity::Info,
+                code: "asset_in_deleted_jump",
+                message: format!("{asset_type} {name} belongs to a jump in the recycle bin"),
+                jump_id: Some(jump_id),
+                asset_id: Some(asset_id),
+            },
+        };
+        issues.push(issue);
+    }
+
+    issues.sort_by(|a, b| b.severity.cmp(&a.severity));
+    Ok(issues)
+}
+
+/// How many perk categories `chain_statistics` ranks.
+const TOP_CATEGORIES: usize = 5;
+/// Perks without a category are ranked under this name.
+const UNCATEGORIZED: &str = "Uncategorized";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct CategoryUsage {
+    category: String,
+    purchases: i64,
+    spent: f64,
+}
+
+/// The live jump with the widest span between its start and end dates.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct LongestJump {
+    jump_id: String,
+    title: String,
+    days: f64,
+}
+
+/// Dashboard totals over every live jump and purchase. Spending is priced
+/// the way `validate_budget` prices it, not read from `cp_spent`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct ChainStatistics {
+    total_jumps: i64,
+    total_purchases: i64,
+    cp_budget: f64,
+    cp_spent: f64,
+    drawback_credit: f64,
+    purchases_by_type: BTreeMap<String, i64>,
+    /// Most purchased perk categories, most used first.
+    top_perk_categories: Vec<CategoryUsage>,
+    /// `None` until some jump has both dates in a form SQLite can read.
+    longest_jump: Option<LongestJump>,
+}
+
+/// An [`AssetRow`] followed by the asset's category.
+type CategorizedRow = (String, String, String, i64, i64, bool, bool, Option<String>);
+
+/// Three read-only queries: jump totals, the longest jump, and one pass
+/// over every live purchase.
+pub(crate) async fn chain_stats(pool: &SqlitePool) -> AppResult<ChainStatistics> {
+    let (total_jumps, cp_budget): (i64, i64) = sqlx::query_as(
+        "SELECT COUNT(*), COALESCE(SUM(COALESCE(cp_budget, 0)), 0)
+         FROM jumps WHERE deleted_at IS NULL",
+    )
+    .fetch_one(pool)
+    .await?;
+    let longest_jump = sqlx::query_as::<_, (String, String, f64)>(
+        "SELECT id, title, julianday(end_date) - julianday(start_date) AS days
+         FROM jumps
+         WHERE deleted_at IS NULL
+           AND julianday(end_date) - julianday(start_date) IS NOT NULL
+         ORDER BY days DESC, sort_order, created_at LIMIT 1",
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(|(jump_id, title, days)| LongestJump {
+        jump_id,
+        title,
+        days,
+    });
+
+    let rows: Vec<CategorizedRow> = sqlx::query_as(
+        "SELECT a.id, a.name, a.asset_type, COALESCE(a.cost, 0), COALESCE(a.quantity, 1),
+                COALESCE(a.discounted, 0) = 1, COALESCE(a.freebie, 0) = 1, a.category
+         FROM jump_assets a JOIN jumps j ON j.id = a.jump_id
+         WHERE a.deleted_at IS NULL AND j.deleted_at IS NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let total_purchases = rows.len() as i64;
+    let (mut cp_spent, mut drawback_credit) = (0.0, 0.0);
+    let mut purchases_by_type = BTreeMap::new();
+    let mut categories: HashMap<String, CategoryUsage> = HashMap::new();
+    for (id, name, asset_type, cost, quantity, discounted, freebie, category) in rows {
+        let line = price_asset((id, name, asset_type, cost, quantity, discounted, freebie));
+        cp_spent += line.net;
+        drawback_credit += line.credit;
+        *purchases_by_type
+            .entry(line.asset_type.clone())
+            .or_insert(0) += 1;
+        if line.asset_type == "perk" {
+            let category = category
+                .map(|category| category.trim().to_string())
+                .filter(|category| !category.is_empty())
+                .unwrap_or_else(|| UNCATEGORIZED.to_string());
+            let usage = categories
+                .entry(category.clone())
+                .or_insert_with(|| CategoryUsage {
+                    category,
+                    purchases: 0,
+                    spent: 0.0,
+                });
+            usage.purchases += 1;
+            usage.spent += line.net;
+        }
+    }
+    let mut top_perk_categories: Vec<CategoryUsage> = categories.into_values().collect();
+    top_perk_categories.sort_by(|a, b| {
+        b.purchases
+            .cmp(&a.purchases)
+            .then_with(|| a.category.cmp(&b.category))
+    });
+    top_perk_categories.truncate(TOP_CATEGORIES);
+
+    Ok(ChainStatistics {
+        total_jumps,
+        total_purchases,
+        cp_budget: cp_budget as f64,
+        cp_spent,
+        drawback_credit,
+        purchases_by_type,
+        top_perk_categories,
+        longest_jump,
+    })
+}
+
+async fn ensure_character(pool: &SqlitePool, character_id: &str) -> AppResult<()> {
+    let known: Option<i64> = sqlx::query_scalar("SELECT 1 FROM character_profiles WHERE id = ?")
+        .bind(character_id)
+        .fetch_optional(pool)
+        .await?;
+    match known {
+        Some(_) => Ok(()),
+        None => Err(AppError::NotFound(format!(
+            "Character {character_id} not found"
+        ))),
+    }
+}
+
+/// Checks a jump's purchases against its budget, so every caller shares
+/// one copy of the arithmetic.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn validate_budget(app: AppHandle, jump_id: String) -> AppResult<BudgetReport> {
+    let pool = db::pool(&app).await?;
+    jump_budget(&pool, &jump_id).await
+}
+
+/// Audits the chain `character_id` plays through. Jumps are shared by the
+/// whole chain rather than owned per character, so every live jump is
+/// checked.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn audit_chain(
+    app: AppHandle,
+    character_id: String,
+) -> AppResult<Vec<ChainIssue>> {
+    let pool = db::pool(&app).await?;
+    ensure_character(&pool, &character_id).await?;
+    audit(&pool).await
+}
+
+/// Totals for a home dashboard of the chain `character_id` plays through;
+/// like `audit_chain`, it covers every live jump.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn chain_statistics(
+    app: AppHandle,
+    character_id: String,
+) -> AppResult<ChainStatistics> {
+    let pool = db::pool(&app).await?;
+    ensure_character(&pool, &character_id).await?;
+    chain_stats(&pool).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::{apply_migrations, MIGRATIONS};
+
+    #[tokio::test]
+    async fn jump_budget_applies_discounts_freebies_and_credit() {
+        let pool = db::memory_pool().await;
+        apply_migrations(&pool, MIGRATIONS).await.unwrap();
+        db::run_batch(
+            &pool,
+            &[
+                (
+                    "INSERT INTO jumps (id, title, cp_budget) VALUES ('j1', 'Naruto', 1000)".into(),
+                    None,
+                ),
+                (
+                    "INSERT INTO jump_assets
+                        (id, jump_id, asset_type, name, cost, quantity, discounted, freebie, sort_order)
+                     VALUES ('a1', 'j1', 'perk', 'Shadow Clone', 600, 1, 0, 0, 0),
+                            ('a2', 'j1', 'perk', 'Chakra Control', 300, 1, 1, 0, 1),
+                            ('a3', 'j1', 'item', 'Kunai', 50, 4, 0, 0, 2),
+                            ('a4', 'j1', 'item', 'Headband', 100, 1, 0, 1, 3),
+                            ('a5', 'j1', 'drawback', 'Rival', 200, 1, 1, 0, 4)"
+                        .into(),
+                    None,
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let report = jump_budget(&pool, "j1").await.unwrap();
+        let nets: Vec<f64> = report.breakdown.iter().map(|line| line.net).collect();
+        assert_eq!(nets, [600.0, 150.0, 200.0, 0.0, 0.0]);
+        assert_eq!(report.breakdown[4].credit, 200.0);
+        assert_eq!(report.spent, 950.0);
+        assert_eq!(report.budget, 1200.0);
+        assert_eq!(report.remaining, 250.0);
+        assert!(!report.over_budget);
+
+        db::run_batch(
+            &pool,
+            &[(
+                "UPDATE jump_assets SET discounted = 0 WHERE id = 'a2'".into(),
+                None,
+            )],
+        )
+        .await
+        .unwrap();
+        let report = jump_budget(&pool, "j1").await.unwrap();
+        assert_eq!(report.remaining, -50.0);
+        assert!(report.over_budget);
+
+        let missing = jump_budget(&pool, "nope").await.unwrap_err();
+        assert_eq!(missing.code(), "not_found");
+    }
+
+    #[tokio::test]
+    async fn audit_flags_drift_and_stray_assets() {
+        let pool = db::memory_pool().await;
+        apply_migrations(&pool, MIGRATIONS).await.unwrap();
+        // Stands in for a database from before foreign keys were enforced.
+        sqlx::query("PRAGMA foreign_keys = OFF")
+            .execute(&pool)
+            .await
+            .unwrap();
+        db::run_batch(
+            &pool,
+            &[
+                (
+                    "INSERT INTO jumps (id, title, cp_budget, cp_spent, deleted_at)
+                     VALUES ('j1', 'Naruto', 1000, 600, NULL),
+                            ('j2', 'Bleach', 100, 200, NULL),
+                            ('j3', 'Worm', 1000, 0, '2000-01-01T00:00:00.000Z')"
+                        .into(),
+                    None,
+                ),
+                (
+                    "INSERT INTO jump_assets (id, jump_id, asset_type, name, cost)
+                     VALUES ('a1', 'j1', 'perk', 'Shadow Clone', 600),
+                            ('a2', 'j2', 'perk', 'Bankai', 150),
+                            ('a3', 'j3', 'item', 'Tinker Lab', 200),
+                            ('a4', 'gone', 'perk', 'Sharingan', 100)"
+                        .into(),
+                    None,
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let issues = audit(&pool).await.unwrap();
+        let found: Vec<(Severity, &str, Option<&str>)> = issues
+            .iter()
+            .map(|issue| {
+                (
+                    issue.severity,
+                    issue.code,
+                    issue.asset_id.as_deref().or(issue.jump_id.as_deref()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Severity::Error, "unlinked_asset", Some("a4")),
+                (Severity::Warning, "spent_mismatch", Some("j2")),
+                (Severity::Warning, "over_budget", Some("j2")),
+                (Severity::Info, "asset_in_deleted_jump", Some("a3")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn chain_stats_sum_live_jumps_and_rank_categories() {
+        let pool = db::memory_pool().await;
+        apply_migrations(&pool, MIGRATIONS).await.unwrap();
+
+        let empty = chain_stats(&pool).await.unwrap();
+        assert_eq!((empty.total_jumps, empty.cp_spent), (0, 0.0));
+        assert_eq!(empty.longest_jump, None);
+
+        db::run_batch(
+            &pool,
+            &[
+                (
+                    "INSERT INTO jumps (id, title, cp_budget, start_date, end_date, deleted_at)
+                     VALUES ('j1', 'Naruto', 1000, '2024-01-01', '2034-01-01', NULL),
+                            ('j2', 'Bleach', 600, 'ten years ago', NULL, NULL),
+                            ('j3', 'Worm', 1000, '2000-01-01', '2100-01-01',
+                             '2000-01-01T00:00:00.000Z')"
+                        .into(),
+                    None,
+                ),
+                (
+                    "INSERT INTO jump_assets
+                        (id, jump_id, asset_type, name, category, cost, discounted, sort_order)
+                     VALUES ('a1', 'j1', 'perk', 'Shadow Clone', 'Ninjutsu', 600, 0, 0),
+                            ('a2', 'j1', 'perk', 'Rasengan', 'Ninjutsu', 400, 1, 1),
+                            ('a3', 'j2', 'perk', 'Bankai', ' Zanpakuto ', 300, 0, 0),
+                            ('a4', 'j2', 'perk', 'Flash Step', NULL, 100, 0, 1),
+                            ('a5', 'j2', 'drawback', 'Hollowfication', NULL, 200, 0, 2),
+                            ('a6', 'j3', 'perk', 'Tinker', 'Powers', 600, 0, 0)"
+                        .into(),
+                    None,
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let stats = chain_stats(&pool).await.unwrap();
+        assert_eq!((stats.total_jumps, stats.total_purchases), (2, 5));
+        assert_eq!(stats.cp_budget, 1600.0);
+        assert_eq!(stats.cp_spent, 1200.0);
+        assert_eq!(stats.drawback_credit, 200.0);
+        assert_eq!(
+            stats.purchases_by_type,
+            BTreeMap::from([("drawback".to_string(), 1), ("perk".to_string(), 4)])
+        );
+        let ranked: Vec<(&str, i64, f64)> = stats
+            .top_perk_categories
+            .iter()
+            .map(|usage| (usage.category.as_str(), usage.purchases, usage.spent))
+            .collect();
+        assert_eq!(
+            ranked,
+            [
+                ("Ninjutsu", 2, 800.0),
+                ("Uncategorized", 1, 100.0),
+                ("Zanpakuto", 1, 300.0),
+            ]
+        );
+        let longest = stats.longest_jump.unwrap();
+        assert_eq!((longest.jump_id.as_str(), longest.days), ("j1", 3653.0));
+    }
+}
@@ -0,0 +1,1994 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use futures_util::TryStreamExt;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::query::Query;
+use sqlx::sqlite::{
+    SqliteArguments, SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqlitePool,
+    SqliteRow, SqliteSynchronous,
+};
+use sqlx::{
+    Column, Connection, Executor, Row, Sqlite, SqliteExecutor, Statement, TypeInfo, ValueRef,
+};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tauri_plugin_sql::{DbInstances, DbPool};
+
+use crate::error::{AppError, AppResult};
+use crate::transactions::TransactionState;
+
+/// Connection string the frontend passes to `Database.load`, so both sides
+/// share a single pool registered with the SQL plugin.
+pub(crate) const DB_URL: &str = "sqlite:app.db";
+const DB_FILENAME: &str = "app.db";
+const DB_BACKUP_EVENT: &str = "db://backup";
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+const QUERY_STREAM_EVENT: &str = "db://query-stream";
+
+const DEFAULT_PAGE_SIZE: usize = 500;
+/// Upper bound on rows per event so one page never stalls the webview.
+const MAX_PAGE_SIZE: usize = 5000;
+
+type SqliteQuery<'q> = Query<'q, Sqlite, SqliteArguments<'q>>;
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ColumnInfo {
+    name: String,
+    decl_type: Option<String>,
+}
+
+/// Shape returned by `db_query`; `kind` lets the frontend branch without
+/// sniffing fields.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum QueryResult {
+    Select {
+        columns: Vec<ColumnInfo>,
+        rows: Vec<Value>,
+    },
+    Execute {
+        rows_affected: u64,
+        last_insert_id: i64,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct BatchResult {
+    statements: usize,
+    rows_affected: u64,
+    /// Statements skipped by a `continue_on_error` batch; always empty for an
+    /// all-or-nothing one.
+    failures: Vec<BatchFailure>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct BatchFailure {
+    index: usize,
+    error: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum QueryStreamPayload {
+    Columns { columns: Vec<ColumnInfo> },
+    Rows { rows: Vec<Value> },
+    Complete { total: u64 },
+    Cancelled { total: u64 },
+    Error { message: String },
+}
+
+#[derive(Debug, Serialize)]
+struct QueryStreamEvent<'a> {
+    stream_id: &'a str,
+    #[serde(flatten)]
+    payload: QueryStreamPayload,
+}
+
+/// Cancellation flags for in-flight `db_query_stream` calls, keyed by the
+/// caller-chosen `stream_id`.
+#[derive(Default)]
+pub(crate) struct QueryStreamState {
+    streams: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct BackupResult {
+    path: String,
+    bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct OptimizeResult {
+    vacuumed: bool,
+    bytes_before: u64,
+    bytes_after: u64,
+}
+
+/// One step of `EXPLAIN QUERY PLAN`; `parent` is the `id` of the step it
+/// is nested under, 0 at the top level.
+#[derive(Debug, Serialize)]
+pub(crate) struct PlanStep {
+    id: i64,
+    parent: i64,
+    detail: String,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct TableSchema {
+    table: String,
+    columns: Vec<SchemaColumn>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct SchemaColumn {
+    name: String,
+    #[serde(rename = "type")]
+    declared_type: String,
+    notnull: bool,
+    /// Position in the primary key, from 1; 0 for columns outside it.
+    pk: i64,
+}
+
+/// Connection settings as SQLite reports them, to confirm what
+/// [`open_pool`] asked for actually took effect.
+#[derive(Debug, Serialize)]
+pub(crate) struct DbPragmas {
+    journal_mode: String,
+    synchronous: String,
+    foreign_keys: bool,
+}
+
+/// A row `PRAGMA foreign_key_check` flagged: `rowid` in `table` points at a
+/// missing row in `parent`. `rowid` is null for `WITHOUT ROWID` tables.
+#[derive(Debug, Serialize)]
+pub(crate) struct ForeignKeyViolation {
+    table: String,
+    rowid: Option<i64>,
+    parent: String,
+    constraint_index: i64,
+}
+
+/// What `repair_orphans` does to a row whose parent is gone: whatever the
+/// constraint's `ON DELETE` would have done had foreign keys been enforced
+/// when the parent was deleted. `Detach` clears the key of a `SET NULL`
+/// constraint; every other orphan is deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum OrphanAction {
+    Delete,
+    Detach,
+}
+
+/// One repaired row. `missing_id` is the key value the missing parent had.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct OrphanRepair {
+    table: String,
+    rowid: i64,
+    columns: Vec<String>,
+    parent: String,
+    missing_id: Option<String>,
+    action: OrphanAction,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct OrphanReport {
+    dry_run: bool,
+    repairs: Vec<OrphanRepair>,
+    /// Violations left in place, such as rows of `WITHOUT ROWID` tables,
+    /// which cannot be addressed by rowid.
+    unrepaired: Vec<ForeignKeyViolation>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct HealthReport {
+    /// True when neither check found a problem.
+    ok: bool,
+    integrity_errors: Vec<String>,
+    foreign_key_violations: Vec<ForeignKeyViolation>,
+    page_size: i64,
+    page_count: i64,
+    free_pages: i64,
+    journal_mode: String,
+    wal_enabled: bool,
+}
+
+/// Location of the live database file in the app config directory.
+pub(crate) fn db_path(app: &AppHandle) -> AppResult<PathBuf> {
+    let config_dir = app.path().app_config_dir()?;
+    Ok(config_dir.join(DB_FILENAME))
+}
+
+/// Returns the plugin-managed pool for [`DB_URL`], opening (and registering)
+/// it the same way `Database.load` would when the frontend has not yet.
+pub(crate) async fn pool(app: &AppHandle) -> AppResult<SqlitePool> {
+    let instances = app.state::<DbInstances>();
+
+    if let Some(DbPool::Sqlite(pool)) = instances.0.read().await.get(DB_URL) {
+        return Ok(pool.clone());
+    }
+
+    let mut guard = instances.0.write().await;
+    if let Some(DbPool::Sqlite(pool)) = guard.get(DB_URL) {
+        return Ok(pool.clone());
+    }
+
+    let pool = open_pool(&db_path(app)?).await?;
+    guard.insert(DB_URL.to_string(), DbPool::Sqlite(pool.clone()));
+    Ok(pool)
+}
+
+async fn open_pool(path: &Path) -> AppResult<SqlitePool> {
+    if let Some(config_dir) = path.parent() {
+        std::fs::create_dir_all(config_dir)?;
+    }
+
+    // WAL lets the UI keep reading while PDF indexing writes. With
+    // `synchronous = NORMAL` a commit is not fsynced until the next
+    // checkpoint, so a power cut can lose the last few transactions but never
+    // corrupts the file; for a local app that is worth the write speed.
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal)
+        .foreign_keys(true);
+    Ok(SqlitePool::connect_with(options).await?)
+}
+
+/// Reads back the pragmas [`open_pool`] sets.
+pub(crate) async fn read_pragmas(pool: &SqlitePool) -> AppResult<DbPragmas> {
+    let mut conn = pool.acquire().await?;
+    let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode")
+        .fetch_one(&mut *conn)
+        .await?;
+    let synchronous: i64 = sqlx::query_scalar("PRAGMA synchronous")
+        .fetch_one(&mut *conn)
+        .await?;
+    let foreign_keys: bool = sqlx::query_scalar("PRAGMA foreign_keys")
+        .fetch_one(&mut *conn)
+        .await?;
+    let synchronous = match synchronous {
+        0 => "off",
+        1 => "normal",
+        2 => "full",
+        3 => "extra",
+        _ => "unknown",
+    };
+    Ok(DbPragmas {
+        journal_mode: journal_mode.to_ascii_lowercase(),
+        synchronous: synchronous.into(),
+        foreign_keys,
+    })
+}
+
+/// Skips leading whitespace and SQL comments and returns the first keyword,
+/// upper-cased.
+fn leading_keyword(query: &str) -> String {
+    let mut rest = query;
+    loop {
+        rest = rest.trim_start();
+        if let Some(stripped) = rest.strip_prefix("--") {
+            rest = stripped.split_once('\n').map_or("", |(_, tail)| tail);
+        } else if let Some(stripped) = rest.strip_prefix("/*") {
+            rest = stripped.split_once("*/").map_or("", |(_, tail)| tail);
+        } else {
+            break;
+        }
+    }
+
+    rest.chars()
+        .take_while(|ch| ch.is_ascii_alphabetic())
+        .collect::<String>()
+        .to_ascii_uppercase()
+}
+
+/// Whether the statement produces a result set rather than only a row count.
+/// Mutations with a `RETURNING` clause are treated as row-producing.
+fn returns_rows(query: &str) -> bool {
+    if matches!(
+        leading_keyword(query).as_str(),
+        "SELECT" | "WITH" | "PRAGMA" | "EXPLAIN" | "VALUES"
+    ) {
+        return true;
+    }
+
+    without_literals(query)
+        .to_ascii_uppercase()
+        .split(|ch: char| !ch.is_ascii_alphanumeric() && ch != '_')
+        .any(|token| token == "RETURNING")
+}
+
+/// `query` with string literals, quoted identifiers and comments blanked
+/// out, so keyword checks only see the statement's own tokens.
+fn without_literals(query: &str) -> String {
+    let bytes = query.as_bytes();
+    let mut sql = String::with_capacity(query.len());
+    let mut copied = 0;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let next = bytes.get(index + 1).copied();
+        let end = match bytes[index] {
+            quote @ (b'\'' | b'"' | b'`') => skip_past(bytes, index + 1, &[quote]),
+            b'[' => skip_past(bytes, index + 1, b"]"),
+            b'-' if next == Some(b'-') => skip_past(bytes, index + 2, b"\n"),
+            b'/' if next == Some(b'*') => skip_past(bytes, index + 2, b"*/"),
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+        sql.push_str(&query[copied..index]);
+        sql.push(' ');
+        copied = end;
+        index = end;
+    }
+
+    sql.push_str(&query[copied..]);
+    sql
+}
+
+/// Binds a JSON value as a positional parameter. Integers stay integers
+/// (the plugin's own binder widens everything to `f64`), booleans become
+/// 0/1, and arrays/objects are stored as their JSON text.
+fn bind_value<'q>(query: SqliteQuery<'q>, value: &Value) -> SqliteQuery<'q> {
+    match value {
+        Value::Null => query.bind(None::<String>),
+        Value::Bool(flag) => query.bind(*flag),
+        Value::Number(number) => match number.as_i64() {
+            Some(int) => query.bind(int),
+            None => query.bind(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(text) => query.bind(text.clone()),
+        other => query.bind(other.to_string()),
+    }
+}
+
+pub(crate) fn bind_values<'q>(query: SqliteQuery<'q>, values: &[Value]) -> SqliteQuery<'q> {
+    values.iter().fold(query, bind_value)
+}
+
+/// Index just past the next occurrence of `pattern` at or after `from`, or
+/// the end of the input when it never closes.
+fn skip_past(bytes: &[u8], from: usize, pattern: &[u8]) -> usize {
+    bytes[from.min(bytes.len())..]
+        .windows(pattern.len())
+        .position(|window| window == pattern)
+        .map_or(bytes.len(), |found| from + found + pattern.len())
+}
+
+/// Rewrites `:name` and `$name` placeholders to numbered `?N` ones, the only
+/// named form sqlx binds, and returns the values in slot order. A repeated
+/// name reuses its slot; literals, quoted identifiers and comments are left
+/// untouched.
+fn expand_named_params(query: &str, named: &Map<String, Value>) -> AppResult<(String, Vec<Value>)> {
+    let bytes = query.as_bytes();
+    let mut sql = String::with_capacity(query.len());
+    let mut slots: Vec<&str> = Vec::new();
+    let mut values = Vec::new();
+    let mut copied = 0;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let next = bytes.get(index + 1).copied();
+        index = match bytes[index] {
+            quote @ (b'\'' | b'"' | b'`') => skip_past(bytes, index + 1, &[quote]),
+            b'[' => skip_past(bytes, index + 1, b"]"),
+            b'-' if next == Some(b'-') => skip_past(bytes, index + 2, b"\n"),
+            b'/' if next == Some(b'*') => skip_past(bytes, index + 2, b"*/"),
+            b'?' => {
+                return Err(AppError::Validation(
+                    "Named parameters cannot be mixed with `?` placeholders".into(),
+                ))
+            }
+            b':' | b'$' => {
+                let start = index + 1;
+                let end = bytes[start..]
+                    .iter()
+                    .position(|byte| !(byte.is_ascii_alphanumeric() || *byte == b'_'))
+                    .map_or(bytes.len(), |offset| start + offset);
+                if end == start {
+                    index + 1
+                } else {
+                    let name = &query[start..end];
+                    let slot = match slots.iter().position(|known| *known == name) {
+                        Some(existing) => existing + 1,
+                        None => {
+                            let value = named.get(name).ok_or_else(|| {
+                                AppError::Validation(format!("Missing value for parameter {name}"))
+                            })?;
+                            slots.push(name);
+                            values.push(value.clone());
+                            slots.len()
+                        }
+                    };
+                    sql.push_str(&query[copied..index]);
+                    sql.push_str(&format!("?{slot}"));
+                    copied = end;
+                    end
+                }
+            }
+            _ => index + 1,
+        };
+    }
+
+    sql.push_str(&query[copied..]);
+    Ok((sql, values))
+}
+
+/// Chooses the binding style for `db_query`. Positional `values` and
+/// `named` parameters are mutually exclusive: when both are non-empty the
+/// call fails instead of one silently winning.
+fn resolve_params(
+    query: String,
+    values: Option<Vec<Value>>,
+    named: Option<Map<String, Value>>,
+) -> AppResult<(String, Vec<Value>)> {
+    let values = values.unwrap_or_default();
+    match named.filter(|named| !named.is_empty()) {
+        None => Ok((query, values)),
+        Some(_) if !values.is_empty() => Err(AppError::Validation(
+            "Pass either positional values or named parameters, not both".into(),
+        )),
+        Some(named) => expand_named_params(&query, &named),
+    }
+}
+
+fn column_value(row: &SqliteRow, index: usize) -> Result<Value, sqlx::Error> {
+    let raw = row.try_get_raw(index)?;
+    if raw.is_null() {
+        return Ok(Value::Null);
+    }
+
+    let storage = raw.type_info().name().to_string();
+    let value = match storage.as_str() {
+        "INTEGER" => Value::from(row.try_get::<i64, _>(index)?),
+        "REAL" => Value::from(row.try_get::<f64, _>(index)?),
+        "BLOB" => Value::from(row.try_get::<Vec<u8>, _>(index)?),
+        _ => Value::String(row.try_get::<String, _>(index)?),
+    };
+    Ok(value)
+}
+
+fn row_to_json(row: &SqliteRow) -> Result<Value, sqlx::Error> {
+    let mut object = Map::new();
+    for column in row.columns() {
+        object.insert(
+            column.name().to_string(),
+            column_value(row, column.ordinal())?,
+        );
+    }
+    Ok(Value::Object(object))
+}
+
+/// Runs a row-producing `query` and returns each row as a JSON object keyed
+/// by column name.
+pub(crate) async fn fetch_rows(
+    pool: &SqlitePool,
+    query: &str,
+    values: &[Value],
+) -> Result<Vec<Value>, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    fetch_rows_on(&mut conn, query, values).await
+}
+
+/// [`fetch_rows`] on a specific connection, such as an open transaction.
+pub(crate) async fn fetch_rows_on(
+    conn: &mut SqliteConnection,
+    query: &str,
+    values: &[Value],
+) -> Result<Vec<Value>, sqlx::Error> {
+    let rows = bind_values(sqlx::query(query), values)
+        .fetch_all(&mut *conn)
+        .await?;
+    rows.iter().map(row_to_json).collect()
+}
+
+/// Column names and declared types taken from the prepared statement, so
+/// they are available even when the result set is empty. Expression columns
+/// have no declared type and report `None`.
+async fn describe_columns(
+    conn: &mut SqliteConnection,
+    query: &str,
+) -> Result<Vec<ColumnInfo>, sqlx::Error> {
+    let prepared = conn.prepare(query).await?;
+    let columns = prepared
+        .columns()
+        .iter()
+        .map(|column| {
+            let decl_type = column.type_info().name();
+            ColumnInfo {
+                name: column.name().to_string(),
+                decl_type: (decl_type != "NULL").then(|| decl_type.to_string()),
+            }
+        })
+        .collect();
+    Ok(columns)
+}
+
+/// Runs `query` with positional `values`, returning the columns and row
+/// objects for result-producing statements and the affected-row summary for
+/// everything else.
+pub(crate) async fn run_query(
+    pool: &SqlitePool,
+    query: &str,
+    values: &[Value],
+) -> Result<QueryResult, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    run_query_on(&mut conn, query, values).await
+}
+
+/// [`run_query`] on a specific connection, such as an open transaction.
+pub(crate) async fn run_query_on(
+    conn: &mut SqliteConnection,
+    query: &str,
+    values: &[Value],
+) -> Result<QueryResult, sqlx::Error> {
+    if returns_rows(query) {
+        let columns = describe_columns(conn, query).await?;
+        let rows = bind_values(sqlx::query(query), values)
+            .fetch_all(&mut *conn)
+            .await?
+            .iter()
+            .map(row_to_json)
+            .collect::<Result<_, _>>()?;
+        Ok(QueryResult::Select { columns, rows })
+    } else {
+        let result = bind_values(sqlx::query(query), values)
+            .execute(&mut *conn)
+            .await?;
+        Ok(QueryResult::Execute {
+            rows_affected: result.rows_affected(),
+            last_insert_id: result.last_insert_rowid(),
+        })
+    }
+}
+
+/// Executes every statement inside one transaction. The first failure rolls
+/// the whole batch back and reports the zero-based index of the offending
+/// statement.
+pub(crate) async fn run_batch(
+    pool: &SqlitePool,
+    statements: &[(String, Option<Vec<Value>>)],
+) -> AppResult<BatchResult> {
+    let mut tx = pool.begin().await?;
+    let mut rows_affected = 0;
+
+    for (index, (query, values)) in statements.iter().enumerate() {
+        let statement = bind_values(sqlx::query(query), values.as_deref().unwrap_or_default());
+        let result = statement
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| AppError::Db(format!("Statement {index} failed: {err}")))?;
+        rows_affected += result.rows_affected();
+    }
+
+    tx.commit().await?;
+
+    Ok(BatchResult {
+        statements: statements.len(),
+        rows_affected,
+        failures: Vec::new(),
+    })
+}
+
+/// Like [`run_batch`], but each statement runs in its own savepoint, so a
+/// failing one is rolled back on its own and recorded while the rest commit.
+pub(crate) async fn run_batch_partial(
+    pool: &SqlitePool,
+    statements: &[(String, Option<Vec<Value>>)],
+) -> AppResult<BatchResult> {
+    let mut tx = pool.begin().await?;
+    let mut rows_affected = 0;
+    let mut failures = Vec::new();
+
+    for (index, (query, values)) in statements.iter().enumerate() {
+        let mut savepoint = Connection::begin(&mut *tx).await?;
+        let statement = bind_values(sqlx::query(query), values.as_deref().unwrap_or_default());
+        match statement.execute(&mut *savepoint).await {
+            Ok(result) => {
+                savepoint.commit().await?;
+                rows_affected += result.rows_affected();
+            }
+            Err(err) => {
+                savepoint.rollback().await?;
+                failures.push(BatchFailure {
+                    index,
+                    error: err.to_string(),
+                });
+            }
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(BatchResult {
+        statements: statements.len(),
+        rows_affected,
+        failures,
+    })
+}
+
+/// Walks the rows of `query` with a cursor, handing pages of up to
+/// `page_size` rows to `on_page` so only one page is held in memory.
+/// `cancelled` is checked between pages. Returns the number of rows sent and
+/// whether the query ran to the end.
+pub(crate) async fn stream_rows(
+    pool: &SqlitePool,
+    query: &str,
+    values: &[Value],
+    page_size: usize,
+    cancelled: &AtomicBool,
+    mut on_page: impl FnMut(Vec<Value>),
+) -> AppResult<(u64, bool)> {
+    if !returns_rows(query) {
+        return Err(AppError::Validation(
+            "Only row-returning queries can be streamed".into(),
+        ));
+    }
+
+    let mut rows = bind_values(sqlx::query(query), values).fetch(pool);
+    let mut page = Vec::with_capacity(page_size);
+    let mut total = 0u64;
+    while let Some(row) = rows.try_next().await? {
+        page.push(row_to_json(&row)?);
+        if page.len() >= page_size {
+            total += page.len() as u64;
+            on_page(std::mem::replace(&mut page, Vec::with_capacity(page_size)));
+            if cancelled.load(Ordering::SeqCst) {
+                return Ok((total, false));
+            }
+        }
+    }
+
+    if !page.is_empty() {
+        total += page.len() as u64;
+        on_page(page);
+    }
+    Ok((total, true))
+}
+
+impl QueryStreamState {
+    /// Flags every in-flight query stream for cancellation and returns their
+    /// ids.
+    pub(crate) fn cancel_all(&self) -> Vec<String> {
+        let Ok(streams) = self.streams.lock() else {
+            return Vec::new();
+        };
+        for flag in streams.values() {
+            flag.store(true, Ordering::SeqCst);
+        }
+        streams.keys().cloned().collect()
+    }
+}
+
+fn emit_query_stream(window: &Window, stream_id: &str, payload: QueryStreamPayload) {
+    let event = QueryStreamEvent { stream_id, payload };
+    let _ = window.emit(QUERY_STREAM_EVENT, &event);
+}
+
+/// Resolves symlinks and `..` so two spellings of the same file compare
+/// equal. The file itself may not exist yet, so only its parent is resolved.
+fn normalized_path(path: &Path) -> PathBuf {
+    if let Ok(resolved) = path.canonicalize() {
+        return resolved;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent
+            .canonicalize()
+            .map(|dir| dir.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Writes a consistent snapshot of the database behind `pool` to
+/// `destination` with `VACUUM INTO`, which reads inside a transaction and so
+/// is safe while the app keeps writing. The copy lands in a sibling temp file
+/// first and is renamed into place, so an existing file at `destination` is
+/// only replaced once the snapshot is complete.
+pub(crate) async fn backup_database(
+    pool: &SqlitePool,
+    live_path: &Path,
+    destination: &Path,
+) -> AppResult<u64> {
+    if normalized_path(destination) == normalized_path(live_path) {
+        return Err(AppError::Validation(
+            "Backup destination cannot be the live database".into(),
+        ));
+    }
+
+    let mut partial = destination.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    if partial.exists() {
+        std::fs::remove_file(&partial)?;
+    }
+
+    let snapshot = sqlx::query("VACUUM INTO ?")
+        .bind(partial.to_string_lossy().into_owned())
+        .execute(pool)
+        .await;
+    if let Err(err) = snapshot {
+        let _ = std::fs::remove_file(&partial);
+        return Err(err.into());
+    }
+
+    std::fs::rename(&partial, destination).map_err(|err| {
+        let _ = std::fs::remove_file(&partial);
+        AppError::from(err)
+    })?;
+    Ok(std::fs::metadata(destination)?.len())
+}
+
+/// Checks the SQLite header magic, then opens `source` read-only and runs
+/// `PRAGMA integrity_check`, so a truncated or foreign file is rejected
+/// before anything touches the live database.
+pub(crate) async fn validate_database(source: &Path) -> AppResult<()> {
+    let mut header = [0u8; 16];
+    std::fs::File::open(source)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .map_err(|_| {
+            AppError::Validation(format!("{} is not a SQLite database", source.display()))
+        })?;
+    if &header != SQLITE_HEADER {
+        return Err(AppError::Validation(format!(
+            "{} is not a SQLite database",
+            source.display()
+        )));
+    }
+
+    let options = SqliteConnectOptions::new().filename(source).read_only(true);
+    let pool = SqlitePool::connect_with(options).await?;
+    let report: Result<Vec<String>, _> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(&pool)
+        .await;
+    pool.close().await;
+
+    let report = report?;
+    if report.len() == 1 && report[0] == "ok" {
+        Ok(())
+    } else {
+        Err(AppError::Validation(format!(
+            "Integrity check failed: {}",
+            report.join("; ")
+        )))
+    }
+}
+
+async fn foreign_key_violations<'e>(
+    executor: impl SqliteExecutor<'e>,
+) -> Result<Vec<ForeignKeyViolation>, sqlx::Error> {
+    sqlx::query("PRAGMA foreign_key_check")
+        .fetch_all(executor)
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(ForeignKeyViolation {
+                table: row.try_get(0)?,
+                rowid: row.try_get(1)?,
+                parent: row.try_get(2)?,
+                constraint_index: row.try_get(3)?,
+            })
+        })
+        .collect()
+}
+
+/// Runs the integrity and foreign key checks and gathers the storage
+/// figures a bug report needs. Problems are reported, not returned as errors.
+pub(crate) async fn health_check(pool: &SqlitePool) -> AppResult<HealthReport> {
+    let integrity: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(pool)
+        .await?;
+    let integrity_errors = match integrity.as_slice() {
+        [only] if only == "ok" => Vec::new(),
+        _ => integrity,
+    };
+
+    let foreign_key_violations = foreign_key_violations(pool).await?;
+
+    let page_size: i64 = sqlx::query_scalar("PRAGMA page_size")
+        .fetch_one(pool)
+        .await?;
+    let page_count: i64 = sqlx::query_scalar("PRAGMA page_count")
+        .fetch_one(pool)
+        .await?;
+    let free_pages: i64 = sqlx::query_scalar("PRAGMA freelist_count")
+        .fetch_one(pool)
+        .await?;
+    let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode")
+        .fetch_one(pool)
+        .await?;
+
+    Ok(HealthReport {
+        ok: integrity_errors.is_empty() && foreign_key_violations.is_empty(),
+        integrity_errors,
+        foreign_key_violations,
+        page_size,
+        page_count,
+        free_pages,
+        wal_enabled: journal_mode.eq_ignore_ascii_case("wal"),
+        journal_mode,
+    })
+}
+
+/// Deleting an orphan while foreign keys are off can orphan the rows under
+/// it in turn; this many sweeps are made before giving up on the rest.
+const MAX_ORPHAN_PASSES: usize = 16;
+
+fn quoted_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Fixes one orphan as its constraint's `ON DELETE` says. Returns `None`
+/// when the row is already gone, e.g. deleted for an earlier violation.
+async fn repair_orphan(
+    conn: &mut SqliteConnection,
+    table: &str,
+    rowid: i64,
+    parent: &str,
+    constraint_index: i64,
+) -> Result<Option<OrphanRepair>, sqlx::Error> {
+    let keys: Vec<(String, String)> = sqlx::query_as(
+        "SELECT \"from\", on_delete FROM pragma_foreign_key_list(?) WHERE id = ? ORDER BY seq",
+    )
+    .bind(table)
+    .bind(constraint_index)
+    .fetch_all(&mut *conn)
+    .await?;
+    let columns: Vec<String> = keys.iter().map(|(column, _)| column.clone()).collect();
+    let set_null = keys
+        .first()
+        .is_some_and(|(_, on_delete)| on_delete.eq_ignore_ascii_case("SET NULL"));
+    let mut nullable = true;
+    for column in &columns {
+        let notnull: Option<bool> =
+            sqlx::query_scalar("SELECT \"notnull\" FROM pragma_table_info(?) WHERE name = ?")
+                .bind(table)
+                .bind(column)
+                .fetch_optional(&mut *conn)
+                .await?;
+        nullable &= notnull == Some(false);
+    }
+    let action = if set_null && nullable && !columns.is_empty() {
+        OrphanAction::Detach
+    } else {
+        OrphanAction::Delete
+    };
+
+    let table_ident = quoted_ident(table);
+    let missing_id: Option<String> = match columns.first() {
+        Some(column) => sqlx::query_scalar::<_, Option<String>>(&format!(
+            "SELECT CAST({} AS TEXT) FROM {table_ident} WHERE rowid = ?",
+            quoted_ident(column)
+        ))
+        .bind(rowid)
+        .fetch_optional(&mut *conn)
+        .await?
+        .flatten(),
+        None => None,
+    };
+
+    let statement = match action {
+        OrphanAction::Delete => format!("DELETE FROM {table_ident} WHERE rowid = ?"),
+        OrphanAction::Detach => {
+            let cleared: Vec<String> = columns
+                .iter()
+                .map(|column| format!("{} = NULL", quoted_ident(column)))
+                .collect();
+            format!(
+                "UPDATE {table_ident} SET {} WHERE rowid = ?",
+                cleared.join(", ")
+            )
+        }
+    };
+    let affected = sqlx::query(&statement)
+        .bind(rowid)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+    Ok((affected > 0).then(|| OrphanRepair {
+        table: table.to_string(),
+        rowid,
+        columns,
+        parent: parent.to_string(),
+        missing_id,
+        action,
+    }))
+}
+
+/// Finds the rows `PRAGMA foreign_key_check` flags and fixes them in one
+/// transaction, following each constraint's `ON DELETE`. With `dry_run`
+/// the transaction is rolled back, so the report shows what a real run
+/// would do without changing anything. Rows removed by a cascade from a
+/// deleted orphan are not listed separately.
+pub(crate) async fn repair_orphans_in(pool: &SqlitePool, dry_run: bool) -> AppResult<OrphanReport> {
+    let mut tx = pool.begin().await?;
+    let mut repairs = Vec::new();
+    let mut unrepaired = Vec::new();
+    for _ in 0..MAX_ORPHAN_PASSES {
+        let (mut repairable, rest): (Vec<_>, Vec<_>) = foreign_key_violations(&mut *tx)
+            .await?
+            .into_iter()
+            .partition(|violation| violation.rowid.is_some());
+        unrepaired = rest;
+        if repairable.is_empty() {
+            break;
+        }
+        repairable.sort_by(|a, b| (&a.table, a.rowid).cmp(&(&b.table, b.rowid)));
+        for violation in repairable {
+            let Some(rowid) = violation.rowid else {
+                continue;
+            };
+            let repair = repair_orphan(
+                &mut tx,
+                &violation.table,
+                rowid,
+                &violation.parent,
+                violation.constraint_index,
+            )
+            .await?;
+            repairs.extend(repair);
+        }
+    }
+
+    if dry_run {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+    }
+    Ok(OrphanReport {
+        dry_run,
+        repairs,
+        unrepaired,
+    })
+}
+
+/// The query plan for `query` without running it. The plan is read inside
+/// a transaction that is always rolled back, so even a mutation changes
+/// nothing. SQL that does not prepare comes back as a validation error with
+/// SQLite's message, e.g. `near "SELEC": syntax error`.
+pub(crate) async fn explain_query(
+    pool: &SqlitePool,
+    query: &str,
+    values: &[Value],
+) -> AppResult<Vec<PlanStep>> {
+    if leading_keyword(query) == "EXPLAIN" {
+        return Err(AppError::Validation(
+            "Pass the statement itself, without EXPLAIN".into(),
+        ));
+    }
+    let explain = format!("EXPLAIN QUERY PLAN {query}");
+    let mut tx = pool.begin().await?;
+    let rows = bind_values(sqlx::query(&explain), values)
+        .fetch_all(&mut *tx)
+        .await;
+    tx.rollback().await?;
+
+    let rows = rows.map_err(|err| match err {
+        sqlx::Error::Database(err) => {
+            AppError::Validation(format!("Invalid SQL: {}", err.message()))
+        }
+        other => AppError::from(other),
+    })?;
+    rows.iter()
+        .map(|row| {
+            Ok(PlanStep {
+                id: row.try_get("id")?,
+                parent: row.try_get("parent")?,
+                detail: row.try_get("detail")?,
+            })
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()
+        .map_err(AppError::from)
+}
+
+/// Tables SQLite creates to back an FTS3/4/5 virtual table, by suffix.
+const FTS_SHADOW_SUFFIXES: &[&str] = &[
+    "_config",
+    "_content",
+    "_data",
+    "_docsize",
+    "_idx",
+    "_segdir",
+    "_segments",
+    "_stat",
+];
+
+/// User tables and their columns, without SQLite's own tables or the
+/// shadow tables behind full-text indexes.
+pub(crate) async fn read_schema(pool: &SqlitePool) -> AppResult<Vec<TableSchema>> {
+    let tables: Vec<(String, Option<String>)> = sqlx::query_as(
+        "SELECT name, sql FROM sqlite_master
+         WHERE type = 'table' AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\'
+         ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await?;
+    let virtual_tables: Vec<&str> = tables
+        .iter()
+        .filter(|(_, sql)| {
+            sql.as_deref().is_some_and(|sql| {
+                sql.to_ascii_uppercase()
+                    .split_whitespace()
+                    .take(2)
+                    .eq(["CREATE", "VIRTUAL"])
+            })
+        })
+        .map(|(name, _)| name.as_str())
+        .collect();
+    let is_shadow = |name: &str| {
+        virtual_tables.iter().any(|owner| {
+            name.strip_prefix(owner)
+                .is_some_and(|suffix| FTS_SHADOW_SUFFIXES.contains(&suffix))
+        })
+    };
+
+    let mut schema = Vec::new();
+    for (table, _) in tables.iter().filter(|(name, _)| !is_shadow(name)) {
+        let columns = sqlx::query(
+            "SELECT name, type, \"notnull\", pk FROM pragma_table_info(?) ORDER BY cid",
+        )
+        .bind(table)
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(SchemaColumn {
+                name: row.try_get(0)?,
+                declared_type: row.try_get(1)?,
+                notnull: row.try_get(2)?,
+                pk: row.try_get(3)?,
+            })
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()?;
+        schema.push(TableSchema {
+            table: table.clone(),
+            columns,
+        });
+    }
+    Ok(schema)
+}
+
+/// Size of the database file plus its write-ahead log, which can hold most
+/// of the data between checkpoints.
+fn database_bytes(path: &Path) -> u64 {
+    let mut wal = path.as_os_str().to_owned();
+    wal.push("-wal");
+    [path.to_path_buf(), PathBuf::from(wal)]
+        .iter()
+        .filter_map(|file| std::fs::metadata(file).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Moves every committed page out of the WAL into the database file and
+/// truncates the WAL.
+pub(crate) async fn checkpoint(pool: &SqlitePool) -> AppResult<()> {
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Runs `PRAGMA optimize` and, with `vacuum`, rebuilds the file to drop free
+/// pages. The checkpoint afterwards moves the rebuilt pages out of the WAL so
+/// the file on disk actually shrinks.
+pub(crate) async fn optimize_database(
+    pool: &SqlitePool,
+    live_path: &Path,
+    vacuum: bool,
+) -> AppResult<OptimizeResult> {
+    let bytes_before = database_bytes(live_path);
+    sqlx::query("PRAGMA optimize").execute(pool).await?;
+    if vacuum {
+        sqlx::query("VACUUM").execute(pool).await?;
+        checkpoint(pool).await?;
+    }
+    Ok(OptimizeResult {
+        vacuumed: vacuum,
+        bytes_before,
+        bytes_after: database_bytes(live_path),
+    })
+}
+
+/// Copies `source` over `live_path` via a sibling temp file and a rename, and
+/// drops the old write-ahead log so its frames are not replayed onto the
+/// restored file. Callers must have closed every connection to `live_path`.
+fn replace_database_file(source: &Path, live_path: &Path) -> AppResult<()> {
+    let mut partial = live_path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    std::fs::copy(source, &partial)?;
+    for suffix in ["-wal", "-shm"] {
+        let mut sidecar = live_path.as_os_str().to_owned();
+        sidecar.push(suffix);
+        let _ = std::fs::remove_file(PathBuf::from(sidecar));
+    }
+    std::fs::rename(&partial, live_path).map_err(|err| {
+        let _ = std::fs::remove_file(&partial);
+        AppError::from(err)
+    })
+}
+
+/// Binds either positional `values` to `?` placeholders or `named`
+/// parameters to `:name`/`$name` ones, never both. With a `tx_id` from
+/// `db_begin` the query runs inside that transaction.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn db_query(
+    app: AppHandle,
+    transactions: State<'_, TransactionState>,
+    query: String,
+    values: Option<Vec<Value>>,
+    named: Option<Map<String, Value>>,
+    tx_id: Option<String>,
+) -> AppResult<QueryResult> {
+    let (query, values) = resolve_params(query, values, named)?;
+    if let Some(tx_id) = tx_id {
+        return transactions.run(&tx_id, &query, &values).await;
+    }
+    let pool = pool(&app).await?;
+    Ok(run_query(&pool, &query, &values).await?)
+}
+
+/// Runs `statements` in one transaction. By default the first failure rolls
+/// everything back; with `continue_on_error` failing statements are skipped
+/// and listed in `failures` while the rest are committed.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn db_execute_batch(
+    app: AppHandle,
+    statements: Vec<(String, Option<Vec<Value>>)>,
+    continue_on_error: Option<bool>,
+) -> AppResult<BatchResult> {
+    let pool = pool(&app).await?;
+    if continue_on_error.unwrap_or(false) {
+        run_batch_partial(&pool, &statements).await
+    } else {
+        run_batch(&pool, &statements).await
+    }
+}
+
+/// Streams the rows of `query` on `db://query-stream` in pages of
+/// `page_size`, starting with the column list and finishing with a
+/// `complete`, `cancelled` or `error` event that carries the row total.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn db_query_stream(
+    window: Window,
+    state: State<'_, QueryStreamState>,
+    stream_id: String,
+    query: String,
+    values: Option<Vec<Value>>,
+    page_size: Option<usize>,
+) -> AppResult<()> {
+    let page_size = page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let pool = pool(window.app_handle()).await?;
+
+    let cancelled = Arc::new(AtomicBool::new(false));
+    {
+        let mut streams = state
+            .streams
+            .lock()
+            .map_err(|_| AppError::state("query stream"))?;
+        if streams.contains_key(&stream_id) {
+            return Err(AppError::AlreadyRunning(format!(
+                "{stream_id} is already streaming"
+            )));
+        }
+        streams.insert(stream_id.clone(), Arc::clone(&cancelled));
+    }
+
+    let values = values.unwrap_or_default();
+    let columns = match pool.acquire().await {
+        Ok(mut conn) => describe_columns(&mut conn, &query).await,
+        Err(err) => Err(err),
+    };
+    let outcome = match columns {
+        Ok(columns) => {
+            emit_query_stream(&window, &stream_id, QueryStreamPayload::Columns { columns });
+            stream_rows(&pool, &query, &values, page_size, &cancelled, |rows| {
+                emit_query_stream(&window, &stream_id, QueryStreamPayload::Rows { rows });
+            })
+            .await
+        }
+        Err(err) => Err(err.into()),
+    };
+
+    if let Ok(mut streams) = state.streams.lock() {
+        streams.remove(&stream_id);
+    }
+
+    match outcome {
+        Ok((total, true)) => {
+            emit_query_stream(&window, &stream_id, QueryStreamPayload::Complete { total });
+            Ok(())
+        }
+        Ok((total, false)) => {
+            emit_query_stream(&window, &stream_id, QueryStreamPayload::Cancelled { total });
+            Ok(())
+        }
+        Err(error) => {
+            emit_query_stream(
+                &window,
+                &stream_id,
+                QueryStreamPayload::Error {
+                    message: error.to_string(),
+                },
+            );
+            Err(error)
+        }
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn cancel_db_query_stream(
+    state: State<'_, QueryStreamState>,
+    stream_id: String,
+) -> AppResult<()> {
+    let streams = state
+        .streams
+        .lock()
+        .map_err(|_| AppError::state("query stream"))?;
+    if let Some(flag) = streams.get(&stream_id) {
+        flag.store(true, Ordering::SeqCst);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn db_backup(window: Window, destination: String) -> AppResult<BackupResult> {
+    let app = window.app_handle();
+    let pool = pool(app).await?;
+    let live_path = db_path(app)?;
+    let bytes = backup_database(&pool, &live_path, Path::new(&destination)).await?;
+
+    let result = BackupResult {
+        path: destination,
+        bytes,
+    };
+    let _ = window.emit(DB_BACKUP_EVENT, &result);
+    Ok(result)
+}
+
+/// Effective journal mode, synchronous level and foreign key enforcement.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn db_pragmas(app: AppHandle) -> AppResult<DbPragmas> {
+    let pool = pool(&app).await?;
+    read_pragmas(&pool).await
+}
+
+/// Refreshes query planner statistics and, when `vacuum` is set, reclaims
+/// the space left by deletes. VACUUM needs the database to itself, so it is
+/// refused while a `db_begin` transaction is open.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn db_optimize(
+    app: AppHandle,
+    transactions: State<'_, TransactionState>,
+    vacuum: Option<bool>,
+) -> AppResult<OptimizeResult> {
+    let vacuum = vacuum.unwrap_or(false);
+    let _exclusive = if vacuum {
+        Some(transactions.exclusive().await?)
+    } else {
+        None
+    };
+    let pool = pool(&app).await?;
+    optimize_database(&pool, &db_path(&app)?, vacuum).await
+}
+
+/// Validates `query` and returns its plan without executing it. Parameters
+/// are passed the same way as for `db_query`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn db_explain(
+    app: AppHandle,
+    query: String,
+    values: Option<Vec<Value>>,
+    named: Option<Map<String, Value>>,
+) -> AppResult<Vec<PlanStep>> {
+    let (query, values) = resolve_params(query, values, named)?;
+    let pool = pool(&app).await?;
+    explain_query(&pool, &query, &values).await
+}
+
+/// Tables and columns of the live database, for query building and
+/// autocomplete.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn db_schema(app: AppHandle) -> AppResult<Vec<TableSchema>> {
+    let pool = pool(&app).await?;
+    read_schema(&pool).await
+}
+
+/// Integrity, foreign key and storage report for the live database, for
+/// the diagnostics panel and bug reports.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn db_health_check(app: AppHandle) -> AppResult<HealthReport> {
+    let pool = pool(&app).await?;
+    health_check(&pool).await
+}
+
+/// The repair behind `audit_chain`'s unlinked-row findings: deletes or
+/// detaches rows whose parent is missing, or with `dry_run` only reports
+/// what it would do.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn repair_orphans(app: AppHandle, dry_run: bool) -> AppResult<OrphanReport> {
+    let pool = pool(&app).await?;
+    repair_orphans_in(&pool, dry_run).await
+}
+
+/// Replaces the live database with `source` after validating it and
+/// snapshotting the current data into `backups/`. Returns the snapshot path
+/// so the restore can be undone by restoring that file. Refuses while a
+/// `db_begin` transaction is open, since closing the pool would wait on it.
+pub(crate) async fn restore_from(app: &AppHandle, source: &Path) -> AppResult<PathBuf> {
+    let transactions = app.state::<TransactionState>();
+    let _exclusive = transactions.exclusive().await?;
+    let live_path = db_path(app)?;
+    if normalized_path(source) == normalized_path(&live_path) {
+        return Err(AppError::Validation(
+            "Cannot restore the live database onto itself".into(),
+        ));
+    }
+    validate_database(source).await?;
+
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+    let backup_dir = live_path
+        .parent()
+        .map(|dir| dir.join("backups"))
+        .ok_or_else(|| AppError::Internal("Unable to resolve the backup directory".into()))?;
+    std::fs::create_dir_all(&backup_dir)?;
+    let safety_path = backup_dir.join(format!("app-before-restore-{stamp}.db"));
+    backup_database(&pool(app).await?, &live_path, &safety_path).await?;
+
+    // Holding the write lock keeps the frontend and other commands from
+    // reopening the file while it is being swapped.
+    let instances = app.state::<DbInstances>();
+    let mut guard = instances.0.write().await;
+    if let Some(DbPool::Sqlite(live)) = guard.remove(DB_URL) {
+        live.close().await;
+    }
+    let replaced = replace_database_file(source, &live_path);
+    let reopened = open_pool(&live_path).await?;
+    guard.insert(DB_URL.to_string(), DbPool::Sqlite(reopened));
+    replaced?;
+
+    Ok(safety_path)
+}
+
+/// Restores the live database from `source_path`; see [`restore_from`].
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn db_restore(app: AppHandle, source_path: String) -> AppResult<String> {
+    let safety_path = restore_from(&app, Path::new(&source_path)).await?;
+    Ok(safety_path.to_string_lossy().into_owned())
+}
+
+/// A single-connection in-memory database for tests, so every query sees the
+/// same schema.
+#[cfg(test)]
+pub(crate) async fn memory_pool() -> SqlitePool {
+    sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("in-memory database")
+}
+
+/// A migrated in-memory database holding one character, Kaede (`hero`),
+/// and one jump, Naruto (`j1`), with a perk `a1` and a drawback `a2`.
+#[cfg(test)]
+pub(crate) async fn seeded_chain_pool() -> SqlitePool {
+    use crate::migrations::{apply_migrations, MIGRATIONS};
+
+    let pool = memory_pool().await;
+    apply_migrations(&pool, MIGRATIONS).await.unwrap();
+    run_batch(
+        &pool,
+        &[
+            (
+                "INSERT INTO character_profiles (id, name) VALUES ('hero', 'Kaede')".into(),
+                None,
+            ),
+            (
+                "INSERT INTO jumps (id, title, sort_order) VALUES ('j1', 'Naruto', 0)".into(),
+                None,
+            ),
+            (
+                "INSERT INTO jump_assets (id, jump_id, asset_type, name, cost)
+                 VALUES ('a1', 'j1', 'perk', 'Shadow Clone', 400),
+                        ('a2', 'j1', 'drawback', 'Rival', -200)"
+                    .into(),
+                None,
+            ),
+        ],
+    )
+    .await
+    .unwrap();
+    pool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    async fn query_json(pool: &SqlitePool, query: &str, values: &[Value]) -> Value {
+        let result = run_query(pool, query, values).await.unwrap();
+        serde_json::to_value(result).unwrap()
+    }
+
+    #[test]
+    fn leading_keyword_skips_comments() {
+        assert_eq!(leading_keyword("  select 1"), "SELECT");
+        assert_eq!(
+            leading_keyword("-- note\n/* block */ INSERT INTO t"),
+            "INSERT"
+        );
+        assert!(returns_rows("DELETE FROM perks WHERE id = ? RETURNING id"));
+        assert!(!returns_rows("UPDATE jumps SET title = ?"));
+    }
+
+    #[test]
+    fn returning_inside_literals_and_comments_is_ignored() {
+        assert!(!returns_rows(
+            "INSERT INTO notes (body) VALUES ('returning soon')"
+        ));
+        assert!(!returns_rows(
+            "INSERT INTO \"returning\" (a) VALUES (1) -- returning\n/* RETURNING */"
+        ));
+        assert!(!returns_rows(
+            "UPDATE [returning] SET `returning` = 'it''s returning'"
+        ));
+        assert!(returns_rows(
+            "INSERT INTO notes (body) VALUES ('a -- b') RETURNING id"
+        ));
+        assert_eq!(without_literals("SELECT 'x' -- c\n"), "SELECT    ");
+    }
+
+    #[tokio::test]
+    async fn round_trips_bound_parameters() {
+        let pool = memory_pool().await;
+        run_query(
+            &pool,
+            "CREATE TABLE perks (id INTEGER PRIMARY KEY, name TEXT, cost INTEGER, weight REAL, notes TEXT)",
+            &[],
+        )
+        .await
+        .unwrap();
+
+        let inserted = query_json(
+            &pool,
+            "INSERT INTO perks (name, cost, weight, notes) VALUES (?, ?, ?, ?)",
+            &[json!("Fire Ball"), json!(400), json!(1.5), Value::Null],
+        )
+        .await;
+        assert_eq!(
+            inserted,
+            json!({ "kind": "execute", "rows_affected": 1, "last_insert_id": 1 })
+        );
+
+        let result = query_json(&pool, "SELECT * FROM perks WHERE cost = ?", &[json!(400)]).await;
+        assert_eq!(result["kind"], "select");
+        assert_eq!(
+            result["rows"],
+            json!([{ "id": 1, "name": "Fire Ball", "cost": 400, "weight": 1.5, "notes": null }])
+        );
+    }
+
+    #[tokio::test]
+    async fn binds_named_parameters() {
+        let pool = memory_pool().await;
+        run_query(
+            &pool,
+            "CREATE TABLE perks (id INTEGER PRIMARY KEY, name TEXT, cost INTEGER, notes TEXT)",
+            &[],
+        )
+        .await
+        .unwrap();
+
+        let named = json!({ "name": "Fire Ball", "cost": 400, "notes": null });
+        let Value::Object(named) = named else {
+            unreachable!()
+        };
+        let (query, values) = resolve_params(
+            "INSERT INTO perks (name, cost, notes) VALUES (:name, $cost, :notes)".into(),
+            None,
+            Some(named.clone()),
+        )
+        .unwrap();
+        assert_eq!(
+            query,
+            "INSERT INTO perks (name, cost, notes) VALUES (?1, ?2, ?3)"
+        );
+        run_query(&pool, &query, &values).await.unwrap();
+
+        let (query, values) = resolve_params(
+            "SELECT id, ':name' AS label FROM perks \
+             WHERE name = :name AND cost = :cost AND notes IS :notes AND :cost > 0"
+                .into(),
+            Some(Vec::new()),
+            Some(named.clone()),
+        )
+        .unwrap();
+        assert_eq!(values.len(), 3);
+        let result = query_json(&pool, &query, &values).await;
+        assert_eq!(result["rows"], json!([{ "id": 1, "label": ":name" }]));
+
+        let both = resolve_params("SELECT :name".into(), Some(vec![json!(1)]), Some(named));
+        assert_eq!(both.unwrap_err().code(), "validation");
+        let missing = expand_named_params("SELECT :other", &Map::new()).unwrap_err();
+        assert_eq!(missing.message(), "Missing value for parameter other");
+    }
+
+    #[tokio::test]
+    async fn empty_select_still_reports_columns() {
+        let pool = memory_pool().await;
+        run_query(
+            &pool,
+            "CREATE TABLE jumps (id TEXT PRIMARY KEY, cp_budget INTEGER)",
+            &[],
+        )
+        .await
+        .unwrap();
+
+        let result = query_json(&pool, "SELECT id, cp_budget, 1 + 1 AS two FROM jumps", &[]).await;
+        assert_eq!(
+            result,
+            json!({
+                "kind": "select",
+                "columns": [
+                    { "name": "id", "decl_type": "TEXT" },
+                    { "name": "cp_budget", "decl_type": "INTEGER" },
+                    { "name": "two", "decl_type": null },
+                ],
+                "rows": [],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn streams_rows_in_pages() {
+        let pool = memory_pool().await;
+        run_query(&pool, "CREATE TABLE perks (id INTEGER PRIMARY KEY)", &[])
+            .await
+            .unwrap();
+        for id in 1..=5 {
+            run_query(&pool, "INSERT INTO perks (id) VALUES (?)", &[json!(id)])
+                .await
+                .unwrap();
+        }
+
+        let running = AtomicBool::new(false);
+        let mut pages = Vec::new();
+        let outcome = stream_rows(
+            &pool,
+            "SELECT id FROM perks ORDER BY id",
+            &[],
+            2,
+            &running,
+            |rows| pages.push(rows.len()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome, (5, true));
+        assert_eq!(pages, vec![2, 2, 1]);
+
+        let cancelled = AtomicBool::new(true);
+        let outcome = stream_rows(&pool, "SELECT id FROM perks", &[], 2, &cancelled, |_| {})
+            .await
+            .unwrap();
+        assert_eq!(outcome, (2, false));
+
+        let error = stream_rows(&pool, "DELETE FROM perks", &[], 2, &running, |_| {})
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "validation");
+    }
+
+    #[tokio::test]
+    async fn failed_batch_rolls_back() {
+        let pool = memory_pool().await;
+        run_query(
+            &pool,
+            "CREATE TABLE jumps (id TEXT PRIMARY KEY, title TEXT NOT NULL)",
+            &[],
+        )
+        .await
+        .unwrap();
+
+        let error = run_batch(
+            &pool,
+            &[
+                (
+                    "INSERT INTO jumps VALUES (?, ?)".into(),
+                    Some(vec![json!("a"), json!("Naruto")]),
+                ),
+                (
+                    "INSERT INTO jumps VALUES (?, ?)".into(),
+                    Some(vec![json!("b"), Value::Null]),
+                ),
+            ],
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.code(), "db");
+        assert!(error.message().starts_with("Statement 1 failed"), "{error}");
+
+        let result = query_json(&pool, "SELECT COUNT(*) AS total FROM jumps", &[]).await;
+        assert_eq!(result["rows"], json!([{ "total": 0 }]));
+    }
+
+    #[tokio::test]
+    async fn partial_batch_skips_failed_statements() {
+        let pool = memory_pool().await;
+        run_query(
+            &pool,
+            "CREATE TABLE jumps (id TEXT PRIMARY KEY, title TEXT NOT NULL)",
+            &[],
+        )
+        .await
+        .unwrap();
+
+        let insert = "INSERT INTO jumps VALUES (?, ?)".to_string();
+        let result = run_batch_partial(
+            &pool,
+            &[
+                (insert.clone(), Some(vec![json!("a"), json!("Naruto")])),
+                (insert.clone(), Some(vec![json!("b"), Value::Null])),
+                (insert.clone(), Some(vec![json!("a"), json!("Bleach")])),
+                (insert, Some(vec![json!("c"), json!("Worm")])),
+            ],
+        )
+        .await
+        .unwrap();
+        let result = serde_json::to_value(result).unwrap();
+        assert_eq!(result["statements"], json!(4));
+        assert_eq!(result["rows_affected"], json!(2));
+        let failed: Vec<&Value> = result["failures"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|failure| &failure["index"])
+            .collect();
+        assert_eq!(failed, [&json!(1), &json!(2)]);
+
+        let result = query_json(&pool, "SELECT id FROM jumps ORDER BY id", &[]).await;
+        assert_eq!(result["rows"], json!([{ "id": "a" }, { "id": "c" }]));
+    }
+
+    #[tokio::test]
+    async fn explain_plans_without_side_effects() {
+        let pool = memory_pool().await;
+        run_query(
+            &pool,
+            "CREATE TABLE jumps (id TEXT PRIMARY KEY, title TEXT)",
+            &[],
+        )
+        .await
+        .unwrap();
+
+        let plan = explain_query(&pool, "SELECT title FROM jumps WHERE id = ?", &[json!("a")])
+            .await
+            .unwrap();
+        assert!(plan[0].detail.contains("jumps"), "{plan:?}");
+
+        explain_query(&pool, "DELETE FROM jumps", &[])
+            .await
+            .unwrap();
+        explain_query(&pool, "DROP TABLE jumps", &[]).await.unwrap();
+        let result = query_json(&pool, "SELECT COUNT(*) AS total FROM jumps", &[]).await;
+        assert_eq!(result["rows"], json!([{ "total": 0 }]));
+
+        let error = explain_query(&pool, "SELEC title FROM jumps", &[])
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "validation");
+        assert!(error.message().contains("syntax error"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn schema_skips_internal_and_fts_shadow_tables() {
+        let pool = memory_pool().await;
+        for statement in [
+            "CREATE TABLE jumps (id TEXT PRIMARY KEY, title TEXT NOT NULL, cost INTEGER)",
+            "CREATE VIRTUAL TABLE jump_fts USING fts5(title)",
+            "CREATE TABLE jumps_data (note)",
+            "CREATE TABLE t (x INTEGER PRIMARY KEY AUTOINCREMENT)",
+        ] {
+            sqlx::query(statement).execute(&pool).await.unwrap();
+        }
+
+        let schema = serde_json::to_value(read_schema(&pool).await.unwrap()).unwrap();
+        let tables: Vec<&str> = schema
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|table| table["table"].as_str().unwrap())
+            .collect();
+        assert_eq!(tables, ["jump_fts", "jumps", "jumps_data", "t"]);
+        assert_eq!(
+            schema[1]["columns"],
+            json!([
+                { "name": "id", "type": "TEXT", "notnull": false, "pk": 1 },
+                { "name": "title", "type": "TEXT", "notnull": true, "pk": 0 },
+                { "name": "cost", "type": "INTEGER", "notnull": false, "pk": 0 },
+            ])
+        );
+        assert_eq!(schema[2]["columns"][0]["type"], json!(""));
+    }
+
+    #[tokio::test]
+    async fn health_check_lists_foreign_key_violations() {
+        let pool = memory_pool().await;
+        for statement in [
+            "PRAGMA foreign_keys = OFF",
+            "CREATE TABLE jumps (id INTEGER PRIMARY KEY)",
+            "CREATE TABLE perks (id INTEGER PRIMARY KEY, jump_id INTEGER REFERENCES jumps(id))",
+            "INSERT INTO jumps (id) VALUES (1)",
+            "INSERT INTO perks (id, jump_id) VALUES (10, 1), (11, 2)",
+        ] {
+            sqlx::query(statement).execute(&pool).await.unwrap();
+        }
+
+        let report = serde_json::to_value(health_check(&pool).await.unwrap()).unwrap();
+        assert_eq!(report["ok"], json!(false));
+        assert_eq!(report["integrity_errors"], json!([]));
+        assert_eq!(
+            report["foreign_key_violations"],
+            json!([{ "table": "perks", "rowid": 11, "parent": "jumps", "constraint_index": 0 }])
+        );
+        assert!(report["page_count"].as_i64().unwrap() > 0);
+        assert_eq!(report["wal_enabled"], json!(false));
+    }
+
+    #[tokio::test]
+    async fn repair_orphans_follows_on_delete_and_honours_dry_run() {
+        let pool = memory_pool().await;
+        for statement in [
+            "PRAGMA foreign_keys = OFF",
+            "CREATE TABLE jumps (id TEXT PRIMARY KEY)",
+            "CREATE TABLE perks (id INTEGER PRIMARY KEY,
+             jump_id TEXT NOT NULL REFERENCES jumps(id) ON DELETE CASCADE)",
+            "CREATE TABLE perk_tags (id INTEGER PRIMARY KEY,
+             perk_id INTEGER NOT NULL REFERENCES perks(id) ON DELETE CASCADE)",
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY,
+             jump_id TEXT REFERENCES jumps(id) ON DELETE SET NULL)",
+            "INSERT INTO jumps (id) VALUES ('j1')",
+            "INSERT INTO perks (id, jump_id) VALUES (10, 'j1'), (11, 'gone')",
+            "INSERT INTO perk_tags (id, perk_id) VALUES (1, 10), (2, 11)",
+            "INSERT INTO notes (id, jump_id) VALUES (20, 'gone'), (21, 'j1')",
+        ] {
+            sqlx::query(statement).execute(&pool).await.unwrap();
+        }
+
+        let preview = repair_orphans_in(&pool, true).await.unwrap();
+        assert!(preview.dry_run);
+        assert_eq!(
+            preview.repairs,
+            vec![
+                OrphanRepair {
+                    table: "notes".into(),
+                    rowid: 20,
+                    columns: vec!["jump_id".into()],
+                    parent: "jumps".into(),
+                    missing_id: Some("gone".into()),
+                    action: OrphanAction::Detach,
+                },
+                OrphanRepair {
+                    table: "perks".into(),
+                    rowid: 11,
+                    columns: vec!["jump_id".into()],
+                    parent: "jumps".into(),
+                    missing_id: Some("gone".into()),
+                    action: OrphanAction::Delete,
+                },
+                // Only orphaned once its perk was deleted.
+                OrphanRepair {
+                    table: "perk_tags".into(),
+                    rowid: 2,
+                    columns: vec!["perk_id".into()],
+                    parent: "perks".into(),
+                    missing_id: Some("11".into()),
+                    action: OrphanAction::Delete,
+                },
+            ]
+        );
+        assert!(preview.unrepaired.is_empty());
+        assert_eq!(foreign_key_violations(&pool).await.unwrap().len(), 2);
+
+        let report = repair_orphans_in(&pool, false).await.unwrap();
+        assert_eq!(report.repairs.len(), 3);
+        assert!(foreign_key_violations(&pool).await.unwrap().is_empty());
+        let notes = fetch_rows(&pool, "SELECT id, jump_id FROM notes ORDER BY id", &[])
+            .await
+            .unwrap();
+        assert_eq!(
+            notes,
+            vec![
+                json!({ "id": 20, "jump_id": null }),
+                json!({ "id": 21, "jump_id": "j1" })
+            ]
+        );
+        let perks: Vec<i64> = sqlx::query_scalar("SELECT id FROM perks")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(perks, vec![10]);
+
+        assert!(repair_orphans_in(&pool, false)
+            .await
+            .unwrap()
+            .repairs
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn opened_pools_use_wal() {
+        let dir = std::env::temp_dir().join(format!("jumpchain-pragmas-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let pool = open_pool(&dir.join("app.db")).await.unwrap();
+
+        let pragmas = serde_json::to_value(read_pragmas(&pool).await.unwrap()).unwrap();
+        assert_eq!(
+            pragmas,
+            json!({ "journal_mode": "wal", "synchronous": "normal", "foreign_keys": true })
+        );
+
+        pool.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn vacuum_shrinks_the_file_after_deletes() {
+        let dir = std::env::temp_dir().join(format!("jumpchain-optimize-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let live_path = dir.join("app.db");
+        let pool = open_pool(&live_path).await.unwrap();
+        run_batch(
+            &pool,
+            &[
+                ("CREATE TABLE notes (body TEXT)".into(), None),
+                (
+                    "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 2000)
+                     INSERT INTO notes SELECT printf('%0500d', i) FROM n"
+                        .into(),
+                    None,
+                ),
+                ("DELETE FROM notes".into(), None),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let plain = optimize_database(&pool, &live_path, false).await.unwrap();
+        assert!(!plain.vacuumed);
+        let vacuumed = optimize_database(&pool, &live_path, true).await.unwrap();
+        assert!(vacuumed.vacuumed);
+        assert!(vacuumed.bytes_after < vacuumed.bytes_before);
+
+        pool.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn backup_writes_a_readable_copy() {
+        let dir = std::env::temp_dir().join(format!("jumpchain-backup-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let live_path = dir.join("app.db");
+        let destination = dir.join("backup.db");
+        let _ = std::fs::remove_file(&live_path);
+        std::fs::write(&destination, b"stale").unwrap();
+
+        let options = SqliteConnectOptions::new()
+            .filename(&live_path)
+            .create_if_missing(true);
+        let pool = SqlitePool::connect_with(options).await.unwrap();
+        run_batch(
+            &pool,
+            &[
+                ("CREATE TABLE jumps (id TEXT PRIMARY KEY)".into(), None),
+                ("INSERT INTO jumps VALUES ('naruto')".into(), None),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let same = backup_database(&pool, &live_path, &dir.join(".").join("app.db")).await;
+        assert!(same.is_err());
+
+        let bytes = backup_database(&pool, &live_path, &destination)
+            .await
+            .unwrap();
+        assert_eq!(bytes, std::fs::metadata(&destination).unwrap().len());
+
+        let copy = SqlitePool::connect_with(SqliteConnectOptions::new().filename(&destination))
+            .await
+            .unwrap();
+        let result = run_query(&copy, "SELECT id FROM jumps", &[]).await.unwrap();
+        let result = serde_json::to_value(result).unwrap();
+        assert_eq!(result["rows"], json!([{ "id": "naruto" }]));
+
+        copy.close().await;
+        pool.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn restore_validates_before_replacing() {
+        let dir = std::env::temp_dir().join(format!("jumpchain-restore-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let bogus = dir.join("notes.db");
+        std::fs::write(&bogus, b"definitely not sqlite").unwrap();
+
+        let error = validate_database(&bogus).await.unwrap_err();
+        assert_eq!(error.code(), "validation");
+        assert!(
+            error.message().ends_with("is not a SQLite database"),
+            "{error}"
+        );
+        assert!(validate_database(&dir.join("missing.db")).await.is_err());
+
+        let source = dir.join("chain.db");
+        let _ = std::fs::remove_file(&source);
+        let options = SqliteConnectOptions::new()
+            .filename(&source)
+            .create_if_missing(true);
+        let pool = SqlitePool::connect_with(options).await.unwrap();
+        run_query(&pool, "CREATE TABLE jumps (id TEXT PRIMARY KEY)", &[])
+            .await
+            .unwrap();
+        pool.close().await;
+        validate_database(&source).await.unwrap();
+
+        let live_path = dir.join("app.db");
+        std::fs::write(&live_path, b"old").unwrap();
+        replace_database_file(&source, &live_path).unwrap();
+        assert_eq!(
+            std::fs::read(&live_path).unwrap(),
+            std::fs::read(&source).unwrap()
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,161 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+/// Error returned by every command. It reaches the frontend as
+/// `{ code, message }` so callers can branch on `code` instead of matching
+/// message text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum AppError {
+    Io(String),
+    Db(String),
+    Dialog(String),
+    Shell(String),
+    NotFound(String),
+    AlreadyRunning(String),
+    Validation(String),
+    Internal(String),
+}
+
+pub(crate) type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Io(_) => "io",
+            AppError::Db(_) => "db",
+            AppError::Dialog(_) => "dialog",
+            AppError::Shell(_) => "shell",
+            AppError::NotFound(_) => "not_found",
+            AppError::AlreadyRunning(_) => "already_running",
+            AppError::Validation(_) => "validation",
+            AppError::Internal(_) => "internal",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::Io(message)
+            | AppError::Db(message)
+            | AppError::Dialog(message)
+            | AppError::Shell(message)
+            | AppError::NotFound(message)
+            | AppError::AlreadyRunning(message)
+            | AppError::Validation(message)
+            | AppError::Internal(message) => message,
+        }
+    }
+
+    /// A poisoned managed-state mutex, named after the state it guards.
+    pub fn state(name: &str) -> Self {
+        AppError::Internal(format!("Unable to access {name} state"))
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("AppError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", self.message())?;
+        error.end()
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::NotFound => AppError::NotFound(err.to_string()),
+            _ => AppError::Io(err.to_string()),
+        }
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(err: sqlx::Error) -> Self {
+        match err {
+            sqlx::Error::RowNotFound => AppError::NotFound(err.to_string()),
+            _ => AppError::Db(err.to_string()),
+        }
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(err: tauri::Error) -> Self {
+        AppError::Internal(err.to_string())
+    }
+}
+
+impl From<tauri_plugin_shell::Error> for AppError {
+    fn from(err: tauri_plugin_shell::Error) -> Self {
+        AppError::Shell(err.to_string())
+    }
+}
+
+impl From<zip::result::ZipError> for AppError {
+    fn from(err: zip::result::ZipError) -> Self {
+        AppError::Io(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(err: serde_json::Error) -> Self {
+        AppError::Validation(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_code_and_message() {
+        let error = AppError::AlreadyRunning("Test suite is already running".into());
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "code": "already_running",
+                "message": "Test suite is already running",
+            })
+        );
+        assert_eq!(error.to_string(), "Test suite is already running");
+    }
+
+    #[test]
+    fn maps_missing_rows_and_files_to_not_found() {
+        assert_eq!(AppError::from(sqlx::Error::RowNotFound).code(), "not_found");
+        let missing = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        assert_eq!(AppError::from(missing).code(), "not_found");
+        let denied = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "nope");
+        assert_eq!(AppError::from(denied).code(), "io");
+    }
+}
@@ -0,0 +1,1702 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sqlx::sqlite::{SqliteConnection, SqlitePool};
+use sqlx::Row;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::OnceLock;
+use tauri::AppHandle;
+
+use crate::budget;
+use crate::db;
+use crate::error::{AppError, AppResult};
+use crate::jumps::ISO_NOW_SQL;
+use crate::{recycle, snapshots};
+
+/// Bumped whenever the bundle layout changes so importers can migrate or
+/// refuse older files.
+pub(crate) const CHARACTER_BUNDLE_VERSION: u32 = 1;
+
+/// Bundle key for each `jump_assets.asset_type`.
+fn asset_group(asset_type: &str) -> &'static str {
+    match asset_type {
+        "origin" => "origins",
+        "perk" => "perks",
+        "item" => "items",
+        "drawback" => "drawbacks",
+        "companion" => "companions",
+        _ => "other_assets",
+    }
+}
+
+/// SQL expression for a random version 4 UUID, the same shape the frontend
+/// mints with `crypto.randomUUID`.
+pub(crate) const NEW_UUID_SQL: &str = "lower(printf('%s-%s-4%s-%s%s-%s', \
+    hex(randomblob(4)), hex(randomblob(2)), substr(hex(randomblob(2)), 2), \
+    substr('89ab', 1 + abs(random()) % 4, 1), substr(hex(randomblob(2)), 2), \
+    hex(randomblob(6))))";
+
+pub(crate) const ASSET_GROUPS: [&str; 6] = [
+    "origins",
+    "perks",
+    "items",
+    "drawbacks",
+    "companions",
+    "other_assets",
+];
+
+/// Collects the character profile and the chain's jumps, nesting each jump's
+/// purchases under it so the bundle carries no dangling ids.
+pub(crate) async fn character_bundle(pool: &SqlitePool, character_id: &str) -> AppResult<Value> {
+    let mut conn = pool.acquire().await?;
+    character_bundle_on(&mut conn, character_id).await
+}
+
+/// [`character_bundle`] on a specific connection, such as an open
+/// transaction.
+pub(crate) async fn character_bundle_on(
+    conn: &mut SqliteConnection,
+    character_id: &str,
+) -> AppResult<Value> {
+    let character = db::fetch_rows_on(
+        conn,
+        "SELECT * FROM character_profiles WHERE id = ?",
+        &[json!(character_id)],
+    )
+    .await?
+    .into_iter()
+    .next()
+    .ok_or_else(|| AppError::NotFound(format!("Character {character_id} not found")))?;
+
+    let jumps = db::fetch_rows_on(
+        conn,
+        "SELECT * FROM jumps WHERE deleted_at IS NULL ORDER BY sort_order, created_at",
+        &[],
+    )
+    .await?;
+    let assets = db::fetch_rows_on(
+        conn,
+        "SELECT * FROM jump_assets WHERE deleted_at IS NULL ORDER BY sort_order, created_at",
+        &[],
+    )
+    .await?;
+
+    let jumps = jumps
+        .into_iter()
+        .map(|mut jump| {
+            let jump_id = jump["id"].clone();
+            let Value::Object(fields) = &mut jump else {
+                return jump;
+            };
+            let mut groups: Map<String, Value> = ASSET_GROUPS
+                .iter()
+                .map(|group| (group.to_string(), Value::Array(Vec::new())))
+                .collect();
+            for asset in assets.iter().filter(|asset| asset["jump_id"] == jump_id) {
+                let mut asset = asset.clone();
+                let group = asset_group(asset["asset_type"].as_str().unwrap_or_default());
+                if let Value::Object(asset_fields) = &mut asset {
+                    asset_fields.remove("jump_id");
+                }
+                if let Some(Value::Array(list)) = groups.get_mut(group) {
+                    list.push(asset);
+                }
+            }
+            fields.extend(groups);
+            jump
+        })
+        .collect::<Vec<_>>();
+
+    Ok(json!({
+        "schema_version": CHARACTER_BUNDLE_VERSION,
+        "character": character,
+        "jumps": jumps,
+    }))
+}
+
+/// Serializes a character and its chain into a self-contained JSON bundle
+/// for the frontend to hand to `file_save`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn export_character(app: AppHandle, character_id: String) -> AppResult<String> {
+    let pool = db::pool(&app).await?;
+    let bundle = character_bundle(&pool, &character_id).await?;
+    serde_json::to_string_pretty(&bundle).map_err(|err| AppError::Internal(err.to_string()))
+}
+
+/// The jump layout `export_character_markdown` uses unless the caller
+/// supplies one. Each section placeholder renders to nothing when the jump
+/// has no entries for it.
+const DEFAULT_JUMP_TEMPLATE: &str =
+    "## {title}\n\n{world}\n\n{budget}\n\n{origin}\n\n{perks}\n\n{items}\n\n{drawbacks}";
+const JUMP_PLACEHOLDERS: [&str; 7] = [
+    "title",
+    "world",
+    "budget",
+    "origin",
+    "perks",
+    "items",
+    "drawbacks",
+];
+/// Perks without a category are listed under this heading.
+const UNCATEGORIZED_PERKS: &str = "Other Perks";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub(crate) struct MarkdownOptions {
+    include_costs: bool,
+    include_notes: bool,
+    /// Layout of each jump, with `{title}`, `{world}`, `{budget}`,
+    /// `{origin}`, `{perks}`, `{items}` and `{drawbacks}` placeholders.
+    jump_template: Option<String>,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        Self {
+            include_costs: true,
+            include_notes: false,
+            jump_template: None,
+        }
+    }
+}
+
+fn placeholder_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\{(\w+)\}").expect("placeholder pattern is valid"))
+}
+
+/// Rejects placeholders the renderer does not know, so a typo is reported
+/// instead of appearing in the write-up.
+fn check_jump_template(template: &str) -> AppResult<()> {
+    let unknown: BTreeSet<&str> = placeholder_pattern()
+        .captures_iter(template)
+        .filter_map(|captures| captures.get(1))
+        .map(|name| name.as_str())
+        .filter(|name| !JUMP_PLACEHOLDERS.contains(name))
+        .collect();
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Validation(format!(
+            "Unknown template placeholders: {}",
+            unknown.into_iter().collect::<Vec<_>>().join(", ")
+        )))
+    }
+}
+
+/// Backslash-escapes the characters that would otherwise start emphasis,
+/// code or links.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(ch, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// A field's trimmed text, or `None` when it is missing or blank.
+fn text_field(value: Option<&Value>) -> Option<&str> {
+    value
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+/// `"300 CP"`, `"150 CP, discounted"`, `"free"` or `"+200 CP"` for a
+/// drawback, priced as `validate_budget` prices it.
+fn cost_label(asset: &Value) -> String {
+    let row = asset_row(asset);
+    let (quantity, discounted, freebie) = (row.4, row.5, row.6);
+    let is_drawback = row.2 == "drawback";
+    let (spent, credit) = budget::spend_and_credit([row]);
+    let amount = if is_drawback {
+        format!("+{credit} CP")
+    } else if freebie {
+        "free".to_string()
+    } else if discounted {
+        format!("{spent} CP, discounted")
+    } else {
+        format!("{spent} CP")
+    };
+    if quantity > 1 {
+        format!("{amount}, \u{d7}{quantity}")
+    } else {
+        amount
+    }
+}
+
+fn asset_line(asset: &Value, options: &MarkdownOptions) -> String {
+    let mut line = format!(
+        "- **{}**",
+        escape_markdown(text_field(asset.get("name")).unwrap_or("Unnamed"))
+    );
+    if options.include_costs {
+        line.push_str(&format!(" ({})", cost_label(asset)));
+    }
+    if options.include_notes {
+        if let Some(notes) = text_field(asset.get("notes")) {
+            for note in notes.lines() {
+                line.push_str("\n  ");
+                line.push_str(note.trim_end());
+            }
+        }
+    }
+    line
+}
+
+fn asset_section(heading: &str, assets: &[Value], options: &MarkdownOptions) -> String {
+    if assets.is_empty() {
+        return String::new();
+    }
+    let lines: Vec<String> = assets
+        .iter()
+        .map(|asset| asset_line(asset, options))
+        .collect();
+    format!("### {heading}\n\n{}", lines.join("\n"))
+}
+
+/// Perks under a heading per category, in the order categories first
+/// appear; uncategorized perks come last.
+fn perk_section(perks: &[Value], options: &MarkdownOptions) -> String {
+    if perks.is_empty() {
+        return String::new();
+    }
+    let mut categories: Vec<(Option<&str>, Vec<String>)> = Vec::new();
+    for perk in perks {
+        let category = text_field(perk.get("category"));
+        let line = asset_line(perk, options);
+        match categories.iter_mut().find(|(name, _)| *name == category) {
+            Some((_, lines)) => lines.push(line),
+            None => categories.push((category, vec![line])),
+        }
+    }
+    categories.sort_by_key(|(name, _)| name.is_none());
+    let groups: Vec<String> = categories
+        .into_iter()
+        .map(|(name, lines)| {
+            let heading = name.map_or_else(|| UNCATEGORIZED_PERKS.to_string(), escape_markdown);
+            format!("#### {heading}\n\n{}", lines.join("\n"))
+        })
+        .collect();
+    format!("### Perks\n\n{}", groups.join("\n\n"))
+}
+
+fn render_jump(jump: &Map<String, Value>, template: &str, options: &MarkdownOptions) -> String {
+    let origins: Vec<String> = group(Some(jump), "origins")
+        .iter()
+        .map(|origin| {
+            let name = escape_markdown(text_field(origin.get("name")).unwrap_or("Unnamed"));
+            if options.include_costs {
+                format!("{name} ({})", cost_label(origin))
+            } else {
+                name
+            }
+        })
+        .collect();
+    let budget = if options.include_costs {
+        let (budget, spent) = jump_totals(Some(jump));
+        format!("*{spent} / {budget} CP spent*")
+    } else {
+        String::new()
+    };
+    let world = text_field(jump.get("world"))
+        .map(|world| format!("*{}*", escape_markdown(world)))
+        .unwrap_or_default();
+    let origin = if origins.is_empty() {
+        String::new()
+    } else {
+        format!("**Origin:** {}", origins.join(", "))
+    };
+    let sections = [
+        (
+            "title",
+            escape_markdown(text_field(jump.get("title")).unwrap_or("Untitled Jump")),
+        ),
+        ("world", world),
+        ("budget", budget),
+        ("origin", origin),
+        ("perks", perk_section(group(Some(jump), "perks"), options)),
+        (
+            "items",
+            asset_section("Items", group(Some(jump), "items"), options),
+        ),
+        (
+            "drawbacks",
+            asset_section("Drawbacks", group(Some(jump), "drawbacks"), options),
+        ),
+    ];
+    // One pass, so a placeholder inside a jump's own text is left alone.
+    let out = placeholder_pattern().replace_all(template, |captures: &regex::Captures| {
+        sections
+            .iter()
+            .find(|(name, _)| *name == &captures[1])
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default()
+    });
+    collapse_blank_lines(&out)
+}
+
+/// Drops the runs of blank lines left where empty sections were.
+fn collapse_blank_lines(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut blank = true;
+    for line in text.lines() {
+        let is_blank = line.trim().is_empty();
+        if is_blank && blank {
+            continue;
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+        blank = is_blank;
+    }
+    out.trim_end().to_string()
+}
+
+/// Renders an export bundle as a markdown write-up: the character's name,
+/// then each jump laid out by `options.jump_template`.
+pub(crate) fn render_markdown(bundle: &Value, options: &MarkdownOptions) -> AppResult<String> {
+    let template = options
+        .jump_template
+        .as_deref()
+        .unwrap_or(DEFAULT_JUMP_TEMPLATE);
+    check_jump_template(template)?;
+    let parsed = parse_bundle(bundle)?;
+    let name = text_field(parsed.character.get("name")).unwrap_or("Unnamed Jumper");
+    let mut out = format!("# {}\n", escape_markdown(name));
+    for jump in parsed.jumps {
+        out.push('\n');
+        out.push_str(&render_jump(jump, template, options));
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Formats a character and its chain as markdown for posting on forums,
+/// reading the same data as `export_character`. The string is meant for
+/// `file_save`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn export_character_markdown(
+    app: AppHandle,
+    character_id: String,
+    options: Option<MarkdownOptions>,
+) -> AppResult<String> {
+    let pool = db::pool(&app).await?;
+    let bundle = character_bundle(&pool, &character_id).await?;
+    render_markdown(&bundle, &options.unwrap_or_default())
+}
+
+pub(crate) async fn table_columns(
+    conn: &mut SqliteConnection,
+    table: &str,
+) -> Result<HashSet<String>, sqlx::Error> {
+    let names: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?)")
+        .bind(table)
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(names.into_iter().collect())
+}
+
+/// Inserts `row` into `table` under a freshly generated id and returns it.
+/// Keys that are not columns of `table` (nested groups, fields from a newer
+/// layout) are skipped, which also keeps bundle keys out of the SQL text.
+async fn insert_with_new_id(
+    conn: &mut SqliteConnection,
+    table: &str,
+    row: &Map<String, Value>,
+) -> AppResult<String> {
+    let columns = table_columns(conn, table).await?;
+    let fields: Vec<(&String, &Value)> = row
+        .iter()
+        .filter(|(name, _)| name.as_str() != "id" && columns.contains(name.as_str()))
+        .collect();
+
+    let mut names = String::from("id");
+    let mut placeholders = String::from(NEW_UUID_SQL);
+    for (name, _) in &fields {
+        names.push_str(&format!(", \"{name}\""));
+        placeholders.push_str(", ?");
+    }
+    let query = format!("INSERT INTO {table} ({names}) VALUES ({placeholders}) RETURNING id");
+    let values: Vec<Value> = fields.iter().map(|(_, value)| (*value).clone()).collect();
+
+    let inserted = db::bind_values(sqlx::query(&query), &values)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|err| AppError::Db(format!("Unable to import into {table}: {err}")))?;
+    Ok(inserted.try_get("id")?)
+}
+
+/// Top-level keys a bundle produced by [`character_bundle`] carries.
+const BUNDLE_KEYS: [&str; 3] = ["schema_version", "character", "jumps"];
+
+/// The parts of a bundle an import reads, after the checks that would make
+/// it refuse the file.
+pub(crate) struct ParsedBundle<'a> {
+    pub version: u64,
+    pub character: &'a Map<String, Value>,
+    pub jumps: Vec<&'a Map<String, Value>>,
+}
+
+pub(crate) fn parse_bundle(bundle: &Value) -> AppResult<ParsedBundle<'_>> {
+    let version = bundle
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| AppError::Validation("Bundle is missing its schema_version".into()))?;
+    if version > u64::from(CHARACTER_BUNDLE_VERSION) {
+        return Err(AppError::Validation(format!(
+            "Bundle schema version {version} is newer than this app supports \
+             (up to {CHARACTER_BUNDLE_VERSION}); update the app to import it"
+        )));
+    }
+    if version == 0 {
+        return Err(AppError::Validation(
+            "Bundle schema version 0 is not valid".into(),
+        ));
+    }
+
+    let character = bundle
+        .get("character")
+        .and_then(Value::as_object)
+        .ok_or_else(|| AppError::Validation("Bundle does not contain a character".into()))?;
+    let jumps = match bundle.get("jumps") {
+        None | Some(Value::Null) => &[][..],
+        Some(Value::Array(jumps)) => jumps.as_slice(),
+        Some(_) => return Err(AppError::Validation("Bundle jumps must be a list".into())),
+    };
+    let jumps = jumps
+        .iter()
+        .map(|jump| {
+            jump.as_object()
+                .ok_or_else(|| AppError::Validation("Bundle jumps must be objects".into()))
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+    Ok(ParsedBundle {
+        version,
+        character,
+        jumps,
+    })
+}
+
+/// Titles of the bundled jumps that match no live jump of this chain by id
+/// or, failing that, by title.
+async fn unmatched_jumps<'a>(
+    conn: &mut SqliteConnection,
+    jumps: &[&'a Map<String, Value>],
+) -> AppResult<Vec<&'a str>> {
+    let existing: Vec<(String, Option<String>)> =
+        sqlx::query_as("SELECT id, title FROM jumps WHERE deleted_at IS NULL")
+            .fetch_all(&mut *conn)
+            .await?;
+    let ids: HashSet<&str> = existing.iter().map(|(id, _)| id.as_str()).collect();
+    let titles: HashSet<String> = existing
+        .iter()
+        .filter_map(|(_, title)| title.as_deref())
+        .map(|title| title.trim().to_lowercase())
+        .collect();
+    Ok(jumps
+        .iter()
+        .copied()
+        .filter(|jump| {
+            !text_field(jump.get("id")).is_some_and(|id| ids.contains(id))
+                && !text_field(jump.get("title"))
+                    .is_some_and(|title| titles.contains(&title.to_lowercase()))
+        })
+        .map(|jump| text_field(jump.get("title")).unwrap_or("Untitled"))
+        .collect())
+}
+
+/// Inserts the character of a bundle produced by [`character_bundle`]
+/// under a new id, so the import never collides with existing rows.
+/// Jumps belong to the whole chain rather than to a character, so bundled
+/// jumps and their purchases are not inserted; [`inspect_bundle`] warns
+/// about the ones this chain lacks. Returns the new character id.
+pub(crate) async fn import_bundle(pool: &SqlitePool, bundle: &Value) -> AppResult<String> {
+    let ParsedBundle { character, .. } = parse_bundle(bundle)?;
+    let mut conn = pool.acquire().await?;
+    insert_with_new_id(&mut conn, "character_profiles", character).await
+}
+
+fn parse_bundle_json(json: &str) -> AppResult<Value> {
+    serde_json::from_str(json)
+        .map_err(|err| AppError::Validation(format!("Invalid character bundle: {err}")))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn import_character(app: AppHandle, json: String) -> AppResult<String> {
+    let bundle = parse_bundle_json(&json)?;
+    let pool = db::pool(&app).await?;
+    import_bundle(&pool, &bundle).await
+}
+
+/// What an import of a bundle would bring in, for previewing a shared file.
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct BundleSummary {
+    pub schema_version: u64,
+    pub character_name: Option<String>,
+    pub jump_count: usize,
+    pub perk_count: usize,
+    /// Things the import tolerates but drops or reads loosely, such as
+    /// fields this app has no column for.
+    pub warnings: Vec<String>,
+}
+
+/// Records, once per name, the keys of `row` that `columns` has no place
+/// for and that are not in `known`.
+fn unknown_fields<'a>(
+    row: &'a Map<String, Value>,
+    columns: &HashSet<String>,
+    known: &[&str],
+    found: &mut BTreeSet<&'a str>,
+) {
+    found.extend(
+        row.keys()
+            .map(String::as_str)
+            .filter(|name| !columns.contains(*name) && !known.contains(name)),
+    );
+}
+
+fn warn_unknown(warnings: &mut Vec<String>, what: &str, found: BTreeSet<&str>) {
+    warnings.extend(
+        found
+            .into_iter()
+            .map(|name| format!("Unknown {what} field \"{name}\" will be ignored")),
+    );
+}
+
+/// Runs the same checks as [`import_bundle`] and summarizes the bundle
+/// without writing anything.
+pub(crate) async fn inspect_bundle(pool: &SqlitePool, bundle: &Value) -> AppResult<BundleSummary> {
+    let parsed = parse_bundle(bundle)?;
+    let mut warnings = Vec::new();
+
+    if parsed.version < u64::from(CHARACTER_BUNDLE_VERSION) {
+        warnings.push(format!(
+            "Bundle schema version {} is older than this app's ({CHARACTER_BUNDLE_VERSION}); \
+             fields it lacks will take their defaults",
+            parsed.version
+        ));
+    }
+    if let Value::Object(fields) = bundle {
+        warnings.extend(
+            fields
+                .keys()
+                .filter(|key| !BUNDLE_KEYS.contains(&key.as_str()))
+                .map(|key| format!("Unknown bundle field \"{key}\" will be ignored")),
+        );
+    }
+
+    let mut conn = pool.acquire().await?;
+    let character_columns = table_columns(&mut conn, "character_profiles").await?;
+    let jump_columns = table_columns(&mut conn, "jumps").await?;
+    let asset_columns = table_columns(&mut conn, "jump_assets").await?;
+
+    let mut unknown = BTreeSet::new();
+    unknown_fields(parsed.character, &character_columns, &[], &mut unknown);
+    warn_unknown(&mut warnings, "character", unknown);
+
+    let mut unknown_jump = BTreeSet::new();
+    let mut unknown_asset = BTreeSet::new();
+    let mut perk_count = 0;
+    for jump in &parsed.jumps {
+        unknown_fields(jump, &jump_columns, &ASSET_GROUPS, &mut unknown_jump);
+        for group in ASSET_GROUPS {
+            let assets = match jump.get(group) {
+                None | Some(Value::Null) => continue,
+                Some(Value::Array(assets)) => assets,
+                Some(_) => {
+                    warnings.push(format!(
+                        "Jump field \"{group}\" is not a list and will be ignored"
+                    ));
+                    continue;
+                }
+            };
+            for asset in assets {
+                let asset = asset
+                    .as_object()
+                    .ok_or_else(|| AppError::Validation("Bundle assets must be objects".into()))?;
+                unknown_fields(asset, &asset_columns, &[], &mut unknown_asset);
+            }
+            if group == "perks" {
+                perk_count += assets.len();
+            }
+        }
+    }
+    warn_unknown(&mut warnings, "jump", unknown_jump);
+    warn_unknown(&mut warnings, "asset", unknown_asset);
+    let unmatched = unmatched_jumps(&mut conn, &parsed.jumps).await?;
+    if !unmatched.is_empty() {
+        warnings.push(format!(
+            "Jumps not in this chain will not be imported: {}",
+            unmatched.join(", ")
+        ));
+    }
+
+    Ok(BundleSummary {
+        schema_version: parsed.version,
+        character_name: parsed
+            .character
+            .get("name")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        jump_count: parsed.jumps.len(),
+        perk_count,
+        warnings,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn inspect_import(app: AppHandle, json: String) -> AppResult<BundleSummary> {
+    let bundle = parse_bundle_json(&json)?;
+    let pool = db::pool(&app).await?;
+    inspect_bundle(&pool, &bundle).await
+}
+
+/// Copies every `table` row matching `filter` under fresh ids, with the
+/// `overrides` columns set to the bound values instead of copied. Returns
+/// the new ids in row order.
+async fn copy_rows(
+    conn: &mut SqliteConnection,
+    table: &str,
+    filter: &str,
+    filter_values: &[Value],
+    overrides: &[(&str, Value)],
+) -> AppResult<Vec<String>> {
+    let mut copied: Vec<String> = table_columns(conn, table)
+        .await?
+        .into_iter()
+        .filter(|name| name != "id" && !overrides.iter().any(|(column, _)| column == name))
+        .map(|name| format!("\"{name}\""))
+        .collect();
+    copied.sort();
+    let copied = copied.join(", ");
+
+    let mut names = String::from("id");
+    let mut selected = String::from(NEW_UUID_SQL);
+    for (column, _) in overrides {
+        names.push_str(&format!(", \"{column}\""));
+        selected.push_str(", ?");
+    }
+    if !copied.is_empty() {
+        names.push_str(&format!(", {copied}"));
+        selected.push_str(&format!(", {copied}"));
+    }
+    let query = format!(
+        "INSERT INTO {table} ({names}) SELECT {selected} FROM {table} WHERE {filter} \
+         ORDER BY rowid RETURNING id"
+    );
+    let values: Vec<Value> = overrides
+        .iter()
+        .map(|(_, value)| value.clone())
+        .chain(filter_values.iter().cloned())
+        .collect();
+
+    let rows = db::bind_values(sqlx::query(&query), &values)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|err| AppError::Db(format!("Unable to copy {table}: {err}")))?;
+    rows.iter().map(|row| Ok(row.try_get("id")?)).collect()
+}
+
+/// Forks a character: a copy of its profile, with the attributes, traits
+/// and alternate forms stored on it, under `new_name`. Jumps belong to the
+/// whole chain, so the clone shares them rather than getting copies.
+/// Returns the new character id.
+pub(crate) async fn clone_character_rows(
+    pool: &SqlitePool,
+    character_id: &str,
+    new_name: &str,
+) -> AppResult<String> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() {
+        return Err(AppError::Validation("The clone needs a name".into()));
+    }
+
+    let mut conn = pool.acquire().await?;
+    copy_rows(
+        &mut conn,
+        "character_profiles",
+        "id = ?",
+        &[json!(character_id)],
+        &[("name", json!(new_name))],
+    )
+    .await?
+    .pop()
+    .ok_or_else(|| AppError::NotFound(format!("Character {character_id} not found")))
+}
+
+/// How `merge_characters` treats an entry the primary already has by name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MergeStrategy {
+    /// Keep the primary's entry and drop the secondary's.
+    #[default]
+    Skip,
+    /// Keep both under the same name.
+    Append,
+    /// Keep both, suffixing the secondary's with its character's name.
+    Rename,
+}
+
+/// Profile text columns the secondary fills in where the primary is blank.
+const MERGED_TEXT_FIELDS: [&str; 5] = ["alias", "species", "homeland", "biography", "notes"];
+/// Profile columns holding JSON lists of `{ name, ... }` entries, with the
+/// kind reported for each entry.
+const MERGED_LIST_FIELDS: [(&str, &str); 2] =
+    [("traits_json", "trait"), ("alt_forms_json", "alt_form")];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct MergedEntry {
+    kind: &'static str,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    renamed_to: Option<String>,
+}
+
+impl MergedEntry {
+    fn new(kind: &'static str, name: &str) -> Self {
+        Self {
+            kind,
+            name: name.to_string(),
+            renamed_to: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub(crate) struct MergeReport {
+    merged: Vec<MergedEntry>,
+    skipped: Vec<MergedEntry>,
+    /// Live jumps, which both characters already share.
+    shared_jumps: i64,
+    /// The snapshot taken of the secondary as it went to the recycle bin.
+    secondary_snapshot: Option<i64>,
+    /// Whether the secondary was moved to the recycle bin.
+    secondary_removed: bool,
+}
+
+/// `name` suffixed with the secondary's name, numbered until it is free.
+fn merged_name(name: &str, suffix: &str, taken: &HashSet<String>) -> String {
+    let mut candidate = format!("{name} ({suffix})");
+    let mut counter = 2;
+    while taken.contains(&candidate.to_lowercase()) {
+        candidate = format!("{name} ({suffix} {counter})");
+        counter += 1;
+    }
+    candidate
+}
+
+fn json_column(profile: &Map<String, Value>, column: &str) -> Option<Value> {
+    profile
+        .get(column)
+        .and_then(Value::as_str)
+        .and_then(|json| serde_json::from_str(json).ok())
+}
+
+/// Merges the secondary profile into the primary, returning the changed
+/// primary columns. Attributes are keyed, so under `append` a clashing
+/// attribute is renamed as under `rename`.
+fn merge_profiles(
+    primary: &Map<String, Value>,
+    secondary: &Map<String, Value>,
+    strategy: MergeStrategy,
+    report: &mut MergeReport,
+) -> Vec<(&'static str, String)> {
+    let suffix = text_field(secondary.get("name")).unwrap_or("merged");
+    let mut updates = Vec::new();
+
+    for column in MERGED_TEXT_FIELDS {
+        let Some(incoming) = text_field(secondary.get(column)) else {
+            continue;
+        };
+        match text_field(primary.get(column)) {
+            None => {
+                updates.push((column, incoming.to_string()));
+                report.merged.push(MergedEntry::new("field", column));
+            }
+            Some(existing) if existing != incoming => {
+                report.skipped.push(MergedEntry::new("field", column));
+            }
+            Some(_) => {}
+        }
+    }
+
+    for (column, kind) in MERGED_LIST_FIELDS {
+        let Some(Value::Array(incoming)) = json_column(secondary, column) else {
+            continue;
+        };
+        let mut list = match json_column(primary, column) {
+            Some(Value::Array(list)) => list,
+            _ => Vec::new(),
+        };
+        let mut taken: HashSet<String> = list
+            .iter()
+            .filter_map(|entry| text_field(entry.get("name")))
+            .map(str::to_lowercase)
+            .collect();
+        let before = list.len();
+        for mut entry in incoming {
+            let name = text_field(entry.get("name"))
+                .unwrap_or_default()
+                .to_string();
+            let mut merged = MergedEntry::new(kind, &name);
+            if !name.is_empty() && taken.contains(&name.to_lowercase()) {
+                match strategy {
+                    MergeStrategy::Skip => {
+                        report.skipped.push(merged);
+                        continue;
+                    }
+                    MergeStrategy::Append => {}
+                    MergeStrategy::Rename => {
+                        let renamed = merged_name(&name, suffix, &taken);
+                        entry["name"] = json!(renamed);
+                        merged.renamed_to = Some(renamed);
+                    }
+                }
+            }
+            taken.insert(merged.renamed_to.as_deref().unwrap_or(&name).to_lowercase());
+            list.push(entry);
+            report.merged.push(merged);
+        }
+        if list.len() > before {
+            updates.push((column, Value::Array(list).to_string()));
+        }
+    }
+
+    if let Some(Value::Object(incoming)) = json_column(secondary, "attributes_json") {
+        let mut attributes = match json_column(primary, "attributes_json") {
+            Some(Value::Object(attributes)) => attributes,
+            _ => Map::new(),
+        };
+        let before = attributes.len();
+        for (key, value) in incoming {
+            let mut merged = MergedEntry::new("attribute", &key);
+            if let Some(existing) = attributes.get(&key) {
+                if *existing == value || strategy == MergeStrategy::Skip {
+                    report.skipped.push(merged);
+                    continue;
+                }
+                let taken = attributes.keys().map(|key| key.to_lowercase()).collect();
+                let renamed = merged_name(&key, suffix, &taken);
+                merged.renamed_to = Some(renamed.clone());
+                attributes.insert(renamed, value);
+            } else {
+                attributes.insert(key, value);
+            }
+            report.merged.push(merged);
+        }
+        if attributes.len() > before {
+            updates.push(("attributes_json", Value::Object(attributes).to_string()));
+        }
+    }
+    updates
+}
+
+async fn profile(conn: &mut SqliteConnection, character_id: &str) -> AppResult<Map<String, Value>> {
+    match db::fetch_rows_on(
+        conn,
+        "SELECT * FROM character_profiles WHERE id = ? AND deleted_at IS NULL",
+        &[json!(character_id)],
+    )
+    .await?
+    .pop()
+    {
+        Some(Value::Object(profile)) => Ok(profile),
+        _ => Err(AppError::NotFound(format!(
+            "Character {character_id} not found"
+        ))),
+    }
+}
+
+/// Folds `secondary_id`'s profile into `primary_id` in one transaction.
+/// Jumps and their purchases belong to the whole chain rather than to a
+/// character, so both characters already share them and none are copied;
+/// the merge covers what is per character: blank profile fields, traits,
+/// alternate forms and attributes. With `remove_secondary`, the secondary
+/// is snapshotted and moved to the recycle bin in the same transaction, so
+/// `restore_deleted` or `restore_snapshot` can bring it back.
+pub(crate) async fn merge_character_rows(
+    pool: &SqlitePool,
+    primary_id: &str,
+    secondary_id: &str,
+    strategy: MergeStrategy,
+    remove_secondary: bool,
+) -> AppResult<MergeReport> {
+    if primary_id == secondary_id {
+        return Err(AppError::Validation(
+            "A character cannot be merged into itself".into(),
+        ));
+    }
+    recycle::ensure_tombstone_columns(pool).await?;
+
+    let mut tx = pool.begin().await?;
+    let primary = profile(&mut tx, primary_id).await?;
+    let secondary = profile(&mut tx, secondary_id).await?;
+    let mut report = MergeReport::default();
+    let updates = merge_profiles(&primary, &secondary, strategy, &mut report);
+    if remove_secondary {
+        report.secondary_snapshot = match snapshots::take_snapshot_on(&mut tx, secondary_id).await?
+        {
+            Some(id) => Some(id),
+            // Unchanged since the latest snapshot, which still holds it.
+            None => {
+                sqlx::query_scalar("SELECT MAX(id) FROM snapshots WHERE character_id = ?")
+                    .bind(secondary_id)
+                    .fetch_one(&mut *tx)
+                    .await?
+            }
+        };
+    }
+    report.shared_jumps = sqlx::query_scalar("SELECT COUNT(*) FROM jumps WHERE deleted_at IS NULL")
+        .fetch_one(&mut *tx)
+        .await?;
+    if !updates.is_empty() {
+        let assignments: Vec<String> = updates
+            .iter()
+            .map(|(column, _)| format!("{column} = ?"))
+            .collect();
+        let statement = format!(
+            "UPDATE character_profiles SET {}, updated_at = {ISO_NOW_SQL} WHERE id = ?",
+            assignments.join(", ")
+        );
+        let mut query = sqlx::query(&statement);
+        for (_, value) in &updates {
+            query = query.bind(value);
+        }
+        query.bind(primary_id).execute(&mut *tx).await?;
+    }
+    if remove_secondary {
+        sqlx::query(&format!(
+            "UPDATE character_profiles SET deleted_at = {ISO_NOW_SQL} WHERE id = ?"
+        ))
+        .bind(secondary_id)
+        .execute(&mut *tx)
+        .await?;
+        report.secondary_removed = true;
+    }
+    tx.commit().await?;
+    Ok(report)
+}
+
+/// Bookkeeping columns that differ between any two copies of a row and say
+/// nothing about the build.
+const UNDIFFED_FIELDS: [&str; 6] = [
+    "id",
+    "jump_id",
+    "sort_order",
+    "created_at",
+    "updated_at",
+    "deleted_at",
+];
+
+/// One field whose value differs; `before` and `after` are `null` when the
+/// field is missing on that side.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct FieldChange {
+    field: String,
+    before: Value,
+    after: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct AssetChange {
+    name: String,
+    fields: Vec<FieldChange>,
+}
+
+/// Purchases in one asset group, matched by name. Names are sorted.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub(crate) struct AssetDiff {
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<AssetChange>,
+}
+
+impl AssetDiff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum JumpChange {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// How one jump differs. `assets` holds only the groups with changes, and
+/// the deltas are `after - before` in CP, where a missing jump counts as
+/// zero. `budget_delta` includes drawback credit, as `validate_budget` does.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct JumpDiff {
+    title: String,
+    change: JumpChange,
+    fields: Vec<FieldChange>,
+    assets: BTreeMap<&'static str, AssetDiff>,
+    budget_delta: f64,
+    spent_delta: f64,
+}
+
+/// What changed from one character bundle to another. Jumps come in the
+/// order of the first bundle, followed by the ones only the second has.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct CharacterDiff {
+    profile: Vec<FieldChange>,
+    jumps: Vec<JumpDiff>,
+}
+
+fn field_changes(before: &Map<String, Value>, after: &Map<String, Value>) -> Vec<FieldChange> {
+    let fields: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    fields
+        .into_iter()
+        .filter(|field| {
+            !UNDIFFED_FIELDS.contains(&field.as_str()) && !ASSET_GROUPS.contains(&field.as_str())
+        })
+        .filter_map(|field| {
+            let before = before.get(field).cloned().unwrap_or(Value::Null);
+            let after = after.get(field).cloned().unwrap_or(Value::Null);
+            (before != after).then(|| FieldChange {
+                field: field.clone(),
+                before,
+                after,
+            })
+        })
+        .collect()
+}
+
+/// Keys rows by `key_field`, numbering repeats so two rows with the same
+/// name pair up in order rather than collapsing into one.
+fn keyed<'a>(rows: &'a [Value], key_field: &str) -> Vec<((String, usize), &'a Map<String, Value>)> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    rows.iter()
+        .filter_map(Value::as_object)
+        .map(|row| {
+            let key = row
+                .get(key_field)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            let occurrence = seen.entry(key.clone()).or_default();
+            *occurrence += 1;
+            ((key, *occurrence), row)
+        })
+        .collect()
+}
+
+fn group<'a>(jump: Option<&'a Map<String, Value>>, name: &str) -> &'a [Value] {
+    jump.and_then(|jump| jump.get(name))
+        .and_then(Value::as_array)
+        .map_or(&[][..], Vec::as_slice)
+}
+
+fn asset_diff(before: &[Value], after: &[Value]) -> AssetDiff {
+    let before = keyed(before, "name");
+    let after = keyed(after, "name");
+    let after_by_key: HashMap<&(String, usize), &Map<String, Value>> =
+        after.iter().map(|(key, row)| (key, *row)).collect();
+    let before_keys: HashSet<&(String, usize)> = before.iter().map(|(key, _)| key).collect();
+
+    let mut diff = AssetDiff::default();
+    for (key, row) in &before {
+        match after_by_key.get(key) {
+            None => diff.removed.push(key.0.clone()),
+            Some(other) => {
+                let fields = field_changes(row, other);
+                if !fields.is_empty() {
+                    diff.changed.push(AssetChange {
+                        name: key.0.clone(),
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+    diff.added = after
+        .iter()
+        .filter(|(key, _)| !before_keys.contains(key))
+        .map(|((name, _), _)| name.clone())
+        .collect();
+    diff.added.sort();
+    diff.removed.sort();
+    diff.changed.sort_by(|a, b| a.name.cmp(&b.name));
+    diff
+}
+
+fn asset_row(asset: &Value) -> budget::AssetRow {
+    let flag = |field: &str| match &asset[field] {
+        Value::Bool(flag) => *flag,
+        other => other.as_i64() == Some(1),
+    };
+    (
+        asset["id"].as_str().unwrap_or_default().to_string(),
+        asset["name"].as_str().unwrap_or_default().to_string(),
+        asset["asset_type"].as_str().unwrap_or_default().to_string(),
+        asset["cost"].as_i64().unwrap_or(0),
+        asset["quantity"].as_i64().unwrap_or(1),
+        flag("discounted"),
+        flag("freebie"),
+    )
+}
+
+/// `(budget, spent)` for a bundled jump, or zeros when it is absent.
+fn jump_totals(jump: Option<&Map<String, Value>>) -> (f64, f64) {
+    let Some(jump) = jump else {
+        return (0.0, 0.0);
+    };
+    let assets = ASSET_GROUPS
+        .iter()
+        .flat_map(|name| group(Some(jump), name))
+        .map(asset_row);
+    let (spent, credit) = budget::spend_and_credit(assets);
+    let base = jump.get("cp_budget").and_then(Value::as_i64).unwrap_or(0);
+    (base as f64 + credit, spent)
+}
+
+fn jump_diff(
+    title: &str,
+    before: Option<&Map<String, Value>>,
+    after: Option<&Map<String, Value>>,
+) -> Option<JumpDiff> {
+    let change = match (before, after) {
+        (Some(_), None) => JumpChange::Removed,
+        (None, Some(_)) => JumpChange::Added,
+        _ => JumpChange::Changed,
+    };
+    let empty = Map::new();
+    let fields = field_changes(before.unwrap_or(&empty), after.unwrap_or(&empty));
+    let assets: BTreeMap<&'static str, AssetDiff> = ASSET_GROUPS
+        .iter()
+        .map(|name| (*name, asset_diff(group(before, name), group(after, name))))
+        .filter(|(_, diff)| !diff.is_empty())
+        .collect();
+    let (budget_before, spent_before) = jump_totals(before);
+    let (budget_after, spent_after) = jump_totals(after);
+
+    let diff = JumpDiff {
+        title: title.to_string(),
+        change,
+        fields,
+        assets,
+        budget_delta: budget_after - budget_before,
+        spent_delta: spent_after - spent_before,
+    };
+    let unchanged = diff.change == JumpChange::Changed
+        && diff.fields.is_empty()
+        && diff.assets.is_empty()
+        && diff.budget_delta == 0.0
+        && diff.spent_delta == 0.0;
+    (!unchanged).then_some(diff)
+}
+
+/// Compares two bundles shaped like [`character_bundle`]'s, matching jumps
+/// by title and purchases by name within their group. Ids and bookkeeping
+/// columns are ignored, so a fresh clone has no differences.
+pub(crate) fn diff_bundles(before: &Value, after: &Value) -> CharacterDiff {
+    let empty = Map::new();
+    let profile = field_changes(
+        before["character"].as_object().unwrap_or(&empty),
+        after["character"].as_object().unwrap_or(&empty),
+    );
+
+    let jumps_before = keyed(group(before.as_object(), "jumps"), "title");
+    let jumps_after = keyed(group(after.as_object(), "jumps"), "title");
+    let after_by_key: HashMap<&(String, usize), &Map<String, Value>> =
+        jumps_after.iter().map(|(key, row)| (key, *row)).collect();
+    let before_keys: HashSet<&(String, usize)> = jumps_before.iter().map(|(key, _)| key).collect();
+
+    let jumps = jumps_before
+        .iter()
+        .filter_map(|(key, jump)| jump_diff(&key.0, Some(*jump), after_by_key.get(key).copied()))
+        .chain(
+            jumps_after
+                .iter()
+                .filter(|(key, _)| !before_keys.contains(key))
+                .filter_map(|(key, jump)| jump_diff(&key.0, None, Some(*jump))),
+        )
+        .collect();
+
+    CharacterDiff { profile, jumps }
+}
+
+/// Diffs two characters through their export bundles. Jumps are shared by
+/// the whole chain rather than owned per character, so both bundles carry
+/// the same jumps and the differences are in the profiles; exported files
+/// from different chains can be compared with [`diff_bundles`].
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn diff_characters(
+    app: AppHandle,
+    a_id: String,
+    b_id: String,
+) -> AppResult<CharacterDiff> {
+    let pool = db::pool(&app).await?;
+    let before = character_bundle(&pool, &a_id).await?;
+    let after = character_bundle(&pool, &b_id).await?;
+    Ok(diff_bundles(&before, &after))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn clone_character(
+    app: AppHandle,
+    character_id: String,
+    new_name: String,
+) -> AppResult<String> {
+    let pool = db::pool(&app).await?;
+    clone_character_rows(&pool, &character_id, &new_name).await
+}
+
+/// Merges one character into another; see [`merge_character_rows`].
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn merge_characters(
+    app: AppHandle,
+    primary_id: String,
+    secondary_id: String,
+    strategy: Option<MergeStrategy>,
+    remove_secondary: Option<bool>,
+) -> AppResult<MergeReport> {
+    let pool = db::pool(&app).await?;
+    merge_character_rows(
+        &pool,
+        &primary_id,
+        &secondary_id,
+        strategy.unwrap_or_default(),
+        remove_secondary.unwrap_or(false),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn bundles_character_with_nested_assets() {
+        let pool = db::seeded_chain_pool().await;
+        let bundle = character_bundle(&pool, "hero").await.unwrap();
+        assert_eq!(bundle["schema_version"], CHARACTER_BUNDLE_VERSION);
+        assert_eq!(bundle["character"]["name"], "Kaede");
+        let jump = &bundle["jumps"][0];
+        assert_eq!(jump["title"], "Naruto");
+        assert_eq!(jump["perks"][0]["name"], "Shadow Clone");
+        assert_eq!(jump["drawbacks"][0]["cost"], -200);
+        assert_eq!(jump["items"], json!([]));
+        assert!(jump["perks"][0].get("jump_id").is_none());
+
+        let missing = character_bundle(&pool, "nobody").await.unwrap_err();
+        assert_eq!(
+            missing,
+            AppError::NotFound("Character nobody not found".into())
+        );
+    }
+
+    fn without_ids(value: &Value) -> Value {
+        match value {
+            Value::Object(fields) => Value::Object(
+                fields
+                    .iter()
+                    .filter(|(key, _)| key.as_str() != "id")
+                    .map(|(key, value)| (key.clone(), without_ids(value)))
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(items.iter().map(without_ids).collect()),
+            other => other.clone(),
+        }
+    }
+
+    #[tokio::test]
+    async fn import_round_trips_an_export() {
+        let pool = db::seeded_chain_pool().await;
+        let original = character_bundle(&pool, "hero").await.unwrap();
+
+        let imported_id = import_bundle(&pool, &original).await.unwrap();
+        assert_ne!(imported_id, "hero");
+        assert_eq!(imported_id.len(), 36);
+
+        let imported = character_bundle(&pool, &imported_id).await.unwrap();
+        assert_eq!(
+            without_ids(&imported["character"]),
+            without_ids(&original["character"])
+        );
+        // Jumps belong to the chain, so the import adds none.
+        assert_eq!(imported["jumps"], original["jumps"]);
+        let rows = db::fetch_rows(&pool, "SELECT COUNT(*) AS n FROM jump_assets", &[])
+            .await
+            .unwrap();
+        assert_eq!(rows, vec![json!({ "n": 2 })]);
+    }
+
+    #[tokio::test]
+    async fn clone_is_independent_of_the_original() {
+        let pool = db::seeded_chain_pool().await;
+        let clone_id = clone_character_rows(&pool, "hero", " Kaede (alt) ")
+            .await
+            .unwrap();
+        assert_eq!(clone_id.len(), 36);
+
+        let clone = character_bundle(&pool, &clone_id).await.unwrap();
+        assert_eq!(clone["character"]["name"], "Kaede (alt)");
+        // The clone shares the chain's jumps instead of adding copies.
+        let jumps = db::fetch_rows(&pool, "SELECT COUNT(*) AS n FROM jumps", &[])
+            .await
+            .unwrap();
+        assert_eq!(jumps, vec![json!({ "n": 1 })]);
+        let assets = db::fetch_rows(&pool, "SELECT COUNT(*) AS n FROM jump_assets", &[])
+            .await
+            .unwrap();
+        assert_eq!(assets, vec![json!({ "n": 2 })]);
+
+        db::run_batch(
+            &pool,
+            &[(
+                "UPDATE character_profiles SET species = 'Fox' WHERE id = ?".into(),
+                Some(vec![json!(clone_id)]),
+            )],
+        )
+        .await
+        .unwrap();
+        let original = db::fetch_rows(
+            &pool,
+            "SELECT species FROM character_profiles WHERE id = 'hero'",
+            &[],
+        )
+        .await
+        .unwrap();
+        assert_eq!(original, vec![json!({ "species": null })]);
+
+        assert_eq!(
+            clone_character_rows(&pool, "nobody", "Ghost")
+                .await
+                .unwrap_err()
+                .code(),
+            "not_found"
+        );
+        assert_eq!(
+            clone_character_rows(&pool, "hero", "  ")
+                .await
+                .unwrap_err()
+                .code(),
+            "validation"
+        );
+    }
+
+    #[tokio::test]
+    async fn diff_reports_assets_fields_and_budget_deltas() {
+        let pool = db::seeded_chain_pool().await;
+        let before = character_bundle(&pool, "hero").await.unwrap();
+        let clone_id = clone_character_rows(&pool, "hero", "Kaede (alt)")
+            .await
+            .unwrap();
+        let original = character_bundle(&pool, "hero").await.unwrap();
+        let clone = character_bundle(&pool, &clone_id).await.unwrap();
+        let diff = diff_bundles(&original, &clone);
+        assert_eq!(
+            diff.profile,
+            vec![FieldChange {
+                field: "name".into(),
+                before: json!("Kaede"),
+                after: json!("Kaede (alt)"),
+            }]
+        );
+        assert!(diff.jumps.is_empty());
+
+        let mut after = before.clone();
+        after["jumps"][0]["cp_budget"] = json!(1000);
+        after["jumps"][0]["perks"][0]["cost"] = json!(600);
+        after["jumps"][0]["items"] = json!([{ "name": "Kunai", "cost": 50 }]);
+        after["jumps"][0]["drawbacks"] = json!([]);
+        after["jumps"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({ "title": "Bleach", "cp_budget": 1000 }));
+
+        let diff = diff_bundles(&before, &after);
+        assert!(diff.profile.is_empty());
+        assert_eq!(diff.jumps.len(), 2);
+        let naruto = &diff.jumps[0];
+        assert_eq!(naruto.title, "Naruto");
+        assert_eq!(naruto.change, JumpChange::Changed);
+        assert_eq!(
+            naruto.fields,
+            vec![FieldChange {
+                field: "cp_budget".into(),
+                before: json!(0),
+                after: json!(1000),
+            }]
+        );
+        assert_eq!(naruto.assets["items"].added, vec!["Kunai"]);
+        assert_eq!(naruto.assets["drawbacks"].removed, vec!["Rival"]);
+        assert_eq!(naruto.assets["perks"].changed[0].fields[0].field, "cost");
+        // Negative costs price at zero, so losing the drawback costs nothing.
+        assert_eq!(naruto.budget_delta, 1000.0);
+        assert_eq!(naruto.spent_delta, 250.0);
+        let bleach = &diff.jumps[1];
+        assert_eq!(bleach.change, JumpChange::Added);
+        assert_eq!(bleach.budget_delta, 1000.0);
+
+        assert_eq!(diff_bundles(&after, &after).jumps, Vec::new());
+    }
+
+    #[tokio::test]
+    async fn import_rejects_future_versions_without_writing() {
+        let pool = db::seeded_chain_pool().await;
+        let mut bundle = character_bundle(&pool, "hero").await.unwrap();
+        bundle["schema_version"] = json!(CHARACTER_BUNDLE_VERSION + 1);
+
+        let error = import_bundle(&pool, &bundle).await.unwrap_err();
+        assert_eq!(error.code(), "validation");
+        assert!(
+            error.message().contains("newer than this app supports"),
+            "{error}"
+        );
+
+        let rows = db::fetch_rows(
+            &pool,
+            "SELECT COUNT(*) AS total FROM character_profiles",
+            &[],
+        )
+        .await
+        .unwrap();
+        assert_eq!(rows, vec![json!({ "total": 1 })]);
+    }
+
+    #[tokio::test]
+    async fn inspect_summarizes_and_flags_unknown_fields() {
+        let pool = db::seeded_chain_pool().await;
+        let mut bundle = character_bundle(&pool, "hero").await.unwrap();
+
+        let summary = inspect_bundle(&pool, &bundle).await.unwrap();
+        assert_eq!(
+            summary,
+            BundleSummary {
+                schema_version: u64::from(CHARACTER_BUNDLE_VERSION),
+                character_name: Some("Kaede".into()),
+                jump_count: 1,
+                perk_count: 1,
+                warnings: Vec::new(),
+            }
+        );
+
+        bundle["exported_by"] = json!("friend");
+        bundle["character"]["aura"] = json!("blue");
+        bundle["jumps"][0]["perks"][0]["rank"] = json!(3);
+        let summary = inspect_bundle(&pool, &bundle).await.unwrap();
+        assert_eq!(
+            summary.warnings,
+            vec![
+                "Unknown bundle field \"exported_by\" will be ignored",
+                "Unknown character field \"aura\" will be ignored",
+                "Unknown asset field \"rank\" will be ignored",
+            ]
+        );
+
+        // Jumps match by id, then by title; the rest are left out.
+        bundle["jumps"][0]["id"] = json!("elsewhere");
+        bundle["jumps"][0]["title"] = json!("naruto");
+        bundle["jumps"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({ "title": "Bleach" }));
+        let summary = inspect_bundle(&pool, &bundle).await.unwrap();
+        assert_eq!(
+            summary.warnings.last().unwrap(),
+            "Jumps not in this chain will not be imported: Bleach"
+        );
+
+        bundle["jumps"] = json!(["not a jump"]);
+        assert_eq!(
+            inspect_bundle(&pool, &bundle).await.unwrap_err().code(),
+            "validation"
+        );
+
+        let rows = db::fetch_rows(
+            &pool,
+            "SELECT COUNT(*) AS total FROM character_profiles",
+            &[],
+        )
+        .await
+        .unwrap();
+        assert_eq!(rows, vec![json!({ "total": 1 })]);
+    }
+
+    #[test]
+    fn renders_markdown_grouped_by_category() {
+        let bundle = json!({
+            "schema_version": CHARACTER_BUNDLE_VERSION,
+            "character": { "name": "Kaede" },
+            "jumps": [{
+                "title": "Naruto",
+                "world": "Elemental Nations",
+                "cp_budget": 1000,
+                "origins": [{ "name": "Drop-In", "asset_type": "origin", "cost": 0 }],
+                "perks": [
+                    { "name": "Shadow Clone", "asset_type": "perk", "category": "Ninjutsu", "cost": 400 },
+                    { "name": "Lucky_Star", "asset_type": "perk", "cost": 100, "freebie": 1 },
+                    { "name": "Rasengan", "asset_type": "perk", "category": "Ninjutsu", "cost": 300,
+                      "discounted": 1, "notes": "Needs a clone.\nTwo hands." }
+                ],
+                "items": [{ "name": "Kunai", "asset_type": "item", "cost": 50, "quantity": 4 }],
+                "drawbacks": [{ "name": "Rival", "asset_type": "drawback", "cost": 200 }]
+            }, {
+                "title": "{perks}",
+                "perks": []
+            }]
+        });
+
+        let markdown = render_markdown(
+            &bundle,
+            &MarkdownOptions {
+                include_notes: true,
+                ..MarkdownOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            markdown,
+            "# Kaede\n\n\
+             ## Naruto\n\n\
+             *Elemental Nations*\n\n\
+             *750 / 1200 CP spent*\n\n\
+             **Origin:** Drop-In (0 CP)\n\n\
+             ### Perks\n\n\
+             #### Ninjutsu\n\n\
+             - **Shadow Clone** (400 CP)\n\
+             - **Rasengan** (150 CP, discounted)\n  Needs a clone.\n  Two hands.\n\n\
+             #### Other Perks\n\n\
+             - **Lucky\\_Star** (free)\n\n\
+             ### Items\n\n\
+             - **Kunai** (200 CP, \u{d7}4)\n\n\
+             ### Drawbacks\n\n\
+             - **Rival** (+200 CP)\n\n\
+             ## {perks}\n\n\
+             *0 / 0 CP spent*\n"
+        );
+
+        let options = MarkdownOptions {
+            include_costs: false,
+            jump_template: Some("{title}: {items}".into()),
+            ..MarkdownOptions::default()
+        };
+        let markdown = render_markdown(&bundle, &options).unwrap();
+        assert_eq!(
+            markdown,
+            "# Kaede\n\nNaruto: ### Items\n\n- **Kunai**\n\n{perks}:\n"
+        );
+
+        let options = MarkdownOptions {
+            jump_template: Some("{title} {cost}".into()),
+            ..MarkdownOptions::default()
+        };
+        let error = render_markdown(&bundle, &options).unwrap_err();
+        assert_eq!(error.message(), "Unknown template placeholders: cost");
+    }
+
+    #[tokio::test]
+    async fn merge_folds_secondary_profile_into_primary() {
+        let pool = db::seeded_chain_pool().await;
+        db::run_batch(
+            &pool,
+            &[
+                (
+                    r#"UPDATE character_profiles SET species = 'Human',
+                         traits_json = '[{"name":"Brave"}]',
+                         attributes_json = '{"Strength":"B"}'
+                       WHERE id = 'hero'"#
+                        .into(),
+                    None,
+                ),
+                (
+                    r#"INSERT INTO character_profiles
+                         (id, name, species, homeland, traits_json, attributes_json)
+                       VALUES ('alt', 'Rin', 'Elf', 'Konoha',
+                         '[{"name":"brave"},{"name":"Swift"}]',
+                         '{"Strength":"A","Speed":"S"}')"#
+                        .into(),
+                    None,
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let error = merge_character_rows(&pool, "hero", "hero", MergeStrategy::Skip, false)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "validation");
+
+        let report = merge_character_rows(&pool, "hero", "alt", MergeStrategy::Rename, true)
+            .await
+            .unwrap();
+        let renamed: Vec<_> = report
+            .merged
+            .iter()
+            .filter_map(|entry| entry.renamed_to.as_deref())
+            .collect();
+        assert_eq!(renamed, ["brave (Rin)", "Strength (Rin)"]);
+        assert_eq!(report.merged.len(), 5);
+        assert_eq!(report.skipped, [MergedEntry::new("field", "species")]);
+        assert_eq!(report.shared_jumps, 1);
+        assert!(report.secondary_removed);
+        assert!(report.secondary_snapshot.is_some());
+
+        let mut conn = pool.acquire().await.unwrap();
+        let primary = profile(&mut conn, "hero").await.unwrap();
+        assert_eq!(text_field(primary.get("species")), Some("Human"));
+        assert_eq!(text_field(primary.get("homeland")), Some("Konoha"));
+        assert_eq!(
+            json_column(&primary, "traits_json").unwrap(),
+            json!([{ "name": "Brave" }, { "name": "brave (Rin)" }, { "name": "Swift" }])
+        );
+        assert_eq!(
+            json_column(&primary, "attributes_json").unwrap(),
+            json!({ "Strength": "B", "Strength (Rin)": "A", "Speed": "S" })
+        );
+        let error = profile(&mut conn, "alt").await.unwrap_err();
+        assert_eq!(error.code(), "not_found");
+        drop(conn);
+
+        // The secondary is in the recycle bin rather than gone.
+        let deleted = recycle::deleted_entries(&pool, "character").await.unwrap();
+        assert_eq!(deleted.len(), 1);
+        recycle::restore_entry(&pool, "character", "alt")
+            .await
+            .unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+        assert_eq!(
+            text_field(profile(&mut conn, "alt").await.unwrap().get("name")),
+            Some("Rin")
+        );
+    }
+}
@@ -0,0 +1,338 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, State, Window};
+use tauri_plugin_fs::FsExt;
+
+use crate::error::{AppError, AppResult};
+use crate::sanitize_line;
+
+const FILE_STREAM_EVENT: &str = "file-stream://lines";
+
+const DEFAULT_BATCH_LINES: usize = 200;
+/// Upper bound on lines per event so one batch never stalls the webview.
+const MAX_BATCH_LINES: usize = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum FileStreamPayload {
+    Lines {
+        lines: Vec<String>,
+        next_offset: u64,
+    },
+    Done {
+        next_offset: u64,
+    },
+    Cancelled {
+        next_offset: u64,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct FileStreamEvent<'a> {
+    stream_id: &'a str,
+    #[serde(flatten)]
+    payload: FileStreamPayload,
+}
+
+/// Cancellation flags for in-flight `stream_file_lines` calls, keyed by the
+/// caller-chosen `stream_id`.
+#[derive(Default)]
+pub(crate) struct FileStreamState {
+    streams: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl FileStreamState {
+    /// Flags every in-flight stream for cancellation and returns their ids.
+    pub(crate) fn cancel_all(&self) -> Vec<String> {
+        let Ok(streams) = self.streams.lock() else {
+            return Vec::new();
+        };
+        for flag in streams.values() {
+            flag.store(true, Ordering::SeqCst);
+        }
+        streams.keys().cloned().collect()
+    }
+}
+
+fn emit_stream(window: &Window, stream_id: &str, payload: FileStreamPayload) {
+    let event = FileStreamEvent { stream_id, payload };
+    let _ = window.emit(FILE_STREAM_EVENT, &event);
+}
+
+/// Reads `path` from raw line `offset`, handing batches of up to
+/// `batch_size` cleaned lines to `on_batch` along with the raw line offset
+/// to resume from. Blank lines are dropped by `sanitize_line` but still
+/// counted, so offsets always refer to lines in the file. Cancellation is
+/// checked on every line, flushing the partial batch, so a run of dropped
+/// lines cannot hold it off. Returns the final offset and whether the read
+/// ran to the end.
+fn read_lines(
+    path: &Path,
+    offset: u64,
+    batch_size: usize,
+    cancelled: &AtomicBool,
+    mut on_batch: impl FnMut(Vec<String>, u64),
+) -> AppResult<(u64, bool)> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
+    let mut line_number = 0u64;
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut buffer = Vec::new();
+
+    loop {
+        if cancelled.load(Ordering::SeqCst) {
+            let resume = line_number.max(offset);
+            if !batch.is_empty() {
+                on_batch(std::mem::take(&mut batch), resume);
+            }
+            return Ok((resume, false));
+        }
+        buffer.clear();
+        let read = reader.read_until(b'\n', &mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        line_number += 1;
+        if line_number <= offset {
+            continue;
+        }
+
+        if let Some(line) = sanitize_line(std::mem::take(&mut buffer), true) {
+            batch.push(line);
+        }
+        if batch.len() >= batch_size {
+            on_batch(std::mem::take(&mut batch), line_number);
+        }
+    }
+
+    if !batch.is_empty() {
+        on_batch(batch, line_number.max(offset));
+    }
+    Ok((line_number.max(offset), true))
+}
+
+/// Streams a text file to the frontend in line batches on
+/// `file-stream://lines`, finishing with a `done`, `cancelled` or `error`
+/// event. The path must fall inside the fs scope.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn stream_file_lines(
+    window: Window,
+    state: State<'_, FileStreamState>,
+    stream_id: String,
+    path: String,
+    offset: Option<u64>,
+    batch_size: Option<usize>,
+) -> AppResult<()> {
+    let requested = PathBuf::from(&path);
+    let resolved = requested.canonicalize().unwrap_or(requested);
+    if !window.fs_scope().is_allowed(&resolved) {
+        return Err(AppError::Validation(format!(
+            "Access to {path} is not permitted"
+        )));
+    }
+    stream_lines(window, &state, stream_id, resolved, offset, batch_size).await
+}
+
+/// Streams `path` as `file-stream://lines` events under `stream_id`. The
+/// caller has already checked that `path` may be read.
+pub(crate) async fn stream_lines(
+    window: Window,
+    state: &FileStreamState,
+    stream_id: String,
+    path: PathBuf,
+    offset: Option<u64>,
+    batch_size: Option<usize>,
+) -> AppResult<()> {
+    let batch_size = batch_size
+        .unwrap_or(DEFAULT_BATCH_LINES)
+        .clamp(1, MAX_BATCH_LINES);
+
+    let cancelled = Arc::new(AtomicBool::new(false));
+    {
+        let mut streams = state
+            .streams
+            .lock()
+            .map_err(|_| AppError::state("file stream"))?;
+        if streams.contains_key(&stream_id) {
+            return Err(AppError::AlreadyRunning(format!(
+                "{stream_id} is already streaming"
+            )));
+        }
+        streams.insert(stream_id.clone(), Arc::clone(&cancelled));
+    }
+
+    let batch_window = window.clone();
+    let batch_id = stream_id.clone();
+    let outcome = tauri::async_runtime::spawn_blocking(move || {
+        read_lines(
+            &path,
+            offset.unwrap_or(0),
+            batch_size,
+            &cancelled,
+            |lines, next_offset| {
+                emit_stream(
+                    &batch_window,
+                    &batch_id,
+                    FileStreamPayload::Lines { lines, next_offset },
+                );
+            },
+        )
+    })
+    .await
+    .map_err(AppError::from)
+    .and_then(|result| result);
+
+    if let Ok(mut streams) = state.streams.lock() {
+        streams.remove(&stream_id);
+    }
+
+    match outcome {
+        Ok((next_offset, true)) => {
+            emit_stream(&window, &stream_id, FileStreamPayload::Done { next_offset });
+            Ok(())
+        }
+        Ok((next_offset, false)) => {
+            emit_stream(
+                &window,
+                &stream_id,
+                FileStreamPayload::Cancelled { next_offset },
+            );
+            Ok(())
+        }
+        Err(error) => {
+            emit_stream(
+                &window,
+                &stream_id,
+                FileStreamPayload::Error {
+                    message: error.to_string(),
+                },
+            );
+            Err(error)
+        }
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn cancel_file_stream(
+    state: State<'_, FileStreamState>,
+    stream_id: String,
+) -> AppResult<()> {
+    let streams = state
+        .streams
+        .lock()
+        .map_err(|_| AppError::state("file stream"))?;
+    if let Some(flag) = streams.get(&stream_id) {
+        flag.store(true, Ordering::SeqCst);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(path: &Path, offset: u64, batch_size: usize) -> (Vec<(Vec<String>, u64)>, u64) {
+        let mut batches = Vec::new();
+        let (end, finished) = read_lines(
+            path,
+            offset,
+            batch_size,
+            &AtomicBool::new(false),
+            |lines, next| batches.push((lines, next)),
+        )
+        .unwrap();
+        assert!(finished);
+        (batches, end)
+    }
+
+    #[test]
+    fn batches_lines_from_an_offset() {
+        let path =
+            std::env::temp_dir().join(format!("jumpchain-stream-{}.log", std::process::id()));
+        std::fs::write(&path, "one\r\ntwo\n\nthree\nfour\nfive").unwrap();
+
+        let (batches, end) = collect(&path, 0, 2);
+        assert_eq!(
+            batches,
+            vec![
+                (vec!["one".to_string(), "two".to_string()], 2),
+                (vec!["three".to_string(), "four".to_string()], 5),
+                (vec!["five".to_string()], 6),
+            ]
+        );
+        assert_eq!(end, 6);
+
+        let (batches, end) = collect(&path, 4, 10);
+        assert_eq!(
+            batches,
+            vec![(vec!["four".to_string(), "five".to_string()], 6)]
+        );
+        assert_eq!(end, 6);
+
+        let (batches, end) = collect(&path, 50, 10);
+        assert!(batches.is_empty());
+        assert_eq!(end, 50);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn stops_after_the_batch_that_sees_cancellation() {
+        let path =
+            std::env::temp_dir().join(format!("jumpchain-cancel-{}.log", std::process::id()));
+        std::fs::write(&path, "a\nb\nc\nd\n").unwrap();
+
+        let cancelled = AtomicBool::new(false);
+        let mut seen = Vec::new();
+        let (next, finished) = read_lines(&path, 0, 1, &cancelled, |lines, _| {
+            seen.extend(lines);
+            cancelled.store(true, Ordering::SeqCst);
+        })
+        .unwrap();
+        assert!(!finished);
+        assert_eq!((seen, next), (vec!["a".to_string()], 1));
+
+        // Blank lines never fill a batch, yet cancellation still stops the
+        // read at once.
+        std::fs::write(&path, "\n".repeat(1000)).unwrap();
+        let (next, finished) = read_lines(&path, 3, 10, &cancelled, |_, _| {
+            panic!("no lines were read")
+        })
+        .unwrap();
+        assert_eq!((next, finished), (3, false));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,614 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use tauri::AppHandle;
+
+use crate::budget;
+use crate::db;
+use crate::error::{AppError, AppResult};
+use crate::export::NEW_UUID_SQL;
+
+/// Same shape as the frontend's `new Date().toISOString()`, which writes the
+/// other `updated_at` values.
+pub(crate) const ISO_NOW_SQL: &str = "strftime('%Y-%m-%dT%H:%M:%fZ', 'now')";
+
+/// Names the ids that keep `ordered` from being exactly `current`
+/// rearranged, or `None` when it is.
+fn ordering_mismatch(current: &[String], ordered: &[String]) -> Option<String> {
+    let mut seen = HashSet::new();
+    let duplicates: Vec<&str> = ordered
+        .iter()
+        .filter(|id| !seen.insert(id.as_str()))
+        .map(String::as_str)
+        .collect();
+    let missing: Vec<&str> = current
+        .iter()
+        .filter(|id| !seen.contains(id.as_str()))
+        .map(String::as_str)
+        .collect();
+    let known: HashSet<&str> = current.iter().map(String::as_str).collect();
+    let extra: Vec<&str> = ordered
+        .iter()
+        .filter(|id| !known.contains(id.as_str()))
+        .map(String::as_str)
+        .collect();
+
+    let problems: Vec<String> = [
+        ("duplicated", duplicates),
+        ("missing", missing),
+        ("not in this jump", extra),
+    ]
+    .into_iter()
+    .filter(|(_, ids)| !ids.is_empty())
+    .map(|(label, ids)| format!("{label}: {}", ids.join(", ")))
+    .collect();
+    (!problems.is_empty()).then(|| problems.join("; "))
+}
+
+/// Rewrites `sort_order` for every `asset_type` asset of a jump in one
+/// transaction. `ordered_ids` must list each of them exactly once, so a
+/// stale list from the UI can never leave the order half applied.
+pub(crate) async fn reorder_assets(
+    pool: &SqlitePool,
+    jump_id: &str,
+    asset_type: &str,
+    ordered_ids: &[String],
+) -> AppResult<Vec<String>> {
+    let mut tx = pool.begin().await?;
+    let current: Vec<String> = sqlx::query_scalar(
+        "SELECT id FROM jump_assets
+         WHERE jump_id = ? AND asset_type = ? AND deleted_at IS NULL",
+    )
+    .bind(jump_id)
+    .bind(asset_type)
+    .fetch_all(&mut *tx)
+    .await?;
+    if let Some(mismatch) = ordering_mismatch(&current, ordered_ids) {
+        return Err(AppError::Validation(format!(
+            "Order does not match the jump's {asset_type} assets ({mismatch})"
+        )));
+    }
+
+    let update =
+        format!("UPDATE jump_assets SET sort_order = ?, updated_at = {ISO_NOW_SQL} WHERE id = ?");
+    for (index, id) in ordered_ids.iter().enumerate() {
+        sqlx::query(&update)
+            .bind(index as i64)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let reordered = sqlx::query_scalar(
+        "SELECT id FROM jump_assets
+         WHERE jump_id = ? AND asset_type = ? AND deleted_at IS NULL
+         ORDER BY sort_order, created_at",
+    )
+    .bind(jump_id)
+    .bind(asset_type)
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(reordered)
+}
+
+/// Applies a drag-and-drop order to a jump's perks and returns the stored
+/// order.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn reorder_perks(
+    app: AppHandle,
+    jump_id: String,
+    ordered_ids: Vec<String>,
+) -> AppResult<Vec<String>> {
+    let pool = db::pool(&app).await?;
+    reorder_assets(&pool, &jump_id, "perk", &ordered_ids).await
+}
+
+/// Layout of a shared jump template: comma-separated values, or a markdown
+/// pipe table whose first `# ` heading names the jump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TemplateFormat {
+    Csv,
+    Markdown,
+}
+
+/// `asset_type` values a template row may use in its `type` column; rows
+/// without one are perks.
+const TEMPLATE_ASSET_TYPES: [&str; 5] = ["origin", "perk", "item", "drawback", "companion"];
+
+/// Parse errors past this many are counted rather than listed.
+const MAX_TEMPLATE_ERRORS: usize = 20;
+
+const UNTITLED_JUMP: &str = "Imported jump";
+
+/// One purchase read from a template; `line` is 1-based.
+#[derive(Debug, PartialEq)]
+struct TemplateRow {
+    line: usize,
+    asset_type: String,
+    name: String,
+    cost: i64,
+    category: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+struct JumpTemplate {
+    title: Option<String>,
+    rows: Vec<TemplateRow>,
+}
+
+/// Splits CSV into records of fields, each tagged with the line it starts
+/// on. Quoted fields may hold commas, doubled quotes and line breaks.
+fn csv_records(content: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut start = 1;
+    let mut chars = content.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                fields.push(std::mem::take(&mut field));
+                if fields.iter().any(|field| !field.trim().is_empty()) {
+                    records.push((start, std::mem::take(&mut fields)));
+                }
+                fields.clear();
+                line += 1;
+                start = line;
+            }
+            '\n' => {
+                field.push(ch);
+                line += 1;
+            }
+            _ => field.push(ch),
+        }
+    }
+    if quoted {
+        return Err(format!("line {start}: a quoted field is never closed"));
+    }
+    fields.push(field);
+    if fields.iter().any(|field| !field.trim().is_empty()) {
+        records.push((start, fields));
+    }
+    Ok(records)
+}
+
+/// Cells of a markdown table row; `\|` stands for a literal pipe.
+fn table_cells(row: &str) -> Vec<String> {
+    let row = row.trim();
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let row = row.strip_suffix('|').unwrap_or(row);
+    let mut cells = vec![String::new()];
+    let mut chars = row.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars.peek() == Some(&'|') => {
+                chars.next();
+                cells.last_mut().unwrap().push('|');
+            }
+            '|' => cells.push(String::new()),
+            _ => cells.last_mut().unwrap().push(ch),
+        }
+    }
+    cells
+}
+
+fn is_table_separator(cells: &[String]) -> bool {
+    cells.iter().all(|cell| {
+        let cell = cell.trim().trim_matches(':');
+        !cell.is_empty() && cell.chars().all(|ch| ch == '-')
+    })
+}
+
+/// Table rows of a markdown template, skipping the header separator, plus
+/// the first `# ` heading as the jump title. Other prose is ignored.
+fn markdown_records(content: &str) -> (Option<String>, Vec<(usize, Vec<String>)>) {
+    let mut title = None;
+    let mut records = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if let Some(heading) = trimmed.strip_prefix("# ") {
+            title.get_or_insert_with(|| heading.trim().to_string());
+        } else if trimmed.starts_with('|') {
+            let cells = table_cells(trimmed);
+            if !is_table_separator(&cells) {
+                records.push((index + 1, cells));
+            }
+        }
+    }
+    (title, records)
+}
+
+/// Reads a cost such as `200`, `-100` or `400 CP`; blank means free.
+fn parse_cost(raw: &str) -> Option<i64> {
+    let raw = raw.trim();
+    let number = match raw.len().checked_sub(2) {
+        Some(split) if raw.is_char_boundary(split) && raw[split..].eq_ignore_ascii_case("cp") => {
+            raw[..split].trim_end()
+        }
+        _ => raw,
+    };
+    if number.is_empty() {
+        return Some(0);
+    }
+    number.strip_prefix('+').unwrap_or(number).parse().ok()
+}
+
+/// Parses a template whose first row is a header naming its columns:
+/// `name` and `cost` are required, `type` and `category` optional, and
+/// unknown columns are ignored. Every bad line is reported by number.
+fn parse_template(content: &str, format: TemplateFormat) -> AppResult<JumpTemplate> {
+    let (title, records) = match format {
+        TemplateFormat::Csv => (None, csv_records(content).map_err(AppError::Validation)?),
+        TemplateFormat::Markdown => markdown_records(content),
+    };
+    let mut records = records.into_iter();
+    let Some((_, header)) = records.next() else {
+        return Err(AppError::Validation("Template is empty".into()));
+    };
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|cell| cell.trim().eq_ignore_ascii_case(name))
+    };
+    let (Some(name_column), Some(cost_column)) = (column("name"), column("cost")) else {
+        return Err(AppError::Validation(
+            "Template header must name a \"name\" and a \"cost\" column".into(),
+        ));
+    };
+    let type_column = column("type");
+    let category_column = column("category");
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for (line, cells) in records {
+        let cell = |column: Option<usize>| {
+            column
+                .and_then(|index| cells.get(index))
+                .map_or("", |cell| cell.trim())
+        };
+        let name = cell(Some(name_column));
+        if name.is_empty() {
+            errors.push(format!("line {line}: the name is empty"));
+            continue;
+        }
+        let Some(cost) = parse_cost(cell(Some(cost_column))) else {
+            errors.push(format!(
+                "line {line}: cost \"{}\" is not a whole number",
+                cell(Some(cost_column))
+            ));
+            continue;
+        };
+        let asset_type = match cell(type_column).to_ascii_lowercase() {
+            kind if kind.is_empty() => "perk".to_string(),
+            kind if TEMPLATE_ASSET_TYPES.contains(&kind.as_str()) => kind,
+            kind => {
+                errors.push(format!(
+                    "line {line}: type \"{kind}\" is not one of {}",
+                    TEMPLATE_ASSET_TYPES.join(", ")
+                ));
+                continue;
+            }
+        };
+        let category = Some(cell(category_column))
+            .filter(|category| !category.is_empty())
+            .map(str::to_string);
+        rows.push(TemplateRow {
+            line,
+            asset_type,
+            name: name.to_string(),
+            cost,
+            category,
+        });
+    }
+
+    if !errors.is_empty() {
+        let extra = errors.len().saturating_sub(MAX_TEMPLATE_ERRORS);
+        errors.truncate(MAX_TEMPLATE_ERRORS);
+        if extra > 0 {
+            errors.push(format!("and {extra} more"));
+        }
+        return Err(AppError::Validation(format!(
+            "Template has errors: {}",
+            errors.join("; ")
+        )));
+    }
+    if rows.is_empty() {
+        return Err(AppError::Validation("Template has no rows".into()));
+    }
+    Ok(JumpTemplate { title, rows })
+}
+
+/// Creates a jump at the end of the chain holding the template's rows in
+/// one transaction, with `cp_spent` priced the way `validate_budget` does.
+/// Returns the new jump id.
+async fn import_template(
+    pool: &SqlitePool,
+    template: JumpTemplate,
+    title: Option<&str>,
+) -> AppResult<String> {
+    let title = title
+        .map(str::trim)
+        .filter(|title| !title.is_empty())
+        .or(template.title.as_deref())
+        .unwrap_or(UNTITLED_JUMP);
+    let (spent, _) = budget::spend_and_credit(template.rows.iter().map(|row| {
+        (
+            String::new(),
+            row.name.clone(),
+            row.asset_type.clone(),
+            row.cost,
+            1,
+            false,
+            false,
+        )
+    }));
+
+    let mut tx = pool.begin().await?;
+    let jump_id: String = sqlx::query_scalar(&format!(
+        "INSERT INTO jumps (id, title, sort_order, cp_spent)
+         VALUES ({NEW_UUID_SQL}, ?,
+                 (SELECT COALESCE(MAX(sort_order), -1) + 1 FROM jumps WHERE deleted_at IS NULL),
+                 ?)
+         RETURNING id"
+    ))
+    .bind(title)
+    .bind(spent.round() as i64)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let insert = format!(
+        "INSERT INTO jump_assets (id, jump_id, asset_type, name, category, cost, sort_order)
+         VALUES ({NEW_UUID_SQL}, ?, ?, ?, ?, ?, ?)"
+    );
+    for (sort_order, row) in template.rows.iter().enumerate() {
+        sqlx::query(&insert)
+            .bind(&jump_id)
+            .bind(&row.asset_type)
+            .bind(&row.name)
+            .bind(&row.category)
+            .bind(row.cost)
+            .bind(sort_order as i64)
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| AppError::Db(format!("Unable to import line {}: {err}", row.line)))?;
+    }
+    tx.commit().await?;
+    Ok(jump_id)
+}
+
+/// Imports a community jump list shared as CSV or a markdown table into a
+/// new jump. Nothing is written unless every row parses. `title` overrides
+/// the markdown heading.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn import_jump_from_template(
+    app: AppHandle,
+    content: String,
+    format: TemplateFormat,
+    title: Option<String>,
+) -> AppResult<String> {
+    let template = parse_template(&content, format)?;
+    let pool = db::pool(&app).await?;
+    import_template(&pool, template, title.as_deref()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::{apply_migrations, MIGRATIONS};
+
+    fn ids(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn ordering_mismatch_names_every_problem() {
+        let current = ids(&["a", "b", "c"]);
+        assert_eq!(ordering_mismatch(&current, &ids(&["c", "a", "b"])), None);
+        assert_eq!(
+            ordering_mismatch(&current, &ids(&["a", "a", "x"])),
+            Some("duplicated: a; missing: b, c; not in this jump: x".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn reorder_assets_rewrites_the_order_or_nothing() {
+        let pool = db::memory_pool().await;
+        apply_migrations(&pool, MIGRATIONS).await.unwrap();
+        db::run_batch(
+            &pool,
+            &[
+                (
+                    "INSERT INTO jumps (id, title) VALUES ('j1', 'Naruto')".into(),
+                    None,
+                ),
+                (
+                    "INSERT INTO jump_assets (id, jump_id, asset_type, name, sort_order)
+                     VALUES ('p1', 'j1', 'perk', 'Shadow Clone', 0),
+                            ('p2', 'j1', 'perk', 'Chakra Control', 1),
+                            ('p3', 'j1', 'perk', 'Sage Mode', 2),
+                            ('i1', 'j1', 'item', 'Kunai', 0)"
+                        .into(),
+                    None,
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let order = reorder_assets(&pool, "j1", "perk", &ids(&["p3", "p1", "p2"]))
+            .await
+            .unwrap();
+        assert_eq!(order, ids(&["p3", "p1", "p2"]));
+
+        let error = reorder_assets(&pool, "j1", "perk", &ids(&["p1", "i1"]))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "validation");
+        let stored: Vec<String> = sqlx::query_scalar(
+            "SELECT id FROM jump_assets WHERE asset_type = 'perk' ORDER BY sort_order",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(stored, ids(&["p3", "p1", "p2"]));
+    }
+
+    #[test]
+    fn csv_templates_handle_quotes_and_report_lines() {
+        let content = "name,cost,type,category\n\
+                       Shadow Clone,400,perk,Jutsu\n\
+                       \"Kunai, Set of 10\",50 CP,item,\n\
+                       \n\
+                       \"Sage \"\"Mode\"\"\",,,\n";
+        let template = parse_template(content, TemplateFormat::Csv).unwrap();
+        assert_eq!(template.title, None);
+        assert_eq!(
+            template.rows[1],
+            TemplateRow {
+                line: 3,
+                asset_type: "item".into(),
+                name: "Kunai, Set of 10".into(),
+                cost: 50,
+                category: None,
+            }
+        );
+        assert_eq!(template.rows[2].line, 5);
+        assert_eq!(template.rows[2].name, "Sage \"Mode\"");
+        assert_eq!(template.rows[2].asset_type, "perk");
+        assert_eq!(template.rows[2].cost, 0);
+
+        let error = parse_template(
+            "name,cost,type\nA,lots,perk\n,100,perk\nC,100,spell\n",
+            TemplateFormat::Csv,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Template has errors: line 2: cost \"lots\" is not a whole number; \
+             line 3: the name is empty; \
+             line 4: type \"spell\" is not one of origin, perk, item, drawback, companion"
+        );
+        assert_eq!(
+            parse_template("name,type\nA,perk\n", TemplateFormat::Csv)
+                .unwrap_err()
+                .code(),
+            "validation"
+        );
+        assert_eq!(
+            parse_template("name,cost\n\"A,1\n", TemplateFormat::Csv)
+                .unwrap_err()
+                .message(),
+            "line 2: a quoted field is never closed"
+        );
+    }
+
+    #[tokio::test]
+    async fn markdown_templates_import_into_a_new_jump() {
+        let pool = db::memory_pool().await;
+        apply_migrations(&pool, MIGRATIONS).await.unwrap();
+        db::run_batch(
+            &pool,
+            &[(
+                "INSERT INTO jumps (id, title, sort_order) VALUES ('j1', 'Naruto', 4)".into(),
+                None,
+            )],
+        )
+        .await
+        .unwrap();
+
+        let content = "# Bleach\n\
+                       \n\
+                       Perks and items for the Soul Society.\n\
+                       \n\
+                       | Name | Cost | Type | Category |\n\
+                       |:-----|-----:|------|----------|\n\
+                       | Bankai | 600 CP | perk | Zanpakuto |\n\
+                       | Hell \\| Butterfly | 100 | item | |\n\
+                       | Rival | 200 | drawback | |\n";
+        let template = parse_template(content, TemplateFormat::Markdown).unwrap();
+        assert_eq!(template.title.as_deref(), Some("Bleach"));
+        assert_eq!(template.rows[1].name, "Hell | Butterfly");
+        assert_eq!(template.rows[1].line, 8);
+
+        let jump_id = import_template(&pool, template, None).await.unwrap();
+        let jump = db::fetch_rows(
+            &pool,
+            "SELECT title, sort_order, cp_spent FROM jumps WHERE id = ?",
+            &[serde_json::json!(jump_id)],
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            jump,
+            vec![serde_json::json!({ "title": "Bleach", "sort_order": 5, "cp_spent": 700 })]
+        );
+        let assets: Vec<(String, String, i64, Option<String>)> = sqlx::query_as(
+            "SELECT asset_type, name, cost, category FROM jump_assets
+             WHERE jump_id = ? ORDER BY sort_order",
+        )
+        .bind(&jump_id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(assets.len(), 3);
+        assert_eq!(
+            assets[0],
+            (
+                "perk".into(),
+                "Bankai".into(),
+                600,
+                Some("Zanpakuto".into())
+            )
+        );
+
+        let renamed = parse_template(content, TemplateFormat::Markdown).unwrap();
+        let renamed_id = import_template(&pool, renamed, Some(" Bleach (TYBW) "))
+            .await
+            .unwrap();
+        let title: String = sqlx::query_scalar("SELECT title FROM jumps WHERE id = ?")
+            .bind(&renamed_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(title, "Bleach (TYBW)");
+    }
+}
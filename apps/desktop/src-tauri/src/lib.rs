@@ -20,18 +20,80 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+mod budget;
+mod checksum;
+mod db;
+mod error;
+mod export;
+mod file_stream;
+mod jumps;
+mod logging;
+mod migrations;
+mod ocr;
+mod pdf;
+mod preferences;
+mod recent;
+mod recycle;
+mod search;
+mod snapshots;
+mod test_output;
+mod thumbnails;
+mod transactions;
+mod workspace;
+
+use error::{AppError, AppResult};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::collections::HashSet;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-use tauri::{path::BaseDirectory, AppHandle, Emitter, Manager, Window, State};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{
+    path::BaseDirectory, AppHandle, DragDropEvent, Emitter, Manager, RunEvent, State, Window,
+    WindowEvent,
+};
 use tauri_plugin_dialog::{DialogExt, FilePath};
+use tauri_plugin_fs::FsExt;
 use tauri_plugin_shell::{process::CommandChild, process::CommandEvent, ShellExt};
+use test_output::{
+    CoverageFile, CoverageSummary, ReportFile, RunLog, SummaryParser, TestReportEntry, TestSummary,
+    WatchEvent, WatchIterations, DEFAULT_COVERAGE_SUMMARY,
+};
+use tokio::sync::{oneshot, watch};
+use tokio::time::Instant;
 
 const TEST_RUN_EVENT: &str = "devtools://test-run";
+const FILES_DROPPED_EVENT: &str = "files-dropped";
+/// Points the test runner at a workspace when the upward search cannot find
+/// one, e.g. in packaged builds or dev containers.
+const WORKSPACE_ENV: &str = "JUMPCHAIN_WORKSPACE";
+const RUNNER_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long the runner may stay silent before a heartbeat is sent.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+/// Log lines kept while output is paused; older ones are dropped past this.
+const PAUSED_LINE_CAPACITY: usize = 5000;
+/// How long shutdown waits for cancelled PDF jobs to emit their terminal
+/// events before the process exits regardless.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+/// Latest log lines kept per run for `get_recent_test_lines`.
+const RECENT_LINE_CAPACITY: usize = 1000;
+/// Lines per `LogBatch` when a paused backlog is released.
+const RESUME_BATCH_LINES: usize = 500;
+/// Frames kept per coalesced stack trace; deeper ones are only counted.
+const MAX_TRACE_FRAMES: usize = 50;
+const DEFAULT_TEST_RUNNER: &str = "npm";
+const DEFAULT_TEST_SCRIPT: &str = "test:full";
+const DEFAULT_WATCH_SCRIPT: &str = "test:watch";
+/// Package managers `run_full_test_suite` may launch. Anything else is
+/// rejected so the command cannot be used to start arbitrary programs.
+const ALLOWED_TEST_RUNNERS: &[&str] = &["npm", "pnpm", "yarn", "bun"];
+/// Environment the test runner is started with on top of the inherited one.
+/// Only these are reported in `started`, so nothing inherited (tokens,
+/// credentials) ever reaches the log.
+const TEST_ENV_OVERRIDES: &[(&str, &str)] = &[("FORCE_COLOR", "0"), ("npm_config_color", "false")];
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "lowercase")]
 enum LogLevel {
     Info,
@@ -46,17 +108,97 @@ enum LogSource {
     Stderr,
 }
 
+/// A `file:line[:column]` reference found in a line of runner output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct LogLocation {
+    file: String,
+    line: u32,
+    column: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct LogLine {
+    level: LogLevel,
+    message: String,
+    source: LogSource,
+    location: Option<LogLocation>,
+    /// Locations of the `at ...` frames when the line is a whole stack
+    /// trace; `location` is then the innermost one.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    frames: Vec<LogLocation>,
+}
+
+impl LogLine {
+    fn new(level: LogLevel, message: String, source: LogSource) -> Self {
+        let location = parse_location(&message);
+        Self {
+            level,
+            message,
+            source,
+            location,
+            frames: Vec::new(),
+        }
+    }
+
+    /// The line as written to the saved run log, e.g. `STDERR ERROR: ...`.
+    fn log_text(&self) -> String {
+        let source = match self.source {
+            LogSource::Stdout => "STDOUT",
+            LogSource::Stderr => "STDERR",
+        };
+        let level = match self.level {
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        };
+        format!("{source} {level}: {}", self.message)
+    }
+}
+
+/// Everything needed to reproduce a run: where it ran, the exact arguments
+/// and the environment the app set explicitly.
+#[derive(Debug, Clone, Serialize)]
+struct RunDescription {
+    runner: String,
+    command: String,
+    pid: u32,
+    workspace_dir: String,
+    args: Vec<String>,
+    env_overrides: BTreeMap<String, String>,
+}
+
 #[derive(Debug, Serialize)]
 #[serde(tag = "kind", rename_all = "snake_case")]
 enum TestRunPayload {
-    Started,
-    Log {
-        level: LogLevel,
-        message: String,
-        source: LogSource,
+    /// `iteration` counts the re-runs of a watch-mode process from 1 and is
+    /// null outside watch mode.
+    Started {
+        #[serde(flatten)]
+        run: RunDescription,
+        iteration: Option<u32>,
+    },
+    Log(LogLine),
+    /// Consecutive `Log` lines coalesced when `log_flush_ms` is set.
+    LogBatch {
+        lines: Vec<LogLine>,
     },
+    Summary(TestSummary),
+    /// Sent while the runner is alive but quiet; `elapsed_ms` is the time
+    /// since its last line of output.
+    Heartbeat {
+        elapsed_ms: u64,
+    },
+    Report {
+        results: Vec<TestReportEntry>,
+    },
+    /// Sent before `terminated` when the run was started with `coverage`
+    /// and wrote a summary.
+    Coverage(CoverageSummary),
+    /// With an `iteration`, one watch-mode re-run finished and the process
+    /// is still alive; without one, the process itself exited.
     Terminated {
         code: Option<i32>,
+        iteration: Option<u32>,
     },
     Error {
         message: String,
@@ -75,6 +217,23 @@ pub struct FilePickRequest {
     pub multiple: bool,
     pub directory: bool,
     pub filters: Vec<FileFilter>,
+    pub start_dir: Option<String>,
+    pub enforce_extensions: bool,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct FileMetadata {
+    pub size_bytes: u64,
+    pub modified_ms: Option<u64>,
+    pub is_dir: bool,
+    pub exists: bool,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct FileSaveRequest {
+    pub default_name: Option<String>,
+    pub filters: Vec<FileFilter>,
 }
 
 fn normalize_extensions(source: &[String]) -> Vec<String> {
@@ -85,244 +244,1742 @@ fn normalize_extensions(source: &[String]) -> Vec<String> {
         .collect()
 }
 
-fn paths_to_strings(paths: Vec<FilePath>) -> Result<Vec<String>, String> {
+/// Filters with their extensions normalized, dropping any left empty.
+fn dialog_filters(filters: &[FileFilter]) -> Vec<(String, Vec<String>)> {
+    filters
+        .iter()
+        .filter_map(|filter| {
+            let cleaned = normalize_extensions(&filter.extensions);
+            if cleaned.is_empty() {
+                None
+            } else {
+                Some((filter.name.clone().unwrap_or_default(), cleaned))
+            }
+        })
+        .collect()
+}
+
+/// Returns the selections whose names end in none of the filter extensions.
+/// A `*` filter, or no filters at all, accepts everything.
+fn rejected_selections(paths: &[String], filters: &[FileFilter]) -> Vec<String> {
+    let allowed: Vec<String> = filters
+        .iter()
+        .flat_map(|filter| normalize_extensions(&filter.extensions))
+        .collect();
+    if allowed.is_empty() || allowed.iter().any(|ext| ext == "*") {
+        return Vec::new();
+    }
+
+    paths
+        .iter()
+        .filter(|path| {
+            let name = PathBuf::from(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_ascii_lowercase())
+                .unwrap_or_default();
+            !allowed.iter().any(|ext| name.ends_with(&format!(".{ext}")))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Appends `extension` when the chosen file name has none, so a bare
+/// "my-build" typed into the dialog still saves as "my-build.json".
+fn with_default_extension(path: String, extension: Option<&str>) -> String {
+    match extension {
+        Some(ext) if PathBuf::from(&path).extension().is_none() => format!("{path}.{ext}"),
+        _ => path,
+    }
+}
+
+fn paths_to_strings(paths: Vec<FilePath>) -> AppResult<Vec<String>> {
     paths
         .into_iter()
         .map(|path| {
             path.simplified()
                 .into_path()
-                .map_err(|err| err.to_string())
+                .map_err(|err| AppError::Dialog(err.to_string()))
                 .map(|pb| pb.to_string_lossy().into_owned())
         })
         .collect()
 }
 
-fn sanitize_line(bytes: Vec<u8>) -> Option<String> {
+/// Keeps only the `.pdf` paths from a drop, in the order they were dropped.
+fn dropped_pdf_paths(paths: &[PathBuf]) -> Vec<String> {
+    paths
+        .iter()
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+        })
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Forwards PDFs dropped onto a window to the frontend, which decides
+/// whether to index them. Drops with no PDFs are ignored silently.
+fn handle_window_event(window: &Window, event: &WindowEvent) {
+    if let WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) = event {
+        let pdfs = dropped_pdf_paths(paths);
+        if !pdfs.is_empty() {
+            let scope = window.fs_scope();
+            for pdf in &pdfs {
+                let _ = scope.allow_file(pdf);
+            }
+            let _ = window.emit(FILES_DROPPED_EVENT, &pdfs);
+        }
+    }
+}
+
+/// CSI sequences (colors, cursor moves, line erases), OSC sequences
+/// (titles, hyperlinks) and the remaining two-byte escapes.
+const ANSI_PATTERN: &str = r"\x1b(?:\[[0-?]*[ -/]*[@-~]|\][^\x07\x1b]*(?:\x07|\x1b\\)|[@-_])";
+
+/// Drops ANSI escapes and any other control characters except tabs.
+fn strip_ansi(text: &str) -> String {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| Regex::new(ANSI_PATTERN).expect("ANSI pattern is valid"));
+    pattern
+        .replace_all(text, "")
+        .chars()
+        .filter(|c| *c == '\t' || !c.is_control())
+        .collect()
+}
+
+/// Turns one raw output line into log text, or `None` when nothing is
+/// left. Progress bars redraw themselves with `\r`, so only the last
+/// non-blank rewrite of a line is kept, with escapes stripped. `raw` only
+/// trims the line ending, for debugging the runner's own output.
+fn sanitize_line(bytes: Vec<u8>, raw: bool) -> Option<String> {
     let text = String::from_utf8(bytes).ok()?;
-    let cleaned = text.trim_end_matches(['\r', '\n']);
+    let trimmed = text.trim_end_matches(['\r', '\n']);
+    let cleaned = if raw {
+        trimmed.to_string()
+    } else {
+        trimmed
+            .rsplit('\r')
+            .map(strip_ansi)
+            .find(|segment| !segment.trim().is_empty())
+            .unwrap_or_default()
+    };
     if cleaned.is_empty() {
         None
     } else {
-        Some(cleaned.to_string())
+        Some(cleaned)
     }
 }
 
+/// Matches `src/foo.test.ts:42:10`, `C:\app\foo.test.ts:42` and the like.
+/// The extension has to start with a letter so hosts such as
+/// `127.0.0.1:3000` are not taken for files.
+const LOCATION_PATTERN: &str = r"(?P<file>(?:[A-Za-z]:)?[\w.@~/\\-]*[\w-]\.[A-Za-z][A-Za-z0-9]*):(?P<line>\d+)(?::(?P<column>\d+))?";
+
+/// First file location mentioned in `message`, if any.
+fn parse_location(message: &str) -> Option<LogLocation> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern =
+        PATTERN.get_or_init(|| Regex::new(LOCATION_PATTERN).expect("location pattern is valid"));
+    let captures = pattern.captures(message)?;
+    Some(LogLocation {
+        file: captures["file"].to_string(),
+        line: captures["line"].parse().ok()?,
+        column: captures
+            .name("column")
+            .and_then(|column| column.as_str().parse().ok()),
+    })
+}
+
 fn classify_level(source: LogSource, message: &str) -> LogLevel {
     if matches!(source, LogSource::Stderr) {
         return LogLevel::Error;
     }
 
-    let upper = message.to_ascii_uppercase();
-    if upper.contains("FAIL") || upper.contains("ERROR") {
-        LogLevel::Error
-    } else if upper.contains("WARN") {
-        LogLevel::Warn
+    // Runner result markers decide the level outright, so a passing test
+    // titled "handles error case" stays informational.
+    let trimmed = message.trim_start();
+    if trimmed.starts_with(['✓', '✔', '√', '↓', '○']) {
+        return LogLevel::Info;
+    }
+    if trimmed.starts_with(['×', '✗', '✘']) {
+        return LogLevel::Error;
+    }
+
+    let mut level = LogLevel::Info;
+    for token in message.split_whitespace() {
+        let word = token.trim_matches(|ch: char| !ch.is_alphanumeric());
+        // File paths such as `src/error.test.ts` are not verdicts.
+        if word.is_empty() || word.contains(['/', '\\', '.']) {
+            continue;
+        }
+        if is_error_word(word) || (token.ends_with(':') && word.ends_with("Error")) {
+            return LogLevel::Error;
+        }
+        if matches!(
+            word.to_ascii_uppercase().as_str(),
+            "WARN" | "WARNS" | "WARNING" | "WARNINGS"
+        ) {
+            level = LogLevel::Warn;
+        }
+    }
+    level
+}
+
+/// Matches the line a JS error is printed with, such as `TypeError: ...`,
+/// `AssertionError [ERR_ASSERTION]: ...` or `Uncaught Error: ...`.
+const ERROR_HEADER_PATTERN: &str =
+    r"^\s*(?:Uncaught\s+)?(?:[A-Za-z_$][\w$.]*)?(?:Error|Exception)(?:\s+\[[\w-]+\])?(?::\s|:?$)";
+
+fn is_error_header(message: &str) -> bool {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN
+        .get_or_init(|| Regex::new(ERROR_HEADER_PATTERN).expect("error header pattern is valid"))
+        .is_match(message)
+}
+
+/// An indented `at ...` line of a JS stack trace.
+fn is_stack_frame(message: &str) -> bool {
+    let trimmed = message.trim_start();
+    trimmed.len() < message.len() && trimmed.starts_with("at ")
+}
+
+/// An error header and the frames seen under it so far.
+#[derive(Debug)]
+struct HeldTrace {
+    source: LogSource,
+    header: String,
+    frames: Vec<String>,
+    locations: Vec<LogLocation>,
+    omitted: usize,
+}
+
+impl HeldTrace {
+    fn push_frame(&mut self, frame: String) {
+        if self.frames.len() >= MAX_TRACE_FRAMES {
+            self.omitted += 1;
+            return;
+        }
+        if let Some(location) = parse_location(&frame) {
+            self.locations.push(location);
+        }
+        self.frames.push(frame);
+    }
+
+    /// A header nothing followed is sent as the ordinary line it was.
+    fn into_line(self) -> LogLine {
+        if self.frames.is_empty() {
+            let level = classify_level(self.source, &self.header);
+            return LogLine::new(level, self.header, self.source);
+        }
+        let mut message = self.header;
+        for frame in &self.frames {
+            message.push('\n');
+            message.push_str(frame);
+        }
+        if self.omitted > 0 {
+            message.push_str(&format!("\n    ... {} more frames", self.omitted));
+        }
+        LogLine {
+            level: LogLevel::Error,
+            message,
+            source: self.source,
+            location: self.locations.first().cloned(),
+            frames: self.locations,
+        }
+    }
+}
+
+/// Coalesces a stack trace into one log line instead of one per frame. An
+/// error header is held back until the next line shows whether frames
+/// follow; the trace goes out once a line that is not a frame arrives, or
+/// when [`StackTraces::finish`] is called because the output went quiet.
+#[derive(Debug, Default)]
+struct StackTraces {
+    held: Option<HeldTrace>,
+}
+
+impl StackTraces {
+    /// Takes one line of output and returns the lines now ready to send.
+    fn observe(&mut self, source: LogSource, message: String) -> Vec<LogLine> {
+        if let Some(held) = self.held.as_mut() {
+            if is_stack_frame(&message) {
+                held.push_frame(message);
+                return Vec::new();
+            }
+        }
+        let mut ready: Vec<LogLine> = self.finish().into_iter().collect();
+        if is_error_header(&message) {
+            self.held = Some(HeldTrace {
+                source,
+                header: message,
+                frames: Vec::new(),
+                locations: Vec::new(),
+                omitted: 0,
+            });
+        } else {
+            let level = classify_level(source, &message);
+            ready.push(LogLine::new(level, message, source));
+        }
+        ready
+    }
+
+    /// Releases the trace being collected, if any.
+    fn finish(&mut self) -> Option<LogLine> {
+        self.held.take().map(HeldTrace::into_line)
+    }
+}
+
+fn is_error_word(word: &str) -> bool {
+    let upper = word.to_ascii_uppercase();
+    upper.starts_with("ERR_")
+        || matches!(
+            upper.as_str(),
+            "FAIL" | "FAILS" | "FAILED" | "FAILURE" | "FAILURES" | "ERR" | "ERROR" | "ERRORS"
+        )
+}
+
+fn resolve_test_runner(runner: Option<String>) -> AppResult<&'static str> {
+    let requested = runner
+        .map(|value| value.trim().to_ascii_lowercase())
+        .filter(|value| !value.is_empty());
+    match requested {
+        None => Ok(DEFAULT_TEST_RUNNER),
+        Some(name) => ALLOWED_TEST_RUNNERS
+            .iter()
+            .copied()
+            .find(|allowed| *allowed == name)
+            .ok_or_else(|| AppError::Validation(format!("Unsupported test runner: {name}"))),
+    }
+}
+
+/// Bun ships a native binary on Windows; the Node package managers are
+/// installed as `.cmd` shims.
+fn runner_executable(runner: &str) -> String {
+    if cfg!(target_os = "windows") && runner != "bun" {
+        format!("{runner}.cmd")
     } else {
-        LogLevel::Info
+        runner.to_string()
     }
 }
 
-#[derive(Default)]
-struct TestRunnerState {
-    child: Arc<Mutex<Option<CommandChild>>>,
+/// Script names are package.json keys; rejecting a leading `-` keeps a
+/// value like `--help` from being read as a runner flag. Watch mode
+/// defaults to the watch script instead of the one-shot suite.
+fn resolve_test_script(script: Option<String>, watch: bool) -> AppResult<String> {
+    let Some(name) = script
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+    else {
+        let default = if watch {
+            DEFAULT_WATCH_SCRIPT
+        } else {
+            DEFAULT_TEST_SCRIPT
+        };
+        return Ok(default.to_string());
+    };
+    let valid = !name.starts_with('-')
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, ':' | '-' | '_' | '.'));
+    if valid {
+        Ok(name)
+    } else {
+        Err(AppError::Validation(format!(
+            "Invalid test script name: {name}"
+        )))
+    }
 }
 
-fn locate_workspace_dir(app: &AppHandle) -> Result<PathBuf, String> {
-    let mut candidates: Vec<PathBuf> = Vec::new();
+/// The spawned suite, with its pid kept so it can still be reported after
+/// the child handle has been consumed by `kill`.
+struct RunningTestSuite {
+    child: CommandChild,
+    pid: u32,
+    started_at: SystemTime,
+    recent: RecentLines,
+}
 
-    if let Ok(current) = std::env::current_dir() {
-        candidates.push(current);
+/// The latest log lines of a run, so a client that subscribes late can
+/// catch up. Past `capacity` the oldest lines are dropped.
+#[derive(Debug)]
+struct RecentLines {
+    lines: VecDeque<LogLine>,
+    capacity: usize,
+}
+
+impl RecentLines {
+    fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            capacity,
+        }
     }
 
-    if let Ok(resource_parent) = app.path().resolve("..", BaseDirectory::Resource) {
-        candidates.push(resource_parent);
+    fn push(&mut self, line: LogLine) {
+        if self.lines.len() >= self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
     }
 
-    let mut visited: HashSet<PathBuf> = HashSet::new();
+    /// The last `count` lines, oldest first.
+    fn tail(&self, count: usize) -> Vec<LogLine> {
+        let skip = self.lines.len().saturating_sub(count);
+        self.lines.iter().skip(skip).cloned().collect()
+    }
 
-    for mut candidate in candidates {
-        loop {
-            if !visited.insert(candidate.clone()) {
-                match candidate.parent() {
-                    Some(parent) => {
-                        candidate = parent.to_path_buf();
-                        continue;
-                    }
-                    None => break,
-                }
+    fn clear(&mut self) {
+        self.lines.clear();
+    }
+}
+
+/// Log lines held while output is paused. Past `capacity` the oldest lines
+/// are dropped and counted, and the count is reported ahead of the rest.
+#[derive(Debug)]
+struct PausedLines {
+    lines: VecDeque<LogLine>,
+    dropped: usize,
+    capacity: usize,
+}
+
+impl PausedLines {
+    fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            dropped: 0,
+            capacity,
+        }
+    }
+
+    fn push(&mut self, line: LogLine) {
+        if self.lines.len() >= self.capacity {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+        self.lines.push_back(line);
+    }
+
+    fn drain(&mut self) -> Vec<LogLine> {
+        let mut lines = Vec::with_capacity(self.lines.len() + 1);
+        if self.dropped > 0 {
+            lines.push(LogLine::new(
+                LogLevel::Warn,
+                format!("{} lines dropped while output was paused", self.dropped),
+                LogSource::Stdout,
+            ));
+            self.dropped = 0;
+        }
+        lines.extend(self.lines.drain(..));
+        lines
+    }
+}
+
+/// Sends test-run events in order. With a flush interval, log lines are
+/// held and sent as one `LogBatch` per interval; any other payload flushes
+/// the held lines first, so nothing is reordered or dropped. While paused,
+/// log lines are parked in [`PausedLines`] until output resumes. Every line
+/// also goes straight to the saved run log and the run's [`RecentLines`],
+/// paused or not.
+struct TestRunOutput {
+    window: Window,
+    flush_every: Option<Duration>,
+    pending: Vec<LogLine>,
+    paused: Option<PausedLines>,
+    saved: Option<RunLog>,
+    runs: RunningTests,
+    pid: u32,
+}
+
+impl TestRunOutput {
+    fn new(
+        window: Window,
+        flush_ms: Option<u64>,
+        saved: Option<RunLog>,
+        runs: RunningTests,
+        pid: u32,
+    ) -> Self {
+        Self {
+            window,
+            flush_every: flush_ms.filter(|ms| *ms > 0).map(Duration::from_millis),
+            pending: Vec::new(),
+            paused: None,
+            saved,
+            runs,
+            pid,
+        }
+    }
+
+    /// Applies `update` to this run's recent lines, unless the run has
+    /// already been taken out of the runner state.
+    fn update_recent(&self, update: impl FnOnce(&mut RecentLines)) {
+        if let Ok(mut runs) = self.runs.lock() {
+            if let Some(run) = runs.get_mut(&self.pid) {
+                update(&mut run.recent);
             }
+        }
+    }
 
-            let package = candidate.join("package.json");
-            if package.is_file() {
-                return Ok(candidate);
+    /// Pausing parks log lines; resuming sends the parked lines in batches
+    /// before anything newer.
+    fn set_paused(&mut self, paused: bool) {
+        if paused {
+            if self.paused.is_none() {
+                self.flush();
+                self.paused = Some(PausedLines::new(PAUSED_LINE_CAPACITY));
             }
+            return;
+        }
+        let Some(mut held) = self.paused.take() else {
+            return;
+        };
+        let lines = held.drain();
+        for batch in lines.chunks(RESUME_BATCH_LINES) {
+            let payload = TestRunPayload::LogBatch {
+                lines: batch.to_vec(),
+            };
+            let _ = self.window.emit(TEST_RUN_EVENT, &payload);
+        }
+    }
 
-            match candidate.parent() {
-                Some(parent) => candidate = parent.to_path_buf(),
-                None => break,
+    fn flush_interval(&self) -> Option<tokio::time::Interval> {
+        self.flush_every.map(|period| {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            interval
+        })
+    }
+
+    fn log(&mut self, line: LogLine) {
+        if let Some(saved) = self.saved.as_mut() {
+            saved.write_line(&line.log_text());
+        }
+        self.update_recent(|recent| recent.push(line.clone()));
+        if let Some(held) = self.paused.as_mut() {
+            held.push(line);
+        } else if self.flush_every.is_some() {
+            self.pending.push(line);
+        } else {
+            let _ = self.window.emit(TEST_RUN_EVENT, &TestRunPayload::Log(line));
+        }
+    }
+
+    fn flush(&mut self) {
+        if !self.pending.is_empty() {
+            let lines = std::mem::take(&mut self.pending);
+            let _ = self
+                .window
+                .emit(TEST_RUN_EVENT, &TestRunPayload::LogBatch { lines });
+        }
+        if let Some(saved) = self.saved.as_mut() {
+            saved.flush();
+        }
+    }
+
+    fn emit(&mut self, payload: TestRunPayload) {
+        if let Some(saved) = self.saved.as_mut() {
+            match &payload {
+                TestRunPayload::Terminated {
+                    code,
+                    iteration: None,
+                } => saved.write_exit(*code),
+                TestRunPayload::Terminated {
+                    code,
+                    iteration: Some(iteration),
+                } => saved.write_line(&format!(
+                    "Run {iteration} finished with code {}",
+                    code.map_or_else(|| "unknown".to_string(), |code| code.to_string())
+                )),
+                TestRunPayload::Error { message } => saved.write_line(&format!("Error: {message}")),
+                _ => {}
             }
         }
+        // Each watch-mode re-run starts with an empty tail of its own.
+        if matches!(payload, TestRunPayload::Started { .. }) {
+            self.update_recent(RecentLines::clear);
+        }
+        self.flush();
+        let _ = self.window.emit(TEST_RUN_EVENT, &payload);
     }
+}
+
+/// Spawned suites keyed by pid. A run removes itself once it exits, is
+/// cancelled or times out.
+type RunningTests = Arc<Mutex<BTreeMap<u32, RunningTestSuite>>>;
 
-    Err("Unable to locate workspace directory for npm".into())
+struct TestRunnerState {
+    /// Runs are still started one at a time, since their events carry no
+    /// pid, but each is tracked and cancelled on its own.
+    runs: RunningTests,
+    workspace: Mutex<Option<PathBuf>>,
+    /// Whether log emission is paused; the output loop watches for changes.
+    paused: watch::Sender<bool>,
+    /// Saved log of the latest run started since launch.
+    last_log: Mutex<Option<PathBuf>>,
 }
 
-#[tauri::command]
-async fn db_query(
-    _app: AppHandle,
-    _query: String,
-    _values: Option<Vec<Value>>,
-) -> Result<Value, String> {
-    Err("dbQuery is not implemented yet".into())
+impl Default for TestRunnerState {
+    fn default() -> Self {
+        Self {
+            runs: Arc::default(),
+            workspace: Mutex::default(),
+            paused: watch::channel(false).0,
+            last_log: Mutex::default(),
+        }
+    }
+}
+
+/// The `file_pick` dialog in flight, if any. The receiving half lives in
+/// the waiting command, so a closed sender means no dialog is open.
+#[derive(Default)]
+struct FilePickState {
+    cancel: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+impl FilePickState {
+    /// Claims the single dialog slot, failing while another pick is open.
+    fn begin(&self) -> AppResult<oneshot::Receiver<()>> {
+        let mut slot = self
+            .cancel
+            .lock()
+            .map_err(|_| AppError::state("file pick"))?;
+        if slot.as_ref().is_some_and(|sender| !sender.is_closed()) {
+            return Err(AppError::AlreadyRunning(
+                "A file dialog is already open".into(),
+            ));
+        }
+        let (sender, receiver) = oneshot::channel();
+        *slot = Some(sender);
+        Ok(receiver)
+    }
+
+    /// Releases the waiting pick; false when none was open.
+    fn cancel(&self) -> AppResult<bool> {
+        let sender = self
+            .cancel
+            .lock()
+            .map_err(|_| AppError::state("file pick"))?
+            .take();
+        Ok(sender.is_some_and(|sender| sender.send(()).is_ok()))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct TestRunHandle {
+    pid: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct RunnerAvailability {
+    available: bool,
+    version: Option<String>,
+}
+
+/// What `cancel_all_background_tasks` stopped: the pids of the test runs
+/// that were going and the ids of the jobs that were flagged.
+#[derive(Debug, Default, Serialize)]
+struct CancelledTasks {
+    test_runs: Vec<u32>,
+    pdf_jobs: Vec<String>,
+    file_streams: Vec<String>,
+    query_streams: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct TestRunStatus {
+    running: bool,
+    pid: Option<u32>,
+    started_at: Option<String>,
+}
+
+/// A saved test-run log. `exit_code` is missing for runs that are still
+/// going, were cancelled or timed out.
+#[derive(Debug, Serialize)]
+struct TestLogEntry {
+    path: String,
+    started_at: Option<String>,
+    size_bytes: u64,
+    exit_code: Option<i32>,
+}
+
+/// Formats `time` as an RFC 3339 UTC timestamp such as
+/// `2025-03-01T14:05:09Z`, which the frontend can hand straight to `Date`.
+fn format_rfc3339(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let days = (secs / 86_400) as i64;
+    let (hour, minute, second) = (secs % 86_400 / 3600, secs % 3600 / 60, secs % 60);
+
+    // Days-to-civil conversion from Howard Hinnant's date algorithms.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z")
+}
+
+/// Parses `ps -A -o pid= -o ppid=` output into `(pid, parent)` pairs.
+#[cfg(not(windows))]
+fn parse_process_table(output: &str) -> Vec<(u32, u32)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().map(str::parse::<u32>);
+            match (fields.next(), fields.next()) {
+                (Some(Ok(pid)), Some(Ok(parent))) => Some((pid, parent)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Every process below `root` in `table`, children before grandchildren.
+#[cfg(not(windows))]
+fn descendant_pids(table: &[(u32, u32)], root: u32) -> Vec<u32> {
+    let mut found = vec![root];
+    let mut index = 0;
+    while let Some(&parent) = found.get(index) {
+        let children: Vec<u32> = table
+            .iter()
+            .filter(|(pid, ppid)| *ppid == parent && !found.contains(pid))
+            .map(|(pid, _)| *pid)
+            .collect();
+        found.extend(children);
+        index += 1;
+    }
+    found.split_off(1)
+}
+
+/// Kills `child` and everything it spawned. `npm run` starts node as a
+/// grandchild (through a `.cmd` shim on Windows), which outlives a kill of
+/// the runner alone.
+#[cfg(windows)]
+fn kill_process_tree(child: CommandChild, pid: u32) -> AppResult<()> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let killed = std::process::Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .status()
+        .is_ok_and(|status| status.success());
+    if !killed {
+        child.kill()?;
+    }
+    Ok(())
+}
+
+/// Kills `child` and everything it spawned. `npm run` starts node as a
+/// grandchild, which is reparented rather than killed when only the runner
+/// dies, so the tree is listed before anything is killed.
+#[cfg(not(windows))]
+fn kill_process_tree(child: CommandChild, pid: u32) -> AppResult<()> {
+    let descendants = std::process::Command::new("ps")
+        .args(["-A", "-o", "pid=", "-o", "ppid="])
+        .output()
+        .map(|output| {
+            let table = parse_process_table(&String::from_utf8_lossy(&output.stdout));
+            descendant_pids(&table, pid)
+        })
+        .unwrap_or_default();
+
+    child.kill()?;
+    for descendant in descendants {
+        let _ = std::process::Command::new("kill")
+            .args(["-KILL", &descendant.to_string()])
+            .status();
+    }
+    Ok(())
+}
+
+fn is_workspace_dir(dir: &Path) -> bool {
+    dir.join("package.json").is_file()
+}
+
+/// Walks up from each candidate and returns the nearest directory holding a
+/// `package.json`. Candidates are canonicalized so `..` segments and symlinks
+/// walk the real tree, and a directory already checked for an earlier
+/// candidate ends the walk, since everything above it was checked too.
+fn find_workspace_root(candidates: &[PathBuf]) -> Option<PathBuf> {
+    let mut visited: HashSet<PathBuf> = HashSet::new();
+
+    for candidate in candidates {
+        let start = candidate
+            .canonicalize()
+            .unwrap_or_else(|_| candidate.clone());
+        for dir in start.ancestors() {
+            if !visited.insert(dir.to_path_buf()) {
+                break;
+            }
+            if is_workspace_dir(dir) {
+                return Some(dir.to_path_buf());
+            }
+        }
+    }
+
+    None
+}
+
+/// Resolves the directory `npm` runs in: the configured override, then
+/// `JUMPCHAIN_WORKSPACE`, then the nearest ancestor of the current or
+/// resource directory holding a `package.json`. Overrides that do not point
+/// at a workspace are skipped rather than treated as errors.
+fn locate_workspace_dir(app: &AppHandle, configured: Option<PathBuf>) -> AppResult<PathBuf> {
+    let overrides = configured
+        .into_iter()
+        .chain(std::env::var_os(WORKSPACE_ENV).map(PathBuf::from));
+    for dir in overrides {
+        if is_workspace_dir(&dir) {
+            return Ok(dir);
+        }
+    }
+
+    let mut candidates: Vec<PathBuf> = Vec::new();
+
+    if let Ok(current) = std::env::current_dir() {
+        candidates.push(current);
+    }
+
+    if let Ok(resource_parent) = app.path().resolve("..", BaseDirectory::Resource) {
+        candidates.push(resource_parent);
+    }
+
+    if let Some(root) = find_workspace_root(&candidates) {
+        return Ok(root);
+    }
+
+    Err(AppError::NotFound(format!(
+        "Unable to locate workspace directory for npm; set {WORKSPACE_ENV} to the repository root"
+    )))
 }
 
+/// Only one pick may be open at a time; a second call fails with
+/// `already_running` until the first resolves or is cancelled.
 #[tauri::command]
+#[tracing::instrument(skip_all, err)]
 async fn file_pick(
     app: AppHandle,
+    state: State<'_, FilePickState>,
     payload: Option<FilePickRequest>,
-) -> Result<Option<Vec<String>>, String> {
+) -> AppResult<Option<Vec<String>>> {
     let request = payload.unwrap_or_default();
+    let cancelled = state.begin()?;
     let mut dialog = app.dialog().file();
 
-    for filter in &request.filters {
-        let cleaned = normalize_extensions(&filter.extensions);
-        if cleaned.is_empty() {
-            continue;
-        }
-        let refs: Vec<&str> = cleaned.iter().map(|ext| ext.as_str()).collect();
-        dialog = dialog.add_filter(filter.name.clone().unwrap_or_default(), refs.as_slice());
+    // An invalid start directory falls back to the OS default rather than
+    // failing the pick.
+    if let Some(start_dir) = request
+        .start_dir
+        .or_else(|| preferences::current(&app).default_pick_dir)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_dir())
+    {
+        dialog = dialog.set_directory(start_dir);
     }
 
-    let selection = if request.directory {
+    for (name, extensions) in dialog_filters(&request.filters) {
+        let refs: Vec<&str> = extensions.iter().map(|ext| ext.as_str()).collect();
+        dialog = dialog.add_filter(name, refs.as_slice());
+    }
+
+    let (picked, selection) = oneshot::channel();
+    let reply = move |selection: Option<Vec<FilePath>>| {
+        let _ = picked.send(selection);
+    };
+    if request.directory {
         if request.multiple {
-            dialog.blocking_pick_folders()
+            dialog.pick_folders(reply);
         } else {
-            dialog.blocking_pick_folder().map(|single| vec![single])
+            dialog.pick_folder(move |single| reply(single.map(|single| vec![single])));
         }
     } else if request.multiple {
-        dialog.blocking_pick_files()
+        dialog.pick_files(reply);
     } else {
-        dialog.blocking_pick_file().map(|single| vec![single])
+        dialog.pick_file(move |single| reply(single.map(|single| vec![single])));
+    }
+
+    let selection = tokio::select! {
+        selection = selection => selection.ok().flatten(),
+        _ = cancelled => None,
+    };
+    let Some(selection) = selection else {
+        return Ok(None);
     };
+    let paths = paths_to_strings(selection)?;
+
+    if request.enforce_extensions && !request.directory {
+        let rejected = rejected_selections(&paths, &request.filters);
+        if !rejected.is_empty() {
+            return Err(AppError::Validation(format!(
+                "Unsupported file type for: {}",
+                rejected.join(", ")
+            )));
+        }
+    }
+
+    // Mirror the JS dialog API: whatever the user picked becomes readable
+    // through the fs scope, which `file_metadata` checks against.
+    let scope = app.fs_scope();
+    for path in &paths {
+        let _ = if request.directory {
+            scope.allow_directory(path, true)
+        } else {
+            scope.allow_file(path)
+        };
+    }
+    Ok(Some(paths))
+}
+
+fn read_metadata(path: &Path) -> FileMetadata {
+    match std::fs::metadata(path) {
+        Ok(meta) => FileMetadata {
+            size_bytes: meta.len(),
+            modified_ms: meta
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|elapsed| elapsed.as_millis() as u64),
+            is_dir: meta.is_dir(),
+            exists: true,
+        },
+        Err(_) => FileMetadata {
+            size_bytes: 0,
+            modified_ms: None,
+            is_dir: false,
+            exists: false,
+        },
+    }
+}
 
-    match selection {
-        Some(paths) => paths_to_strings(paths).map(Some),
-        None => Ok(None),
+/// Resolves `path` and checks it against the fs scope. The path is
+/// canonicalized first so `..` segments and symlinks cannot step outside it.
+pub(crate) fn scoped_path(app: &AppHandle, path: &str) -> AppResult<PathBuf> {
+    let requested = PathBuf::from(path);
+    let resolved = requested.canonicalize().unwrap_or(requested);
+    if !app.fs_scope().is_allowed(&resolved) {
+        return Err(AppError::Validation(format!(
+            "Access to {path} is not permitted"
+        )));
     }
+    Ok(resolved)
 }
 
+/// Resolves an open `file_pick` with no selection, e.g. when the view that
+/// asked for it is navigated away from. The native dialog cannot be closed
+/// from here, so whatever is picked in it afterwards is discarded. Returns
+/// whether a pick was open.
 #[tauri::command]
-async fn index_pdf(
-    _app: AppHandle,
-    _file_id: String,
-    _absolute_path: String,
-) -> Result<(), String> {
-    // The heavy lifting happens when the PDF worker pipeline lands in step 3.
-    Ok(())
+#[tracing::instrument(skip_all, err)]
+async fn cancel_file_pick(state: State<'_, FilePickState>) -> AppResult<bool> {
+    state.cancel()
+}
+
+/// Stats a path the app is allowed to read.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn file_metadata(app: AppHandle, path: String) -> AppResult<FileMetadata> {
+    let resolved = scoped_path(&app, &path)?;
+    Ok(read_metadata(&resolved))
 }
 
+/// SHA-256 of a file the app is allowed to read, as lowercase hex.
 #[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn file_checksum(app: AppHandle, path: String) -> AppResult<String> {
+    let resolved = scoped_path(&app, &path)?;
+    Ok(tauri::async_runtime::spawn_blocking(move || checksum::sha256_file(&resolved)).await??)
+}
+
+/// App directories `reveal_app_dir` can show, for support requests that
+/// need the database or the logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum AppDirKind {
+    Data,
+    Logs,
+    Config,
+    Cache,
+}
+
+fn app_dir(app: &AppHandle, kind: AppDirKind) -> AppResult<PathBuf> {
+    let paths = app.path();
+    Ok(match kind {
+        AppDirKind::Data => paths.app_data_dir()?,
+        AppDirKind::Logs => paths.app_log_dir()?,
+        AppDirKind::Config => paths.app_config_dir()?,
+        AppDirKind::Cache => paths.app_cache_dir()?,
+    })
+}
+
+/// The platform file manager, pointed at `dir`. The shell plugin's `open`
+/// is not used here: its scope only admits `.pdf` file URLs.
+fn file_manager_command(dir: &Path) -> std::process::Command {
+    let program = if cfg!(windows) {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    let mut command = std::process::Command::new(program);
+    command.arg(dir);
+    command
+}
+
+/// Opens one of the app's directories in the file manager, creating it
+/// first if nothing has been written there yet, and returns its path.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn reveal_app_dir(app: AppHandle, kind: AppDirKind) -> AppResult<String> {
+    let dir = app_dir(&app, kind)?;
+    std::fs::create_dir_all(&dir)?;
+    if !dir.is_absolute() {
+        return Err(AppError::Validation(format!(
+            "{} is not an absolute path",
+            dir.display()
+        )));
+    }
+    let mut command = file_manager_command(&dir);
+    // Explorer exits with 1 even when the window opened, so only a failure
+    // to start counts; waiting reaps the child.
+    tauri::async_runtime::spawn_blocking(move || command.status())
+        .await?
+        .map_err(|err| AppError::Shell(format!("Unable to open the file manager: {err}")))?;
+    Ok(dir.to_string_lossy().into_owned())
+}
+
+/// Versions and platform details for bug reports.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct AppInfo {
+    app_version: String,
+    tauri_version: &'static str,
+    sqlite_version: String,
+    os: &'static str,
+    arch: &'static str,
+    /// `None` when the platform webview could not be queried.
+    webview_version: Option<String>,
+}
+
+impl AppInfo {
+    fn new(app_version: String, sqlite_version: String, webview_version: Option<String>) -> Self {
+        Self {
+            app_version,
+            tauri_version: tauri::VERSION,
+            sqlite_version,
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            webview_version,
+        }
+    }
+}
+
+/// The app, Tauri, SQLite and webview versions and the OS and CPU
+/// architecture, for the About dialog's copy button.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn app_info(app: AppHandle) -> AppResult<AppInfo> {
+    let pool = db::pool(&app).await?;
+    let sqlite_version: String = sqlx::query_scalar("SELECT sqlite_version()")
+        .fetch_one(&pool)
+        .await?;
+    Ok(AppInfo::new(
+        app.package_info().version.to_string(),
+        sqlite_version,
+        tauri::webview_version().ok(),
+    ))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn file_save(app: AppHandle, payload: Option<FileSaveRequest>) -> AppResult<Option<String>> {
+    let request = payload.unwrap_or_default();
+    let filters = dialog_filters(&request.filters);
+    let default_extension = filters
+        .first()
+        .and_then(|(_, extensions)| extensions.first().cloned());
+
+    let mut dialog = app.dialog().file();
+    if let Some(name) = request.default_name.filter(|name| !name.trim().is_empty()) {
+        dialog = dialog.set_file_name(name);
+    }
+    for (name, extensions) in &filters {
+        let refs: Vec<&str> = extensions.iter().map(|ext| ext.as_str()).collect();
+        dialog = dialog.add_filter(name, refs.as_slice());
+    }
+
+    // The callback form keeps the dialog from tying up a runtime worker.
+    let (saved, selection) = oneshot::channel();
+    dialog.save_file(move |selection| {
+        let _ = saved.send(selection);
+    });
+    let Some(selection) = selection.await.ok().flatten() else {
+        return Ok(None);
+    };
+
+    let path = paths_to_strings(vec![selection])?
+        .pop()
+        .ok_or_else(|| AppError::Dialog("Save dialog returned no path".into()))?;
+    Ok(Some(with_default_extension(
+        path,
+        default_extension.as_deref(),
+    )))
+}
+
+/// With `watch`, the runner stays alive and re-runs on file changes; each
+/// re-run is reported as its own `started`/`terminated` pair carrying an
+/// `iteration`, until `cancel_full_test_suite` stops the process.
+/// With `coverage`, the runner collects coverage and the summary it writes
+/// to `coverage_summary` (relative paths are in the workspace) is reported
+/// once the process exits. With `raw_output`, log lines keep their ANSI
+/// escapes and progress rewrites.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+#[allow(clippy::too_many_arguments)]
 async fn run_full_test_suite(
     window: Window,
     state: State<'_, TestRunnerState>,
-) -> Result<(), String> {
+    filter: Option<String>,
+    timeout_secs: Option<u64>,
+    json_report: Option<bool>,
+    runner: Option<String>,
+    script: Option<String>,
+    log_flush_ms: Option<u64>,
+    watch: Option<bool>,
+    coverage: Option<bool>,
+    coverage_summary: Option<String>,
+    raw_output: Option<bool>,
+) -> AppResult<TestRunHandle> {
+    let watch = watch.unwrap_or(false);
+    let raw_output = raw_output.unwrap_or(false);
     let app = window.app_handle();
-    let workspace_dir = locate_workspace_dir(&app)?;
+    let runner = resolve_test_runner(runner.or(preferences::current(app).test_runner))?;
+    let script = resolve_test_script(script, watch)?;
+    let configured = state
+        .workspace
+        .lock()
+        .map_err(|_| AppError::state("test runner"))?
+        .clone();
+    let workspace_dir = locate_workspace_dir(app, configured)?;
 
-    let npm_executable = if cfg!(target_os = "windows") {
-        "npm.cmd"
-    } else {
-        "npm"
-    };
+    let executable = runner_executable(runner);
+
+    // The filter is passed as its own argument, never spliced into a shell
+    // string, so a pattern like `foo; rm -rf` reaches the runner verbatim.
+    let mut runner_args: Vec<String> = Vec::new();
+    if let Some(pattern) = filter.map(|value| value.trim().to_string()) {
+        if !pattern.is_empty() {
+            runner_args.extend(["-t".to_string(), pattern]);
+        }
+    }
+    // The JSON reporter runs alongside the default one, so the live log
+    // stream is unchanged and the report is read back once the run ends.
+    let report_file = json_report.unwrap_or(false).then(ReportFile::new);
+    if let Some(report) = &report_file {
+        runner_args.extend([
+            "--reporter=default".to_string(),
+            "--reporter=json".to_string(),
+            format!("--outputFile={}", report.path().display()),
+        ]);
+    }
+    // The text reporter keeps the coverage table in the live log.
+    let coverage_file = coverage.unwrap_or(false).then(|| {
+        runner_args.extend([
+            "--coverage".to_string(),
+            "--coverage.reporter=text".to_string(),
+            "--coverage.reporter=json-summary".to_string(),
+        ]);
+        let summary = coverage_summary
+            .map(|path| path.trim().to_string())
+            .filter(|path| !path.is_empty())
+            .unwrap_or_else(|| DEFAULT_COVERAGE_SUMMARY.to_string());
+        CoverageFile::new(workspace_dir.join(summary))
+    });
+    let mut args = vec!["run".to_string(), script];
+    if !runner_args.is_empty() {
+        args.push("--".to_string());
+        args.extend(runner_args);
+    }
+    let command_line = std::iter::once(executable.clone())
+        .chain(args.iter().cloned())
+        .collect::<Vec<_>>()
+        .join(" ");
 
     let command = app
         .shell()
-        .command(npm_executable)
-        .args(["run", "test:full"])
+        .command(executable)
+        .args(&args)
         .current_dir(&workspace_dir)
-        .env("FORCE_COLOR", "0")
-        .env("npm_config_color", "false");
+        .envs(TEST_ENV_OVERRIDES.iter().copied());
 
     let mut guard = state
-        .child
+        .runs
         .lock()
-        .map_err(|_| "Unable to access test runner state".to_string())?;
-    if guard.is_some() {
-        return Err("Test suite is already running".into());
+        .map_err(|_| AppError::state("test runner"))?;
+    if !guard.is_empty() {
+        return Err(AppError::AlreadyRunning(
+            "Test suite is already running".into(),
+        ));
     }
 
-    let (mut rx, child) = command.spawn().map_err(|err| err.to_string())?;
-    *guard = Some(child);
+    let (mut rx, child) = command.spawn()?;
+    let pid = child.pid();
+    guard.insert(
+        pid,
+        RunningTestSuite {
+            child,
+            pid,
+            started_at: SystemTime::now(),
+            recent: RecentLines::new(RECENT_LINE_CAPACITY),
+        },
+    );
     drop(guard);
 
-    let _ = window.emit(TEST_RUN_EVENT, &TestRunPayload::Started);
+    let run = RunDescription {
+        runner: runner.to_string(),
+        command: command_line,
+        pid,
+        workspace_dir: workspace_dir.to_string_lossy().into_owned(),
+        args,
+        env_overrides: TEST_ENV_OVERRIDES
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    };
+    let mut watch = watch.then(WatchIterations::default);
+    let _ = window.emit(
+        TEST_RUN_EVENT,
+        &TestRunPayload::Started {
+            run: run.clone(),
+            iteration: watch.as_ref().map(WatchIterations::current),
+        },
+    );
+
+    // Once the watchdog fires it owns the terminal events, so the exit the
+    // kill itself produces is not reported a second time.
+    let timed_out = Arc::new(AtomicBool::new(false));
+    let watchdog = timeout_secs.filter(|secs| *secs > 0).map(|secs| {
+        let watchdog_window = window.clone();
+        let watchdog_state = Arc::clone(&state.runs);
+        let watchdog_flag = Arc::clone(&timed_out);
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(secs)).await;
+            let running = watchdog_state
+                .lock()
+                .ok()
+                .and_then(|mut guard| guard.remove(&pid));
+            if let Some(running) = running {
+                watchdog_flag.store(true, Ordering::SeqCst);
+                let _ = kill_process_tree(running.child, running.pid);
+                let payload = TestRunPayload::Error {
+                    message: format!("Test run timed out after {secs}s"),
+                };
+                let _ = watchdog_window.emit(TEST_RUN_EVENT, &payload);
+                let payload = TestRunPayload::Terminated {
+                    code: None,
+                    iteration: None,
+                };
+                let _ = watchdog_window.emit(TEST_RUN_EVENT, &payload);
+            }
+        })
+    });
 
-    let event_window = window.clone();
-    let runner_state = Arc::clone(&state.child);
+    let runner_state = Arc::clone(&state.runs);
+    // A run without a saved log is still worth running.
+    let saved = app
+        .path()
+        .app_log_dir()
+        .ok()
+        .and_then(|dir| RunLog::create(&dir).ok());
+    if let Ok(mut last_log) = state.last_log.lock() {
+        *last_log = saved.as_ref().map(|log| log.path().to_path_buf());
+    }
+    let mut output = TestRunOutput::new(
+        window.clone(),
+        log_flush_ms,
+        saved,
+        Arc::clone(&state.runs),
+        pid,
+    );
+    // Every run starts unpaused, whatever the previous one was left at.
+    state.paused.send_replace(false);
+    let mut paused = state.paused.subscribe();
     tauri::async_runtime::spawn(async move {
-        while let Some(event) = rx.recv().await {
-            match event {
-                CommandEvent::Stdout(line) => {
-                    if let Some(message) = sanitize_line(line) {
-                        let level = classify_level(LogSource::Stdout, &message);
-                        let payload = TestRunPayload::Log {
-                            level,
-                            message,
-                            source: LogSource::Stdout,
-                        };
-                        let _ = event_window.emit(TEST_RUN_EVENT, &payload);
+        let mut summary_parser = SummaryParser::default();
+        let mut traces = StackTraces::default();
+        let mut last_output = Instant::now();
+        let mut heartbeat =
+            tokio::time::interval_at(last_output + HEARTBEAT_INTERVAL, HEARTBEAT_INTERVAL);
+        let mut flush_timer = output.flush_interval();
+        loop {
+            let event = tokio::select! {
+                event = rx.recv() => event,
+                _ = heartbeat.tick() => {
+                    // Cancellation and the watchdog clear the runner state
+                    // before the exit event arrives, which stops the beat.
+                    let alive = runner_state
+                        .lock()
+                        .map(|guard| guard.contains_key(&pid))
+                        .unwrap_or(false);
+                    // A quiet runner has finished whatever trace it printed.
+                    if let Some(line) = traces.finish() {
+                        output.log(line);
+                    }
+                    if alive {
+                        output.emit(TestRunPayload::Heartbeat {
+                            elapsed_ms: last_output.elapsed().as_millis() as u64,
+                        });
                     }
+                    continue;
                 }
-                CommandEvent::Stderr(line) => {
-                    if let Some(message) = sanitize_line(line) {
-                        let level = classify_level(LogSource::Stderr, &message);
-                        let payload = TestRunPayload::Log {
-                            level,
-                            message,
-                            source: LogSource::Stderr,
-                        };
-                        let _ = event_window.emit(TEST_RUN_EVENT, &payload);
+                _ = async {
+                    if let Some(timer) = flush_timer.as_mut() {
+                        timer.tick().await;
+                    }
+                }, if flush_timer.is_some() => {
+                    output.flush();
+                    continue;
+                }
+                Ok(()) = paused.changed() => {
+                    output.set_paused(*paused.borrow_and_update());
+                    continue;
+                }
+            };
+            let Some(event) = event else {
+                break;
+            };
+            if matches!(event, CommandEvent::Stdout(_) | CommandEvent::Stderr(_)) {
+                last_output = Instant::now();
+                heartbeat.reset();
+            }
+
+            let source = if matches!(event, CommandEvent::Stderr(_)) {
+                LogSource::Stderr
+            } else {
+                LogSource::Stdout
+            };
+            match event {
+                CommandEvent::Stdout(line) | CommandEvent::Stderr(line) => {
+                    let Some(message) = sanitize_line(line, raw_output) else {
+                        continue;
+                    };
+                    // Iteration boundaries go out ahead of the marker line,
+                    // so the `RERUN` line belongs to the run it starts.
+                    let boundaries = watch
+                        .as_mut()
+                        .map(|watch| watch.observe(&message))
+                        .unwrap_or_default();
+                    if !boundaries.is_empty() {
+                        if let Some(line) = traces.finish() {
+                            output.log(line);
+                        }
+                    }
+                    for boundary in boundaries {
+                        match boundary {
+                            WatchEvent::Finished { iteration, code } => {
+                                if let Some(summary) = summary_parser.finish() {
+                                    output.emit(TestRunPayload::Summary(summary));
+                                }
+                                output.emit(TestRunPayload::Terminated {
+                                    code,
+                                    iteration: Some(iteration),
+                                });
+                            }
+                            WatchEvent::Started { iteration } => {
+                                summary_parser = SummaryParser::default();
+                                output.emit(TestRunPayload::Started {
+                                    run: run.clone(),
+                                    iteration: Some(iteration),
+                                });
+                            }
+                        }
+                    }
+
+                    let summary = summary_parser.observe(&message);
+                    for line in traces.observe(source, message) {
+                        output.log(line);
+                    }
+                    if let Some(summary) = summary {
+                        output.emit(TestRunPayload::Summary(summary));
                     }
                 }
                 CommandEvent::Terminated(details) => {
+                    if let Some(watchdog) = &watchdog {
+                        watchdog.abort();
+                    }
+                    // The run is over, so nothing is left to throttle.
+                    output.set_paused(false);
+                    if let Some(line) = traces.finish() {
+                        output.log(line);
+                    }
+                    if timed_out.load(Ordering::SeqCst) {
+                        continue;
+                    }
                     if let Ok(mut guard) = runner_state.lock() {
-                        let _ = guard.take();
+                        guard.remove(&pid);
+                    }
+                    if let Some(summary) = summary_parser.finish() {
+                        output.emit(TestRunPayload::Summary(summary));
                     }
-                    let payload = TestRunPayload::Terminated { code: details.code };
-                    let _ = event_window.emit(TEST_RUN_EVENT, &payload);
+                    // A missing or malformed report leaves the line-based
+                    // levels and summary as the only result.
+                    let results = report_file.as_ref().and_then(|report| report.read().ok());
+                    if let Some(results) = results {
+                        output.emit(TestRunPayload::Report { results });
+                    }
+                    // Likewise a run that wrote no coverage summary.
+                    let coverage = coverage_file.as_ref().and_then(|file| file.read().ok());
+                    if let Some(coverage) = coverage {
+                        output.emit(TestRunPayload::Coverage(coverage));
+                    }
+                    output.emit(TestRunPayload::Terminated {
+                        code: details.code,
+                        iteration: None,
+                    });
                 }
                 CommandEvent::Error(error) => {
+                    if let Some(watchdog) = &watchdog {
+                        watchdog.abort();
+                    }
+                    output.set_paused(false);
+                    if let Some(line) = traces.finish() {
+                        output.log(line);
+                    }
+                    if timed_out.load(Ordering::SeqCst) {
+                        continue;
+                    }
                     if let Ok(mut guard) = runner_state.lock() {
-                        let _ = guard.take();
+                        guard.remove(&pid);
                     }
-                    let payload = TestRunPayload::Error { message: error };
-                    let _ = event_window.emit(TEST_RUN_EVENT, &payload);
+                    output.emit(TestRunPayload::Error { message: error });
                 }
                 _ => {}
             }
         }
+        if let Some(line) = traces.finish() {
+            output.log(line);
+        }
+        output.set_paused(false);
+        output.flush();
     });
 
+    Ok(TestRunHandle { pid })
+}
+
+/// Takes the run with `pid` out of `runs`, or every run when `pid` is
+/// `None`, in pid order.
+fn take_test_runs(runs: &RunningTests, pid: Option<u32>) -> AppResult<Vec<RunningTestSuite>> {
+    let mut guard = runs.lock().map_err(|_| AppError::state("test runner"))?;
+    Ok(match pid {
+        Some(pid) => guard.remove(&pid).into_iter().collect(),
+        None => std::mem::take(&mut *guard).into_values().collect(),
+    })
+}
+
+/// Kills the given test suites and returns their pids. The exit each kill
+/// causes is reported as `terminated` by its output loop. Every run gets a
+/// kill attempt even after one fails, since the runs are already out of
+/// state; the failures are then reported together.
+fn kill_test_runs(app: &AppHandle, running: Vec<RunningTestSuite>) -> AppResult<Vec<u32>> {
+    let mut pids = Vec::with_capacity(running.len());
+    let mut failures = Vec::new();
+    for RunningTestSuite { child, pid, .. } in running {
+        if let Err(error) = kill_process_tree(child, pid) {
+            failures.push(format!("pid {pid}: {}", error.message()));
+            continue;
+        }
+        let payload = TestRunPayload::Log(LogLine::new(
+            LogLevel::Warn,
+            format!("Cancelled test run (pid {pid})"),
+            LogSource::Stdout,
+        ));
+        let _ = app.emit(TEST_RUN_EVENT, &payload);
+        pids.push(pid);
+    }
+    if !failures.is_empty() {
+        return Err(AppError::Shell(format!(
+            "Unable to cancel {} test run(s): {}",
+            failures.len(),
+            failures.join("; ")
+        )));
+    }
+    Ok(pids)
+}
+
+/// Cancels every test run.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn cancel_full_test_suite(
+    window: Window,
+    state: State<'_, TestRunnerState>,
+) -> AppResult<()> {
+    let running = take_test_runs(&state.runs, None)?;
+    kill_test_runs(window.app_handle(), running)?;
     Ok(())
 }
 
+/// Cancels the run `run_full_test_suite` returned `pid` for, failing with
+/// `not_found` once that run has ended.
 #[tauri::command]
-async fn cancel_full_test_suite(state: State<'_, TestRunnerState>) -> Result<(), String> {
-    let child = {
-        let mut guard = state
-            .child
-            .lock()
-            .map_err(|_| "Unable to access test runner state".to_string())?;
-        guard.take()
+#[tracing::instrument(skip_all, err)]
+async fn cancel_test_run(
+    window: Window,
+    state: State<'_, TestRunnerState>,
+    pid: u32,
+) -> AppResult<()> {
+    let running = take_test_runs(&state.runs, Some(pid))?;
+    if running.is_empty() {
+        return Err(AppError::NotFound(format!("No test run with pid {pid}")));
+    }
+    kill_test_runs(window.app_handle(), running)?;
+    Ok(())
+}
+
+/// Stops every test run, with its child processes, and flags every PDF
+/// index and stream job. Each job emits its own `aborted` or `cancelled`
+/// event as it winds down.
+fn cancel_background_tasks(app: &AppHandle) -> AppResult<CancelledTasks> {
+    let pdf_jobs = app.state::<pdf::PdfIndexState>().cancel_all();
+    let file_streams = app.state::<file_stream::FileStreamState>().cancel_all();
+    let query_streams = app.state::<db::QueryStreamState>().cancel_all();
+    let running = take_test_runs(&app.state::<TestRunnerState>().runs, None)?;
+    let test_runs = kill_test_runs(app, running)?;
+    Ok(CancelledTasks {
+        test_runs,
+        pdf_jobs,
+        file_streams,
+        query_streams,
+    })
+}
+
+/// Leaves nothing half-done behind when the app exits: the test runs are
+/// killed, PDF and stream jobs cancelled and given [`SHUTDOWN_GRACE`] to
+/// wind down, open transactions rolled back and the WAL checkpointed into
+/// the database file.
+async fn shut_down(app: &AppHandle) {
+    let _ = cancel_background_tasks(app);
+    let pdf_jobs = app.state::<pdf::PdfIndexState>();
+    let _ = tokio::time::timeout(SHUTDOWN_GRACE, pdf_jobs.cancel_all_and_wait()).await;
+    app.state::<transactions::TransactionState>()
+        .rollback_all()
+        .await;
+    if let Ok(pool) = db::pool(app).await {
+        let _ = db::checkpoint(&pool).await;
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn cancel_all_background_tasks(app: AppHandle) -> AppResult<CancelledTasks> {
+    cancel_background_tasks(&app)
+}
+
+/// Runs `<runner> --version` to tell the frontend whether tests can be
+/// started at all. A runner that is missing, exits non-zero or does not
+/// answer within a few seconds is reported as unavailable.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn check_runner_available(
+    app: AppHandle,
+    runner: Option<String>,
+) -> AppResult<RunnerAvailability> {
+    let runner = resolve_test_runner(runner.or(preferences::current(&app).test_runner))?;
+    let unavailable = RunnerAvailability {
+        available: false,
+        version: None,
+    };
+
+    let command = app
+        .shell()
+        .command(runner_executable(runner))
+        .arg("--version");
+    let Ok((mut rx, child)) = command.spawn() else {
+        return Ok(unavailable);
     };
 
-    if let Some(mut child) = child {
-        child.kill().map_err(|err| err.to_string())?
+    let mut stdout = Vec::new();
+    let exit = tokio::time::timeout(RUNNER_CHECK_TIMEOUT, async {
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Stdout(line) => stdout.extend(line),
+                CommandEvent::Terminated(details) => return details.code,
+                CommandEvent::Error(_) => return None,
+                _ => {}
+            }
+        }
+        None
+    })
+    .await;
+
+    match exit {
+        Ok(Some(0)) => {
+            let version = String::from_utf8_lossy(&stdout)
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .map(str::to_string);
+            Ok(RunnerAvailability {
+                available: true,
+                version,
+            })
+        }
+        Ok(_) => Ok(unavailable),
+        Err(_) => {
+            let _ = child.kill();
+            Ok(unavailable)
+        }
+    }
+}
+
+/// Sets or clears (with `None`) the workspace the test runner uses ahead of
+/// the environment override and the directory search.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn set_test_workspace(
+    state: State<'_, TestRunnerState>,
+    path: Option<String>,
+) -> AppResult<()> {
+    let dir = path
+        .map(|value| PathBuf::from(value.trim()))
+        .filter(|dir| !dir.as_os_str().is_empty());
+    if let Some(dir) = &dir {
+        if !is_workspace_dir(dir) {
+            return Err(AppError::NotFound(format!(
+                "No package.json found in {}",
+                dir.display()
+            )));
+        }
     }
 
+    let mut workspace = state
+        .workspace
+        .lock()
+        .map_err(|_| AppError::state("test runner"))?;
+    *workspace = dir;
+    Ok(())
+}
+
+/// Holds test log lines back from the webview; the run itself carries on.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn pause_test_output(state: State<'_, TestRunnerState>) -> AppResult<()> {
+    state.paused.send_replace(true);
     Ok(())
 }
 
+/// Sends the lines held since `pause_test_output` and resumes live output.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn resume_test_output(state: State<'_, TestRunnerState>) -> AppResult<()> {
+    state.paused.send_replace(false);
+    Ok(())
+}
+
+/// Path of the newest saved test-run log, including runs from before the
+/// app was restarted, or `None` when there are none.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn get_last_test_log_path(
+    app: AppHandle,
+    state: State<'_, TestRunnerState>,
+) -> AppResult<Option<String>> {
+    let current = state
+        .last_log
+        .lock()
+        .map_err(|_| AppError::state("test runner"))?
+        .clone();
+    let latest = match current.filter(|path| path.is_file()) {
+        Some(path) => Some(path),
+        None => test_output::run_logs(&app.path().app_log_dir()?).pop(),
+    };
+    Ok(latest.map(|path| path.to_string_lossy().into_owned()))
+}
+
+/// Saved test-run logs, newest first.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn list_test_logs(app: AppHandle) -> AppResult<Vec<TestLogEntry>> {
+    let dir = app.path().app_log_dir()?;
+    Ok(test_output::run_logs(&dir)
+        .into_iter()
+        .rev()
+        .map(|path| TestLogEntry {
+            started_at: test_output::run_log_started(&path).map(format_rfc3339),
+            size_bytes: std::fs::metadata(&path)
+                .map(|metadata| metadata.len())
+                .unwrap_or_default(),
+            exit_code: test_output::run_log_exit_code(&path),
+            path: path.to_string_lossy().into_owned(),
+        })
+        .collect())
+}
+
+/// Streams a saved test-run log back as `file-stream://lines` events under
+/// `stream_id`. Only logs in the app log directory are accepted.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn open_test_log(
+    window: Window,
+    state: State<'_, file_stream::FileStreamState>,
+    stream_id: String,
+    path: String,
+) -> AppResult<()> {
+    let log_dir = window.app_handle().path().app_log_dir()?;
+    let log_dir = log_dir.canonicalize().unwrap_or(log_dir);
+    let resolved = PathBuf::from(&path).canonicalize()?;
+    if !resolved.starts_with(&log_dir) || !test_output::is_run_log(&resolved) {
+        return Err(AppError::Validation(format!(
+            "{path} is not a saved test-run log"
+        )));
+    }
+    file_stream::stream_lines(window, &state, stream_id, resolved, None, None).await
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn get_test_run_status(state: State<'_, TestRunnerState>) -> AppResult<TestRunStatus> {
+    let guard = state
+        .runs
+        .lock()
+        .map_err(|_| AppError::state("test runner"))?;
+    // The most recently started run speaks for the rest.
+    let latest = guard.values().max_by_key(|running| running.started_at);
+    Ok(match latest {
+        Some(running) => TestRunStatus {
+            running: true,
+            pid: Some(running.pid),
+            started_at: Some(format_rfc3339(running.started_at)),
+        },
+        None => TestRunStatus {
+            running: false,
+            pid: None,
+            started_at: None,
+        },
+    })
+}
+
+/// The last `count` log lines of the current test run, oldest first, for a
+/// client that subscribed to the run's events late. Empty when no run is
+/// going; a run's lines are dropped once it terminates.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+async fn get_recent_test_lines(
+    state: State<'_, TestRunnerState>,
+    count: usize,
+) -> AppResult<Vec<LogLine>> {
+    let guard = state
+        .runs
+        .lock()
+        .map_err(|_| AppError::state("test runner"))?;
+    let latest = guard.values().max_by_key(|running| running.started_at);
+    Ok(latest.map_or_else(Vec::new, |running| running.recent.tail(count)))
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -331,13 +1988,565 @@ pub fn run() {
         .plugin(tauri_plugin_sql::Builder::default().build())
         .plugin(tauri_plugin_shell::init())
         .manage(TestRunnerState::default())
+        .manage(pdf::PdfIndexState::default())
+        .manage(recent::RecentFilesState::default())
+        .manage(file_stream::FileStreamState::default())
+        .manage(db::QueryStreamState::default())
+        .manage(transactions::TransactionState::default())
+        .manage(FilePickState::default())
+        .manage(preferences::PreferencesState::default())
+        .manage(snapshots::SnapshotState::default())
+        .setup(|app| {
+            // Open the shared pool before the webview does, so every
+            // connection gets the pragmas set in `db::open_pool`.
+            let handle = app.handle().clone();
+            // The app runs without a trace log rather than not at all.
+            let _ = logging::init(&handle);
+            tauri::async_runtime::block_on(db::pool(&handle))?;
+            preferences::load(&handle)?;
+            snapshots::spawn_autosave(handle);
+            Ok(())
+        })
+        .on_window_event(handle_window_event)
         .invoke_handler(tauri::generate_handler![
-            db_query,
+            db::db_query,
+            db::db_execute_batch,
+            db::db_query_stream,
+            db::cancel_db_query_stream,
+            transactions::db_begin,
+            transactions::db_commit,
+            transactions::db_rollback,
+            db::db_backup,
+            db::db_restore,
+            db::db_health_check,
+            db::repair_orphans,
+            db::db_schema,
+            db::db_explain,
+            db::db_pragmas,
+            db::db_optimize,
+            workspace::export_workspace,
+            workspace::import_workspace,
+            workspace::rollback_last_import,
+            budget::validate_budget,
+            budget::audit_chain,
+            budget::chain_statistics,
+            jumps::reorder_perks,
+            jumps::import_jump_from_template,
+            logging::get_trace_log_path,
+            export::export_character,
+            export::export_character_markdown,
+            export::import_character,
+            export::inspect_import,
+            export::clone_character,
+            export::merge_characters,
+            export::diff_characters,
+            migrations::run_migrations,
             file_pick,
-            index_pdf,
+            cancel_file_pick,
+            file_save,
+            file_metadata,
+            file_checksum,
+            reveal_app_dir,
+            app_info,
+            file_stream::stream_file_lines,
+            file_stream::cancel_file_stream,
+            pdf::index_pdf,
+            pdf::index_pdf_folder,
+            pdf::cancel_index_pdf,
+            pdf::cancel_all_pdf_indexing,
+            pdf::remove_indexed_file,
+            pdf::set_pdf_concurrency,
+            pdf::get_pdf_page_count,
+            pdf::get_pdf_page_text,
+            pdf::get_pdf_outline,
+            pdf::get_pdf_links,
+            pdf::get_pdf_stats,
+            pdf::get_indexed_files,
+            pdf::set_pdf_metadata,
+            pdf::export_pdf_text,
+            pdf::open_pdf_external,
+            pdf::search_pdf_text,
+            pdf::search_in_pdf,
+            search::global_search,
+            thumbnails::get_pdf_thumbnails,
+            recent::record_recent_file,
+            recent::list_recent_files,
+            recycle::list_deleted,
+            recycle::restore_deleted,
+            recycle::purge_deleted,
+            preferences::get_preferences,
+            preferences::set_preferences,
+            snapshots::set_autosave_character,
+            snapshots::list_snapshots,
+            snapshots::restore_snapshot,
             run_full_test_suite,
-            cancel_full_test_suite
+            cancel_full_test_suite,
+            cancel_test_run,
+            cancel_all_background_tasks,
+            pause_test_output,
+            resume_test_output,
+            get_test_run_status,
+            get_recent_test_lines,
+            get_last_test_log_path,
+            list_test_logs,
+            open_test_log,
+            set_test_workspace,
+            check_runner_available
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Nothing the app started may outlive it.
+            if let RunEvent::Exit = event {
+                tauri::async_runtime::block_on(shut_down(app));
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(windows))]
+    #[test]
+    fn descendant_pids_walks_the_whole_tree() {
+        let table = parse_process_table(
+            "  1     0\n 40     1\n 41    40\n 42    41\n 43    41\n 50     1\nPID PPID\n",
+        );
+        assert_eq!(table.len(), 6);
+        assert_eq!(descendant_pids(&table, 40), vec![41, 42, 43]);
+        assert_eq!(descendant_pids(&table, 42), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn recent_lines_keep_the_latest_tail() {
+        let mut recent = RecentLines::new(3);
+        assert!(recent.tail(10).is_empty());
+        for n in 1..=5 {
+            recent.push(LogLine::new(
+                LogLevel::Info,
+                format!("line {n}"),
+                LogSource::Stdout,
+            ));
+        }
+        let messages = |lines: Vec<LogLine>| -> Vec<String> {
+            lines.into_iter().map(|line| line.message).collect()
+        };
+        assert_eq!(messages(recent.tail(10)), ["line 3", "line 4", "line 5"]);
+        assert_eq!(messages(recent.tail(2)), ["line 4", "line 5"]);
+        assert!(recent.tail(0).is_empty());
+
+        recent.clear();
+        assert!(recent.tail(10).is_empty());
+    }
+
+    #[test]
+    fn paused_lines_drop_oldest_and_report_it() {
+        let line = |message: &str| LogLine::new(LogLevel::Info, message.into(), LogSource::Stdout);
+        let mut held = PausedLines::new(2);
+        for message in ["one", "two", "three", "four"] {
+            held.push(line(message));
+        }
+
+        let messages: Vec<String> = held.drain().into_iter().map(|line| line.message).collect();
+        assert_eq!(
+            messages,
+            ["2 lines dropped while output was paused", "three", "four"]
+        );
+        assert!(held.drain().is_empty());
+    }
+
+    #[test]
+    fn file_pick_allows_one_dialog_at_a_time() {
+        let state = FilePickState::default();
+        let mut open = state.begin().expect("first pick");
+        assert_eq!(
+            state.begin().map(|_| ()).unwrap_err().code(),
+            "already_running"
+        );
+
+        assert!(state.cancel().unwrap());
+        assert!(open.try_recv().is_ok());
+        assert!(!state.cancel().unwrap());
+
+        let finished = state.begin().expect("pick after cancel");
+        drop(finished);
+        assert!(state.begin().is_ok());
+    }
+
+    #[test]
+    fn sanitize_line_strips_colors_and_progress_rewrites() {
+        let colored = b"\x1b[32m\xe2\x9c\x93\x1b[39m src/budget.test.ts \x1b[2m(3 tests)\x1b[22m\n";
+        assert_eq!(
+            sanitize_line(colored.to_vec(), false).as_deref(),
+            Some("\u{2713} src/budget.test.ts (3 tests)")
+        );
+        let progress = b"[##   ] 40%\r[#### ] 80%\r\x1b[2K[#####] 100%\r\n";
+        assert_eq!(
+            sanitize_line(progress.to_vec(), false).as_deref(),
+            Some("[#####] 100%")
+        );
+        // A trailing clear keeps the last rewrite that had text.
+        let cleared = b"Collecting 3/3\r\x1b[K\r\n";
+        assert_eq!(
+            sanitize_line(cleared.to_vec(), false).as_deref(),
+            Some("Collecting 3/3")
+        );
+        let linked = b"\x1b]8;;https://vitest.dev\x07docs\x1b]8;;\x07\tok\x07\n";
+        assert_eq!(
+            sanitize_line(linked.to_vec(), false).as_deref(),
+            Some("docs\tok")
+        );
+        assert_eq!(sanitize_line(b"\x1b[0m\r\n".to_vec(), false), None);
+
+        assert_eq!(
+            sanitize_line(progress.to_vec(), true).as_deref(),
+            Some("[##   ] 40%\r[#### ] 80%\r\x1b[2K[#####] 100%")
+        );
+    }
+
+    #[test]
+    fn parse_location_handles_common_path_formats() {
+        let location = |file: &str, line, column| {
+            Some(LogLocation {
+                file: file.into(),
+                line,
+                column,
+            })
+        };
+        assert_eq!(
+            parse_location(" FAIL  src/foo.test.ts:42:10"),
+            location("src/foo.test.ts", 42, Some(10))
+        );
+        assert_eq!(
+            parse_location("    at Object.<anonymous> (C:\\Users\\dev\\src\\foo.test.ts:42:10)"),
+            location("C:\\Users\\dev\\src\\foo.test.ts", 42, Some(10))
+        );
+        assert_eq!(
+            parse_location(" ❯ src\\modules\\foo.test.tsx:7"),
+            location("src\\modules\\foo.test.tsx", 7, None)
+        );
+        assert_eq!(
+            parse_location("at file:///C:/app/src/foo.test.ts:4:2"),
+            location("C:/app/src/foo.test.ts", 4, Some(2))
+        );
+        assert_eq!(
+            parse_location("./src/jump-docs/parse_doc.spec.js:12"),
+            location("./src/jump-docs/parse_doc.spec.js", 12, None)
+        );
+        assert_eq!(parse_location("Serving on http://127.0.0.1:3000"), None);
+        assert_eq!(parse_location("Tests  3 passed (3)"), None);
+    }
+
+    #[test]
+    fn classify_level_matches_whole_words() {
+        let cases = [
+            (" ✓ src/dao.spec.ts > handles error case", LogLevel::Info),
+            ("  ✓ handles error case (3 ms)", LogLevel::Info),
+            (" ↓ skips failure reporting [skipped]", LogLevel::Info),
+            ("RUN  v1.6.0 /repo/src/error.test.ts", LogLevel::Info),
+            ("transforming src/utils/failover.ts", LogLevel::Info),
+            ("collecting errors/index.spec.ts", LogLevel::Info),
+            ("Terrorform module loaded", LogLevel::Info),
+            (" FAIL  src/dao.spec.ts > inserts a jump", LogLevel::Error),
+            (" × rejects empty titles", LogLevel::Error),
+            ("Error: connect ECONNREFUSED", LogLevel::Error),
+            ("AssertionError: expected 2 to equal 3", LogLevel::Error),
+            ("      Tests  3 failed | 42 passed (45)", LogLevel::Error),
+            ("npm ERR! code ELIFECYCLE", LogLevel::Error),
+            ("[ERROR] database is locked", LogLevel::Error),
+            ("Warning: act(...) is not supported", LogLevel::Warn),
+            ("[vite] warn: unused import in src/warn.ts", LogLevel::Warn),
+        ];
+
+        for (line, expected) in cases {
+            assert_eq!(classify_level(LogSource::Stdout, line), expected, "{line}");
+        }
+        assert_eq!(
+            classify_level(LogSource::Stderr, "ordinary stderr chatter"),
+            LogLevel::Error
+        );
+    }
+
+    #[test]
+    fn stack_traces_coalesce_into_one_line() {
+        let mut traces = StackTraces::default();
+        let node_trace = [
+            "/app/src/db/dao.js:42",
+            "TypeError: Cannot read properties of undefined (reading 'id')",
+            "    at loadJump (/app/src/db/dao.js:42:17)",
+            "    at async Promise.all (index 0)",
+            "    at Object.<anonymous> (C:\\app\\src\\db\\dao.test.ts:12:5)",
+            "    at Module._compile (node:internal/modules/cjs/loader:1256:14)",
+            "Node.js v18.17.0",
+        ];
+        let mut lines = Vec::new();
+        for line in node_trace {
+            lines.extend(traces.observe(LogSource::Stderr, line.to_string()));
+        }
+        assert!(traces.finish().is_none());
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].message, node_trace[0]);
+        assert_eq!(lines[2].message, "Node.js v18.17.0");
+        let trace = &lines[1];
+        assert_eq!(trace.level, LogLevel::Error);
+        assert_eq!(trace.message, node_trace[1..6].join("\n"));
+        assert_eq!(
+            trace.frames,
+            vec![
+                LogLocation {
+                    file: "/app/src/db/dao.js".into(),
+                    line: 42,
+                    column: Some(17),
+                },
+                LogLocation {
+                    file: "C:\\app\\src\\db\\dao.test.ts".into(),
+                    line: 12,
+                    column: Some(5),
+                },
+            ]
+        );
+        assert_eq!(trace.location.as_ref(), trace.frames.first());
+    }
+
+    #[test]
+    fn stack_traces_pass_other_lines_through() {
+        let mut traces = StackTraces::default();
+        // A header without frames is only released by the next line.
+        assert!(traces
+            .observe(LogSource::Stdout, "Error: connect ECONNREFUSED".into())
+            .is_empty());
+        let lines = traces.observe(LogSource::Stdout, "Retrying in 1s".into());
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].message, "Error: connect ECONNREFUSED");
+        assert_eq!(lines[0].level, LogLevel::Error);
+        assert!(lines[0].frames.is_empty());
+
+        let lines = traces.observe(LogSource::Stdout, "    at stray (src/a.ts:1:1)".into());
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].level, LogLevel::Info);
+
+        assert!(traces
+            .observe(
+                LogSource::Stderr,
+                "AssertionError [ERR_ASSERTION]: nope".into()
+            )
+            .is_empty());
+        let last = traces.finish().expect("held header");
+        assert_eq!(last.message, "AssertionError [ERR_ASSERTION]: nope");
+        assert!(traces.finish().is_none());
+    }
+
+    #[test]
+    fn stack_traces_cap_their_frames() {
+        let mut traces = StackTraces::default();
+        traces.observe(
+            LogSource::Stderr,
+            "RangeError: Maximum call stack size exceeded".into(),
+        );
+        for depth in 0..MAX_TRACE_FRAMES + 7 {
+            let frame = format!("    at recurse (/app/src/loop.js:{}:3)", depth + 1);
+            assert!(traces.observe(LogSource::Stderr, frame).is_empty());
+        }
+        let trace = traces.finish().expect("held trace");
+        assert_eq!(trace.frames.len(), MAX_TRACE_FRAMES);
+        assert!(trace.message.ends_with("... 7 more frames"));
+    }
+
+    #[test]
+    fn test_runner_defaults_and_allowlist() {
+        assert_eq!(resolve_test_runner(None), Ok("npm"));
+        assert_eq!(resolve_test_runner(Some("  ".into())), Ok("npm"));
+        assert_eq!(resolve_test_runner(Some("PNPM".into())), Ok("pnpm"));
+        assert_eq!(resolve_test_runner(Some("yarn".into())), Ok("yarn"));
+        assert!(resolve_test_runner(Some("bash".into())).is_err());
+        assert!(resolve_test_runner(Some("npm; rm -rf /".into())).is_err());
+    }
+
+    #[test]
+    fn test_script_defaults_and_validation() {
+        assert_eq!(resolve_test_script(None, false).as_deref(), Ok("test:full"));
+        assert_eq!(resolve_test_script(None, true).as_deref(), Ok("test:watch"));
+        assert_eq!(
+            resolve_test_script(Some("test:a11y:components".into()), true).as_deref(),
+            Ok("test:a11y:components")
+        );
+        assert!(resolve_test_script(Some("--help".into()), false).is_err());
+        assert!(resolve_test_script(Some("test && curl x".into()), false).is_err());
+    }
+
+    #[test]
+    fn rejects_selections_outside_the_filters() {
+        let filters = vec![
+            FileFilter {
+                name: Some("Jump documents".into()),
+                extensions: vec![".PDF".into(), "txt".into()],
+            },
+            FileFilter {
+                name: Some("Archives".into()),
+                extensions: vec!["tar.gz".into()],
+            },
+        ];
+        let paths = vec![
+            "/chains/Naruto.Pdf".to_string(),
+            "/chains/notes.txt".to_string(),
+            "/chains/backup.tar.gz".to_string(),
+            "/chains/cover.png".to_string(),
+            "/chains/pdf".to_string(),
+        ];
+        assert_eq!(
+            rejected_selections(&paths, &filters),
+            vec!["/chains/cover.png", "/chains/pdf"]
+        );
+
+        let any = vec![FileFilter {
+            name: None,
+            extensions: vec!["*".into()],
+        }];
+        assert!(rejected_selections(&paths, &any).is_empty());
+        assert!(rejected_selections(&paths, &[]).is_empty());
+    }
+
+    #[test]
+    fn reads_metadata_for_files_dirs_and_missing_paths() {
+        let dir = std::env::temp_dir().join(format!("jumpchain-meta-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("jump.pdf");
+        std::fs::write(&file, b"%PDF-1.7").unwrap();
+
+        let meta = read_metadata(&file);
+        assert!(meta.exists && !meta.is_dir);
+        assert_eq!(meta.size_bytes, 8);
+        assert!(meta.modified_ms.is_some());
+        assert!(read_metadata(&dir).is_dir);
+        assert_eq!(
+            read_metadata(&dir.join("missing.pdf")),
+            FileMetadata {
+                size_bytes: 0,
+                modified_ms: None,
+                is_dir: false,
+                exists: false,
+            }
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn finds_nearest_workspace_root() {
+        let root = std::env::temp_dir().join(format!("jumpchain-workspace-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let root = root.canonicalize().unwrap();
+        let desktop = root.join("apps").join("desktop");
+        let src = desktop.join("src-tauri").join("src");
+        let docs = root.join("docs").join("guides");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(root.join("package.json"), "{}").unwrap();
+        std::fs::write(desktop.join("package.json"), "{}").unwrap();
+
+        assert_eq!(find_workspace_root(&[src.clone()]), Some(desktop.clone()));
+        assert_eq!(find_workspace_root(&[docs.clone()]), Some(root.clone()));
+        // `..` is resolved before walking, so this starts in `apps`.
+        assert_eq!(
+            find_workspace_root(&[desktop.join("..")]),
+            Some(root.clone())
+        );
+        assert_eq!(
+            find_workspace_root(&[src.join("missing"), docs.clone()]),
+            Some(desktop.clone())
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn keeps_only_dropped_pdfs() {
+        let paths = [
+            PathBuf::from("/chains/Naruto Jump.pdf"),
+            PathBuf::from("/chains/notes.txt"),
+            PathBuf::from("/chains/GENERIC.PDF"),
+            PathBuf::from("/chains/pdf"),
+        ];
+        assert_eq!(
+            dropped_pdf_paths(&paths),
+            vec!["/chains/Naruto Jump.pdf", "/chains/GENERIC.PDF"]
+        );
+        assert!(dropped_pdf_paths(&paths[1..2]).is_empty());
+    }
+
+    #[test]
+    fn app_dir_kinds_use_frontend_names() {
+        let kinds: Vec<AppDirKind> =
+            serde_json::from_str(r#"["data", "logs", "config", "cache"]"#).unwrap();
+        assert_eq!(
+            kinds,
+            [
+                AppDirKind::Data,
+                AppDirKind::Logs,
+                AppDirKind::Config,
+                AppDirKind::Cache
+            ]
+        );
+        assert!(serde_json::from_str::<AppDirKind>(r#""temp""#).is_err());
+    }
+
+    #[test]
+    fn file_manager_is_given_the_plain_directory() {
+        let dir = std::env::temp_dir().join("Jumpchain Nexus").join("logs");
+        let command = file_manager_command(&dir);
+        let expected = if cfg!(windows) {
+            "explorer"
+        } else if cfg!(target_os = "macos") {
+            "open"
+        } else {
+            "xdg-open"
+        };
+        assert_eq!(command.get_program(), expected);
+        // A path, not a `file://` URL, so nothing depends on the shell
+        // plugin's open scope.
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, [dir.as_os_str()]);
+    }
+
+    #[test]
+    fn app_info_reports_build_and_platform() {
+        let info = AppInfo::new("1.2.0".into(), "3.46.0".into(), None);
+        let value = serde_json::to_value(&info).unwrap();
+        let mut keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort_unstable();
+        assert_eq!(
+            keys,
+            [
+                "app_version",
+                "arch",
+                "os",
+                "sqlite_version",
+                "tauri_version",
+                "webview_version"
+            ]
+        );
+        assert_eq!(value["webview_version"], serde_json::Value::Null);
+        assert!(info.tauri_version.starts_with("2."));
+        assert!(!info.os.is_empty() && !info.arch.is_empty());
+    }
+
+    #[test]
+    fn formats_rfc3339_timestamps() {
+        assert_eq!(format_rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            format_rfc3339(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "2000-02-29T00:00:00Z"
+        );
+        assert_eq!(
+            format_rfc3339(UNIX_EPOCH + Duration::from_secs(1_740_837_909)),
+            "2025-03-01T14:05:09Z"
+        );
+    }
 }
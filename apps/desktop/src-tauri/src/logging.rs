@@ -0,0 +1,182 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, Registry};
+
+use crate::error::{AppError, AppResult};
+
+const TRACE_LOG_NAME: &str = "jumpchain-nexus.log";
+/// A log past this size is moved aside at startup, keeping one old file.
+const MAX_TRACE_LOG_BYTES: u64 = 5 * 1024 * 1024;
+/// Target of this crate's spans and events. Other crates log at `warn`
+/// whatever the preference, since sqlx would otherwise write query text.
+const APP_TARGET: &str = "appsdesktop_lib";
+
+/// How much the trace log records. Commands are instrumented with
+/// `skip_all`, so their arguments stay out of the log at every level.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Off => LevelFilter::OFF,
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+fn targets(level: LogLevel) -> Targets {
+    Targets::new()
+        .with_default(level.filter().min(LevelFilter::WARN))
+        .with_target(APP_TARGET, level.filter())
+}
+
+struct TraceLog {
+    path: PathBuf,
+    filter: reload::Handle<Targets, Registry>,
+}
+
+static TRACE_LOG: OnceLock<TraceLog> = OnceLock::new();
+
+/// Moves `path` to `<name>.1` when it has grown past `max_bytes`.
+fn rotate_if_large(path: &Path, max_bytes: u64) -> AppResult<()> {
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.len() > max_bytes => {
+            let mut rotated = path.as_os_str().to_owned();
+            rotated.push(".1");
+            Ok(std::fs::rename(path, rotated)?)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn open_trace_log(path: &Path) -> AppResult<File> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    rotate_if_large(path, MAX_TRACE_LOG_BYTES)?;
+    Ok(OpenOptions::new().create(true).append(true).open(path)?)
+}
+
+/// Installs the global subscriber writing to the trace log in the app log
+/// dir. Each command span is written when it closes, with its duration;
+/// a command that fails also logs its error.
+pub(crate) fn init(app: &AppHandle) -> AppResult<()> {
+    let path = app.path().app_log_dir()?.join(TRACE_LOG_NAME);
+    let file = open_trace_log(&path)?;
+    let (filter, handle) = reload::Layer::new(targets(LogLevel::default()));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(Mutex::new(file))
+                .with_ansi(false)
+                .with_span_events(FmtSpan::CLOSE),
+        )
+        .try_init()
+        .map_err(|err| AppError::Internal(err.to_string()))?;
+    let _ = TRACE_LOG.set(TraceLog {
+        path,
+        filter: handle,
+    });
+    Ok(())
+}
+
+/// Applies `level` to the running subscriber; a no-op before `init`.
+pub(crate) fn set_level(level: LogLevel) -> AppResult<()> {
+    match TRACE_LOG.get() {
+        Some(log) => log
+            .filter
+            .reload(targets(level))
+            .map_err(|err| AppError::Internal(err.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Where the trace log is written, for attaching to bug reports.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn get_trace_log_path() -> AppResult<String> {
+    TRACE_LOG
+        .get()
+        .map(|log| log.path.display().to_string())
+        .ok_or_else(|| AppError::NotFound("Trace logging is not running".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn other_crates_never_log_below_warn() {
+        let debug = targets(LogLevel::Debug);
+        assert!(debug.would_enable(APP_TARGET, &tracing::Level::DEBUG));
+        assert!(!debug.would_enable("sqlx::query", &tracing::Level::DEBUG));
+        assert!(debug.would_enable("sqlx::query", &tracing::Level::WARN));
+
+        let error = targets(LogLevel::Error);
+        assert!(!error.would_enable(APP_TARGET, &tracing::Level::WARN));
+        assert!(!error.would_enable("sqlx::query", &tracing::Level::WARN));
+        assert!(!targets(LogLevel::Off).would_enable(APP_TARGET, &tracing::Level::ERROR));
+    }
+
+    #[test]
+    fn large_trace_logs_are_rotated() {
+        let dir = std::env::temp_dir().join(format!("jumpchain-trace-log-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("logs").join(TRACE_LOG_NAME);
+        drop(open_trace_log(&path).unwrap());
+        assert!(path.exists());
+
+        std::fs::write(&path, "0123456789").unwrap();
+        rotate_if_large(&path, 10).unwrap();
+        assert!(path.exists());
+        rotate_if_large(&path, 9).unwrap();
+        assert!(!path.exists());
+        let rotated = dir.join("logs").join(format!("{TRACE_LOG_NAME}.1"));
+        assert_eq!(std::fs::read_to_string(rotated).unwrap(), "0123456789");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
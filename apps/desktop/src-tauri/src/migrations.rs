@@ -0,0 +1,185 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use sqlx::sqlite::SqlitePool;
+use sqlx::Row;
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+use crate::db;
+use crate::error::{AppError, AppResult};
+
+const MIGRATIONS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS _migrations (
+    version INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    applied_at INTEGER NOT NULL
+)";
+
+pub(crate) struct Migration {
+    version: i64,
+    name: &'static str,
+    sql: &'static str,
+}
+
+/// The frontend's schema files, embedded so the backend applies exactly the
+/// SQL that ships with the build. Versions follow the file-name prefixes.
+pub(crate) const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "init",
+        sql: include_str!("../../src/db/migrations/001_init.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "supplements",
+        sql: include_str!("../../src/db/migrations/004_supplements.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "knowledge_import_errors",
+        sql: include_str!("../../src/db/migrations/005_knowledge_import_errors.sql"),
+    },
+];
+
+/// Highest schema version this build knows how to apply.
+pub(crate) fn latest_version() -> i64 {
+    MIGRATIONS
+        .iter()
+        .map(|migration| migration.version)
+        .max()
+        .unwrap_or_default()
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Applies every migration whose version is not yet recorded in
+/// `_migrations`, in ascending version order, inside one transaction. A
+/// failing migration rolls back the whole run and is reported by version.
+pub(crate) async fn apply_migrations(
+    pool: &SqlitePool,
+    migrations: &[Migration],
+) -> AppResult<Vec<i64>> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(MIGRATIONS_SCHEMA).execute(&mut *tx).await?;
+    let applied: HashSet<i64> = sqlx::query("SELECT version FROM _migrations")
+        .fetch_all(&mut *tx)
+        .await?
+        .iter()
+        .map(|row| row.get::<i64, _>("version"))
+        .collect();
+
+    let mut pending: Vec<&Migration> = migrations
+        .iter()
+        .filter(|migration| !applied.contains(&migration.version))
+        .collect();
+    pending.sort_by_key(|migration| migration.version);
+
+    let mut newly_applied = Vec::with_capacity(pending.len());
+    for migration in pending {
+        // Migration files hold several statements (and trigger bodies with
+        // their own semicolons), so they run unprepared as one script.
+        sqlx::raw_sql(migration.sql)
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| {
+                AppError::Db(format!("Migration {} failed: {err}", migration.version))
+            })?;
+        sqlx::query("INSERT INTO _migrations (version, name, applied_at) VALUES (?, ?, ?)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(now_millis())
+            .execute(&mut *tx)
+            .await?;
+        newly_applied.push(migration.version);
+    }
+
+    tx.commit().await?;
+    Ok(newly_applied)
+}
+
+/// Safe to call on every startup: an up-to-date database returns an empty
+/// list without touching the schema.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn run_migrations(app: AppHandle) -> AppResult<Vec<i64>> {
+    let pool = db::pool(&app).await?;
+    apply_migrations(&pool, MIGRATIONS).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn applies_embedded_migrations_once() {
+        let pool = db::memory_pool().await;
+
+        assert_eq!(apply_migrations(&pool, MIGRATIONS).await, Ok(vec![1, 4, 5]));
+        assert_eq!(apply_migrations(&pool, MIGRATIONS).await, Ok(vec![]));
+
+        let tables: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'jumps'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(tables, 1);
+    }
+
+    #[tokio::test]
+    async fn failed_migration_rolls_back_pending_run() {
+        let pool = db::memory_pool().await;
+        let migrations = [
+            Migration {
+                version: 2,
+                name: "broken",
+                sql: "CREATE TABLE perks (id INTEGER PRIMARY KEY); INSERT INTO missing VALUES (1);",
+            },
+            Migration {
+                version: 1,
+                name: "jumps",
+                sql: "CREATE TABLE jumps (id TEXT PRIMARY KEY);",
+            },
+        ];
+
+        let error = apply_migrations(&pool, &migrations).await.unwrap_err();
+        assert_eq!(error.code(), "db");
+        assert!(error.message().starts_with("Migration 2 failed"), "{error}");
+
+        // Version 1 ran first and succeeded, but was rolled back with 2.
+        let tables: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE name IN ('jumps', 'perks', '_migrations')",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(tables, 0);
+        assert_eq!(apply_migrations(&pool, &migrations[1..]).await, Ok(vec![1]));
+    }
+}
@@ -0,0 +1,150 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use pdfium_render::prelude::*;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::{AppError, AppResult};
+use crate::thumbnails;
+
+/// Render width for OCR, roughly 200 DPI on a letter-sized page, which is
+/// where tesseract's accuracy levels off.
+const OCR_RENDER_WIDTH: i32 = 1700;
+
+/// Tesseract is not bundled: a copy placed next to the app binary is
+/// preferred, and otherwise the system install on `PATH` is used.
+fn tesseract_path() -> PathBuf {
+    let name = if cfg!(windows) {
+        "tesseract.exe"
+    } else {
+        "tesseract"
+    };
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(name)))
+        .filter(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+fn tesseract_command(tesseract: &Path) -> Command {
+    let mut command = Command::new(tesseract);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    command
+}
+
+/// Rasterizes pages with PDFium and reads them back with tesseract, for
+/// scanned PDFs that carry no text layer.
+pub(crate) struct PageOcr {
+    pdfium: Pdfium,
+    tesseract: PathBuf,
+}
+
+impl PageOcr {
+    /// Binds the renderer and checks that tesseract starts, so a missing
+    /// engine fails once up front rather than on every page.
+    pub(crate) fn new(resource_dir: Option<PathBuf>) -> AppResult<Self> {
+        let pdfium = thumbnails::bind_pdfium(resource_dir)?;
+        let tesseract = tesseract_path();
+        let probe = tesseract_command(&tesseract)
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        if !probe.is_ok_and(|status| status.success()) {
+            return Err(AppError::Internal(
+                "OCR engine unavailable; install tesseract to index scanned PDFs".into(),
+            ));
+        }
+        Ok(Self { pdfium, tesseract })
+    }
+
+    fn read_image(&self, png: &[u8]) -> AppResult<String> {
+        let mut child = tesseract_command(&self.tesseract)
+            .args(["stdin", "stdout"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        // Tesseract reads all of its input before writing anything, so the
+        // whole image can go in before the output is collected.
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(png)?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(AppError::Internal(format!(
+                "OCR failed with {}",
+                output.status
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Recognizes the zero-based page `indexes` of `source`, returning their
+    /// text in the same order. `on_page` fires before each page with
+    /// `(current, total, page)`; `Ok(None)` means `cancelled` was raised.
+    pub(crate) fn recognize(
+        &self,
+        source: &Path,
+        indexes: &[usize],
+        cancelled: &AtomicBool,
+        mut on_page: impl FnMut(u32, u32, u32),
+    ) -> AppResult<Option<Vec<String>>> {
+        let document = self
+            .pdfium
+            .load_pdf_from_file(source, None)
+            .map_err(|err| AppError::Validation(format!("Unable to read PDF: {err}")))?;
+        let config = PdfRenderConfig::new().set_target_width(OCR_RENDER_WIDTH);
+        let total = indexes.len() as u32;
+
+        let mut texts = Vec::with_capacity(indexes.len());
+        for (position, index) in indexes.iter().enumerate() {
+            if cancelled.load(Ordering::SeqCst) {
+                return Ok(None);
+            }
+            on_page(position as u32 + 1, total, *index as u32 + 1);
+
+            let page = u16::try_from(*index)
+                .ok()
+                .and_then(|index| document.pages().get(index).ok())
+                .ok_or_else(|| AppError::Internal(format!("Unable to open page {}", index + 1)))?;
+            let mut png = Cursor::new(Vec::new());
+            page.render_with_config(&config)
+                .map_err(|err| {
+                    AppError::Internal(format!("Unable to render page {}: {err}", index + 1))
+                })?
+                .as_image()
+                .write_to(&mut png, image::ImageFormat::Png)
+                .map_err(|err| AppError::Internal(err.to_string()))?;
+            texts.push(self.read_image(png.get_ref())?);
+        }
+        Ok(Some(texts))
+    }
+}
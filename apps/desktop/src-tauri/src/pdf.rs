@@ -0,0 +1,3432 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqliteConnection, SqlitePool};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::Read;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager, State, Url, Window};
+use tauri_plugin_shell::ShellExt;
+use tokio::sync::{watch, Semaphore};
+
+use crate::error::{AppError, AppResult};
+use crate::{checksum, db, ocr, preferences, thumbnails};
+
+const PDF_INDEX_EVENT: &str = "pdf://index";
+const PDF_FOLDER_EVENT: &str = "pdf://index-folder";
+
+/// Upper bound on the decompressed content stream of a single page, so a
+/// hostile document cannot balloon memory during extraction.
+const MAX_PAGE_CONTENT_BYTES: usize = 64 * 1024 * 1024;
+
+/// Header every PDF starts with. Readers accept it anywhere in the first
+/// kilobyte, since some producers write junk ahead of it.
+const PDF_SIGNATURE: &[u8] = b"%PDF-";
+const SIGNATURE_WINDOW: u64 = 1024;
+
+/// Number of PDFs extracted at once unless the frontend overrides it.
+pub(crate) const DEFAULT_PDF_CONCURRENCY: usize = 2;
+
+const PAGE_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS file_pages (
+    file_id TEXT NOT NULL,
+    page INTEGER NOT NULL,
+    content TEXT NOT NULL DEFAULT '',
+    sha256 TEXT,
+    PRIMARY KEY (file_id, page)
+)";
+
+pub(crate) const PAGE_FTS_SCHEMA: &str = "CREATE VIRTUAL TABLE IF NOT EXISTS file_page_fts
+    USING fts5(content, file_id UNINDEXED, page UNINDEXED)";
+
+const OUTLINE_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS file_outlines (
+    file_id TEXT PRIMARY KEY,
+    outline TEXT NOT NULL DEFAULT '[]'
+)";
+
+const CHECKSUM_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS file_checksums (
+    file_id TEXT PRIMARY KEY,
+    sha256 TEXT NOT NULL
+)";
+
+const METADATA_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS file_metadata (
+    file_id TEXT PRIMARY KEY,
+    title TEXT,
+    series TEXT,
+    tags TEXT NOT NULL DEFAULT '[]'
+)";
+
+const STATS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS file_stats (
+    file_id TEXT PRIMARY KEY,
+    words INTEGER NOT NULL DEFAULT 0,
+    characters INTEGER NOT NULL DEFAULT 0,
+    reading_minutes INTEGER NOT NULL DEFAULT 0
+)";
+
+/// Text of the pages extracted so far by an index that has not finished,
+/// keyed to the checksum of the file it was read from.
+const CHECKPOINT_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS file_index_checkpoints (
+    file_id TEXT NOT NULL,
+    sha256 TEXT NOT NULL,
+    page INTEGER NOT NULL,
+    content TEXT NOT NULL DEFAULT '',
+    PRIMARY KEY (file_id, page)
+)";
+
+/// Pages extracted between checkpoint writes.
+const CHECKPOINT_INTERVAL: usize = 25;
+
+/// Silent reading speed behind `reading_minutes`.
+const WORDS_PER_MINUTE: u32 = 200;
+
+const LINK_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS file_links (
+    file_id TEXT NOT NULL,
+    page INTEGER NOT NULL,
+    rect TEXT NOT NULL,
+    target TEXT NOT NULL
+)";
+
+/// URI schemes a link annotation may point at. Anything else, such as
+/// `javascript:` or `file:`, is dropped during indexing.
+const LINK_SCHEMES: &[&str] = &["http", "https", "mailto"];
+const MAX_LINK_URI_LENGTH: usize = 2048;
+/// How many references, named destinations or name-tree levels are
+/// followed before a link is given up on, so cyclic documents terminate.
+const MAX_LINK_DEPTH: usize = 16;
+
+/// Whether the default viewer honours a `#page=N` fragment on a file URL.
+/// Edge, the stock Windows PDF viewer, does; Preview and the usual Linux
+/// viewers ignore it or fail to resolve the file.
+const PAGE_FRAGMENT_SUPPORTED: bool = cfg!(windows);
+
+/// Emitted before each page's text in `export_pdf_text`, so exported files
+/// can be split back into pages; `{page}` is the 1-based page number.
+const TEXT_PAGE_MARKER: &str = "--- page {page} ---";
+const MARKDOWN_PAGE_MARKER: &str = "<!-- page {page} -->";
+
+pub(crate) const DEFAULT_SEARCH_LIMIT: u32 = 20;
+pub(crate) const MAX_SEARCH_LIMIT: u32 = 200;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum PdfIndexPayload {
+    Queued,
+    /// `resume_from` is the page extraction picks up at when an earlier,
+    /// interrupted index of the same file left a checkpoint.
+    Started {
+        resume_from: Option<u32>,
+    },
+    /// `eta_ms` estimates the time left from the recent page rate and is
+    /// left out until there is a rate to go on.
+    Page {
+        current: u32,
+        total: u32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        eta_ms: Option<u64>,
+    },
+    /// A page without a text layer is being run through OCR; `page` is the
+    /// page number and `current`/`total` count only the OCR pages.
+    Ocr {
+        page: u32,
+        current: u32,
+        total: u32,
+    },
+    /// The file matches the checksum stored at its last index, so
+    /// extraction was skipped.
+    Unchanged,
+    /// Another indexed file has the same contents; its text was copied to
+    /// this `file_id` instead of being extracted again.
+    Reused {
+        existing_file_id: String,
+    },
+    /// Another indexed file has the same contents and nothing was stored.
+    /// This is the job's last event, in place of `completed`.
+    Duplicate {
+        existing_file_id: String,
+    },
+    /// Sent once the text is stored: `updated` pages were written, while
+    /// `skipped` ones extracted to the same text as before and were left
+    /// alone.
+    Pages {
+        updated: Vec<u32>,
+        skipped: Vec<u32>,
+    },
+    Completed,
+    Aborted,
+    Error {
+        message: String,
+    },
+}
+
+/// What `index_pdf` does with a file whose contents are already indexed
+/// under another `file_id`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DuplicateMode {
+    /// Extract it again, as for any other file.
+    #[default]
+    Index,
+    /// Copy the stored text of the existing file.
+    Reuse,
+    /// Store nothing and report the existing file, so the UI can offer to
+    /// merge the two.
+    Report,
+}
+
+/// How one index job runs.
+#[derive(Debug, Default)]
+struct IndexOptions {
+    page_range: Option<(u32, u32)>,
+    ocr: bool,
+    duplicates: DuplicateMode,
+    /// The file's checksum, when the caller already read the file for it.
+    sha256: Option<String>,
+}
+
+/// How an `index_pdf` job ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum IndexOutcome {
+    Indexed,
+    Aborted,
+    Duplicate { existing_file_id: String },
+}
+
+/// One bookmark from the document outline, with `page` numbered from 1.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct OutlineEntry {
+    title: String,
+    page: u32,
+    children: Vec<OutlineEntry>,
+}
+
+/// Size of a document's stored text. `reading_minutes` is rounded up, so
+/// any text at all reads in at least a minute.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub(crate) struct PdfStats {
+    words: u32,
+    characters: u32,
+    reading_minutes: u32,
+}
+
+/// Word and character counts over `pages`; pages without text add nothing.
+fn text_stats<S: AsRef<str>>(pages: &[S]) -> PdfStats {
+    let (words, characters) = pages.iter().fold((0u32, 0u32), |(words, chars), page| {
+        let page = page.as_ref();
+        (
+            words.saturating_add(page.split_whitespace().count() as u32),
+            chars.saturating_add(page.chars().count() as u32),
+        )
+    });
+    PdfStats {
+        words,
+        characters,
+        reading_minutes: words.div_ceil(WORDS_PER_MINUTE),
+    }
+}
+
+/// Where a link annotation leads: a page of the same document, numbered
+/// from 1, or a sanitized external URI.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum LinkTarget {
+    Page { page: u32 },
+    Uri { uri: String },
+}
+
+/// A link annotation on `page`. `rect` is `[left, bottom, right, top]` in
+/// PDF points, measured from the bottom-left corner of the page.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct PdfLink {
+    page: u32,
+    rect: [f32; 4],
+    target: LinkTarget,
+}
+
+/// A PDF with stored text. `available` is false when the source file has
+/// since been moved or deleted; `checksum` is missing after a page-range
+/// index. `title`, `series` and `tags` are whatever `set_pdf_metadata`
+/// last stored.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct IndexedFile {
+    file_id: String,
+    path: String,
+    page_count: i64,
+    indexed_at: String,
+    checksum: Option<String>,
+    available: bool,
+    title: Option<String>,
+    series: Option<String>,
+    tags: Vec<String>,
+}
+
+/// User-facing details for an indexed document, in place of its raw file
+/// name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct PdfMetadata {
+    title: Option<String>,
+    tags: Vec<String>,
+    series: Option<String>,
+}
+
+impl PdfMetadata {
+    /// Blank titles and series become `None`; tags are trimmed, lowercased
+    /// and de-duplicated, keeping the first occurrence of each.
+    fn normalized(self) -> Self {
+        let text = |value: Option<String>| {
+            value
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let mut tags: Vec<String> = Vec::with_capacity(self.tags.len());
+        for tag in self.tags {
+            let tag = tag.trim().to_lowercase();
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        Self {
+            title: text(self.title),
+            tags,
+            series: text(self.series),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct PdfSearchHit {
+    pub(crate) file_id: String,
+    pub(crate) page: i64,
+    pub(crate) snippet: String,
+    pub(crate) rank: f64,
+}
+
+/// A page of one document that matches an in-document find.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct PdfPageMatch {
+    page: i64,
+    /// Matched terms and phrases on the page.
+    hits: u32,
+    snippet: String,
+}
+
+/// `matches` are in page order, so the viewer can step to the next or
+/// previous page from its current position.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub(crate) struct PdfFindResult {
+    total_hits: u32,
+    matches: Vec<PdfPageMatch>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct PdfIndexEvent<'a> {
+    file_id: &'a str,
+    #[serde(flatten)]
+    payload: PdfIndexPayload,
+}
+
+/// A queued or running `index_pdf` job. `done` flips to true once the job
+/// has emitted its terminal event.
+struct IndexJob {
+    cancelled: Arc<AtomicBool>,
+    done: watch::Receiver<bool>,
+}
+
+/// The configured worker count, and how many permits a shrink still has to
+/// take out of the semaphore once busy workers release them.
+#[derive(Debug, Default)]
+struct WorkerSizing {
+    concurrency: usize,
+    debt: usize,
+}
+
+/// In-flight `index_pdf` jobs, keyed by `file_id`, plus the worker permits
+/// that bound how many extract at once.
+pub(crate) struct PdfIndexState {
+    jobs: Arc<Mutex<HashMap<String, IndexJob>>>,
+    workers: Arc<Semaphore>,
+    sizing: Arc<Mutex<WorkerSizing>>,
+}
+
+impl Default for PdfIndexState {
+    fn default() -> Self {
+        Self {
+            jobs: Arc::default(),
+            workers: Arc::new(Semaphore::new(DEFAULT_PDF_CONCURRENCY)),
+            sizing: Arc::new(Mutex::new(WorkerSizing {
+                concurrency: DEFAULT_PDF_CONCURRENCY,
+                debt: 0,
+            })),
+        }
+    }
+}
+
+/// Retires released permits one at a time until `sizing.debt` is paid off.
+/// A grow that cancels the debt first makes the next permit go straight
+/// back to the pool, so permits added later are never retired.
+fn spawn_permit_retirement(workers: Arc<Semaphore>, sizing: Arc<Mutex<WorkerSizing>>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let Ok(permit) = Arc::clone(&workers).acquire_owned().await else {
+                return;
+            };
+            let Ok(mut current) = sizing.lock() else {
+                return;
+            };
+            if current.debt == 0 {
+                return;
+            }
+            current.debt -= 1;
+            permit.forget();
+            if current.debt == 0 {
+                return;
+            }
+        }
+    });
+}
+
+impl PdfIndexState {
+    /// Sets how many PDFs may be extracted at once.
+    pub(crate) fn resize(&self, n: usize) -> AppResult<usize> {
+        if n == 0 {
+            return Err(AppError::Validation(
+                "PDF concurrency must be at least 1".into(),
+            ));
+        }
+
+        let mut sizing = self
+            .sizing
+            .lock()
+            .map_err(|_| AppError::state("PDF indexing"))?;
+        let current = sizing.concurrency;
+
+        if n > current {
+            // Permits a shrink has yet to retire count toward the growth.
+            let added = n - current;
+            let cancelled = added.min(sizing.debt);
+            sizing.debt -= cancelled;
+            self.workers.add_permits(added - cancelled);
+        } else if n < current {
+            let surplus = current - n;
+            let retired = self.workers.forget_permits(surplus);
+            if retired < surplus {
+                let retiring = sizing.debt > 0;
+                sizing.debt += surplus - retired;
+                if !retiring {
+                    spawn_permit_retirement(Arc::clone(&self.workers), Arc::clone(&self.sizing));
+                }
+            }
+        }
+
+        sizing.concurrency = n;
+        Ok(n)
+    }
+
+    /// Records a job for `file_id`, unless one is already queued or running.
+    /// The job reports its terminal event through the returned sender.
+    fn register(&self, file_id: &str) -> AppResult<(Arc<AtomicBool>, watch::Sender<bool>)> {
+        let mut jobs = self
+            .jobs
+            .lock()
+            .map_err(|_| AppError::state("PDF indexing"))?;
+        if jobs.contains_key(file_id) {
+            return Err(AppError::AlreadyRunning(format!(
+                "{file_id} is already being indexed"
+            )));
+        }
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (done, done_rx) = watch::channel(false);
+        jobs.insert(
+            file_id.to_string(),
+            IndexJob {
+                cancelled: Arc::clone(&cancelled),
+                done: done_rx,
+            },
+        );
+        Ok((cancelled, done))
+    }
+
+    /// Flags every queued or running job for cancellation and returns their
+    /// ids. Each job emits `aborted` and removes itself as it stops.
+    pub(crate) fn cancel_all(&self) -> Vec<String> {
+        let Ok(jobs) = self.jobs.lock() else {
+            return Vec::new();
+        };
+        for job in jobs.values() {
+            job.cancelled.store(true, Ordering::SeqCst);
+        }
+        jobs.keys().cloned().collect()
+    }
+
+    /// Like [`cancel_all`](Self::cancel_all), but resolves only once every
+    /// cancelled job has emitted its terminal event. Returns how many there
+    /// were.
+    pub(crate) async fn cancel_all_and_wait(&self) -> AppResult<usize> {
+        let waiting: Vec<watch::Receiver<bool>> = {
+            let jobs = self
+                .jobs
+                .lock()
+                .map_err(|_| AppError::state("PDF indexing"))?;
+            jobs.values()
+                .map(|job| {
+                    job.cancelled.store(true, Ordering::SeqCst);
+                    job.done.clone()
+                })
+                .collect()
+        };
+        for mut done in waiting.iter().cloned() {
+            // A dropped sender means the job is gone, which is just as final.
+            let _ = done.wait_for(|done| *done).await;
+        }
+        Ok(waiting.len())
+    }
+}
+
+/// Samples [`ProgressRate`] averages over; older ones fall out.
+const ETA_WINDOW: usize = 10;
+
+/// Moving average of how fast an index gets through its pages, or a folder
+/// run through its files, for the `eta_ms` of progress events.
+#[derive(Debug, Default)]
+struct ProgressRate {
+    samples: VecDeque<(Instant, u32)>,
+}
+
+impl ProgressRate {
+    /// Records `done` of `total` units finished at `at` and estimates the
+    /// milliseconds left, or `None` until the samples span some progress.
+    fn update(&mut self, at: Instant, done: u32, total: u32) -> Option<u64> {
+        if self.samples.len() == ETA_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((at, done));
+        let (first_at, first_done) = *self.samples.front()?;
+        let elapsed = at.saturating_duration_since(first_at).as_millis();
+        let progressed = u128::from(done.saturating_sub(first_done));
+        if elapsed == 0 || progressed == 0 {
+            return None;
+        }
+        let remaining = u128::from(total.saturating_sub(done));
+        u64::try_from(remaining * elapsed / progressed).ok()
+    }
+}
+
+fn emit_progress(window: &Window, file_id: &str, payload: PdfIndexPayload) {
+    let event = PdfIndexEvent { file_id, payload };
+    let _ = window.emit(PDF_INDEX_EVENT, &event);
+}
+
+/// Confirms `path` is a file carrying the PDF header, so a mislabeled file
+/// is turned away before the parser sees it.
+fn check_pdf_file(path: &Path) -> AppResult<()> {
+    if !path.is_file() {
+        return Err(AppError::NotFound(format!(
+            "PDF not found at {}",
+            path.display()
+        )));
+    }
+
+    let mut head = Vec::new();
+    std::fs::File::open(path)?
+        .take(SIGNATURE_WINDOW)
+        .read_to_end(&mut head)?;
+    if !head
+        .windows(PDF_SIGNATURE.len())
+        .any(|window| window == PDF_SIGNATURE)
+    {
+        return Err(AppError::Validation(format!(
+            "{} is not a PDF: the %PDF- header is missing",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+fn load_document(path: &Path) -> AppResult<Document> {
+    let metadata = Document::load_metadata(path)
+        .map_err(|err| AppError::Validation(format!("Unable to read PDF: {err}")))?;
+    if metadata.encrypted && metadata.page_count == 0 {
+        return Err(AppError::Validation(
+            "PDF is password protected and cannot be indexed".into(),
+        ));
+    }
+
+    let document = Document::load(path)
+        .map_err(|err| AppError::Validation(format!("Unable to read PDF: {err}")))?;
+    if document.is_encrypted() {
+        return Err(AppError::Validation(
+            "PDF is password protected and cannot be indexed".into(),
+        ));
+    }
+    Ok(document)
+}
+
+/// Builds the bookmark tree from `(level, title, page)` rows in document
+/// order: each row becomes a child of the closest earlier row with a lower
+/// level.
+fn nest_outline(rows: Vec<(usize, String, u32)>) -> Vec<OutlineEntry> {
+    let mut roots: Vec<OutlineEntry> = Vec::new();
+    // Levels of the entries on the path from the root to the latest entry.
+    let mut path: Vec<usize> = Vec::new();
+    for (level, title, page) in rows {
+        while path.last().is_some_and(|open| *open >= level) {
+            path.pop();
+        }
+        let mut siblings = &mut roots;
+        for _ in 0..path.len() {
+            siblings = &mut siblings.last_mut().expect("open entry").children;
+        }
+        siblings.push(OutlineEntry {
+            title: title.trim().to_string(),
+            page,
+            children: Vec::new(),
+        });
+        path.push(level);
+    }
+    roots
+}
+
+/// The document outline, or an empty list when the PDF has none or it
+/// cannot be parsed; bookmarks are a convenience and never fail the index.
+fn read_outline(document: &Document) -> Vec<OutlineEntry> {
+    document
+        .get_toc()
+        .map(|toc| {
+            nest_outline(
+                toc.toc
+                    .into_iter()
+                    .map(|entry| (entry.level, entry.title, entry.page as u32))
+                    .collect(),
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Zero-based slice of a `page_count`-page document covered by the
+/// inclusive, 1-based `range`; the whole document when there is none.
+fn page_span(range: Option<(u32, u32)>, page_count: u32) -> AppResult<Range<usize>> {
+    let Some((first, last)) = range else {
+        return Ok(0..page_count as usize);
+    };
+    if first == 0 || first > last {
+        return Err(AppError::Validation(format!(
+            "Page range {first}-{last} is invalid; it must run forward from page 1 or later"
+        )));
+    }
+    if last > page_count {
+        return Err(AppError::Validation(format!(
+            "Page range {first}-{last} is outside the document's {page_count} pages"
+        )));
+    }
+    Ok(first as usize - 1..last as usize)
+}
+
+/// Follows references until a direct object.
+fn resolve<'a>(document: &'a Document, mut object: &'a Object) -> Option<&'a Object> {
+    for _ in 0..MAX_LINK_DEPTH {
+        match object {
+            Object::Reference(id) => object = document.get_object(*id).ok()?,
+            direct => return Some(direct),
+        }
+    }
+    None
+}
+
+fn entry<'a>(document: &'a Document, dict: &'a Dictionary, key: &[u8]) -> Option<&'a Object> {
+    resolve(document, dict.get(key).ok()?)
+}
+
+/// Looks `name` up in a name tree, such as the catalog's `/Names /Dests`.
+fn name_tree_lookup<'a>(
+    document: &'a Document,
+    node: &'a Dictionary,
+    name: &[u8],
+    depth: usize,
+) -> Option<&'a Object> {
+    if depth >= MAX_LINK_DEPTH {
+        return None;
+    }
+    if let Some(Object::Array(pairs)) = entry(document, node, b"Names") {
+        for pair in pairs.chunks_exact(2) {
+            if matches!(resolve(document, &pair[0]), Some(Object::String(key, _)) if key == name) {
+                return Some(&pair[1]);
+            }
+        }
+    }
+    if let Some(Object::Array(kids)) = entry(document, node, b"Kids") {
+        for kid in kids {
+            if let Some(Object::Dictionary(kid)) = resolve(document, kid) {
+                if let Some(found) = name_tree_lookup(document, kid, name, depth + 1) {
+                    return Some(found);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// The destination registered under `name`, either in the PDF 1.1
+/// `/Dests` dictionary or the `/Names` tree that replaced it.
+fn named_destination<'a>(document: &'a Document, name: &[u8]) -> Option<&'a Object> {
+    let Some(Object::Dictionary(catalog)) = entry(document, &document.trailer, b"Root") else {
+        return None;
+    };
+    if let Some(Object::Dictionary(dests)) = entry(document, catalog, b"Dests") {
+        if let Ok(destination) = dests.get(name) {
+            return Some(destination);
+        }
+    }
+    let Some(Object::Dictionary(names)) = entry(document, catalog, b"Names") else {
+        return None;
+    };
+    let Some(Object::Dictionary(tree)) = entry(document, names, b"Dests") else {
+        return None;
+    };
+    name_tree_lookup(document, tree, name, 0)
+}
+
+/// The page, numbered from 1, an explicit or named destination points at.
+/// `page_numbers` maps page object ids to their numbers.
+fn destination_page(
+    document: &Document,
+    destination: &Object,
+    page_numbers: &HashMap<ObjectId, u32>,
+    depth: usize,
+) -> Option<u32> {
+    if depth >= MAX_LINK_DEPTH {
+        return None;
+    }
+    match resolve(document, destination)? {
+        Object::Array(items) => match items.first()? {
+            Object::Reference(id) => page_numbers.get(id).copied(),
+            // Remote destinations give a 0-based index; some producers
+            // write the same for local ones.
+            Object::Integer(index) => u32::try_from(*index).ok()?.checked_add(1),
+            _ => None,
+        },
+        Object::Dictionary(dict) => {
+            destination_page(document, dict.get(b"D").ok()?, page_numbers, depth + 1)
+        }
+        Object::Name(name) | Object::String(name, _) => destination_page(
+            document,
+            named_destination(document, name)?,
+            page_numbers,
+            depth + 1,
+        ),
+        _ => None,
+    }
+}
+
+/// An external link target as it may be shown to the UI: absolute, free of
+/// control characters and using one of [`LINK_SCHEMES`].
+fn sanitize_link_uri(raw: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(raw).ok()?.trim();
+    if text.len() > MAX_LINK_URI_LENGTH || text.chars().any(char::is_control) {
+        return None;
+    }
+    let url = Url::parse(text).ok()?;
+    LINK_SCHEMES
+        .contains(&url.scheme())
+        .then(|| url.to_string())
+}
+
+fn link_target(
+    document: &Document,
+    annotation: &Dictionary,
+    page_numbers: &HashMap<ObjectId, u32>,
+) -> Option<LinkTarget> {
+    let Some(Object::Dictionary(action)) = entry(document, annotation, b"A") else {
+        let page = destination_page(document, annotation.get(b"Dest").ok()?, page_numbers, 0)?;
+        return Some(LinkTarget::Page { page });
+    };
+    let Some(Object::Name(kind)) = entry(document, action, b"S") else {
+        return None;
+    };
+    match kind.as_slice() {
+        b"URI" => match entry(document, action, b"URI")? {
+            Object::String(uri, _) => sanitize_link_uri(uri).map(|uri| LinkTarget::Uri { uri }),
+            _ => None,
+        },
+        b"GoTo" => {
+            let page = destination_page(document, action.get(b"D").ok()?, page_numbers, 0)?;
+            Some(LinkTarget::Page { page })
+        }
+        _ => None,
+    }
+}
+
+/// `[left, bottom, right, top]`, whichever corners the rectangle was
+/// written with.
+fn link_rect(document: &Document, rect: &Object) -> Option<[f32; 4]> {
+    let Object::Array(corners) = rect else {
+        return None;
+    };
+    let values = corners
+        .iter()
+        .map(|value| resolve(document, value)?.as_float().ok())
+        .collect::<Option<Vec<f32>>>()?;
+    let [x1, y1, x2, y2] = values[..] else {
+        return None;
+    };
+    Some([x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2)])
+}
+
+/// The link annotations on `pages` with a usable target, in page order.
+/// Malformed annotations are skipped; links never fail the index.
+fn read_links(document: &Document, pages: &[u32]) -> Vec<PdfLink> {
+    let page_ids = document.get_pages();
+    let page_numbers: HashMap<ObjectId, u32> =
+        page_ids.iter().map(|(number, id)| (*id, *number)).collect();
+    let mut links = Vec::new();
+    for page in pages {
+        let Some(page_dict) = page_ids
+            .get(page)
+            .and_then(|id| document.get_dictionary(*id).ok())
+        else {
+            continue;
+        };
+        let Some(Object::Array(annotations)) = entry(document, page_dict, b"Annots") else {
+            continue;
+        };
+        for annotation in annotations {
+            let Some(Object::Dictionary(annotation)) = resolve(document, annotation) else {
+                continue;
+            };
+            if !matches!(entry(document, annotation, b"Subtype"), Some(Object::Name(kind)) if kind == b"Link")
+            {
+                continue;
+            }
+            let rect =
+                entry(document, annotation, b"Rect").and_then(|rect| link_rect(document, rect));
+            let target = link_target(document, annotation, &page_numbers);
+            if let (Some(rect), Some(target)) = (rect, target) {
+                links.push(PdfLink {
+                    page: *page,
+                    rect,
+                    target,
+                });
+            }
+        }
+    }
+    links
+}
+
+/// What `extract_pages` read from a document.
+#[derive(Debug)]
+struct Extraction {
+    texts: Vec<String>,
+    outline: Vec<OutlineEntry>,
+    links: Vec<PdfLink>,
+}
+
+/// Extracts the text of the pages in `range` (every page when `None`) in
+/// order, invoking `on_page` after each one with `(current, total)` counted
+/// within the range and the page's text, along with the document outline
+/// and the links on those pages. The first `skip` pages of the range are
+/// not extracted and have no entry in `texts`. Returns `Ok(None)` when
+/// `cancelled` is raised between pages.
+fn extract_pages(
+    path: &Path,
+    range: Option<(u32, u32)>,
+    skip: usize,
+    cancelled: &AtomicBool,
+    mut on_page: impl FnMut(u32, u32, &str),
+) -> AppResult<Option<Extraction>> {
+    let document = load_document(path)?;
+    let pages: Vec<u32> = document.get_pages().keys().copied().collect();
+    if pages.is_empty() {
+        return Err(AppError::Validation(
+            "PDF does not contain any pages".into(),
+        ));
+    }
+    let pages = &pages[page_span(range, pages.len() as u32)?];
+    let total = pages.len() as u32;
+
+    let mut texts = Vec::with_capacity(pages.len().saturating_sub(skip));
+    for (index, page) in pages.iter().enumerate().skip(skip) {
+        if cancelled.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+        let text = document
+            .extract_text_with_limit(&[*page], MAX_PAGE_CONTENT_BYTES)
+            .map(|text| text.trim().to_string())
+            .unwrap_or_default();
+        on_page(index as u32 + 1, total, &text);
+        texts.push(text);
+    }
+
+    Ok(Some(Extraction {
+        texts,
+        outline: read_outline(&document),
+        links: read_links(&document, pages),
+    }))
+}
+
+/// Zero-based indexes of the pages that came back without any text.
+fn blank_pages(texts: &[String]) -> Vec<usize> {
+    texts
+        .iter()
+        .enumerate()
+        .filter(|(_, text)| text.is_empty())
+        .map(|(index, _)| index)
+        .collect()
+}
+
+fn no_text_error(ocr: bool) -> AppError {
+    let hint = if ocr {
+        "OCR could not recognize any text either"
+    } else {
+        "the PDF appears to contain only scanned images; enable OCR to index it"
+    };
+    AppError::Validation(format!("No extractable text found; {hint}"))
+}
+
+/// Page numbers `store_pages` rewrote and the ones it left untouched.
+#[derive(Debug, Default, PartialEq)]
+struct PageChanges {
+    updated: Vec<u32>,
+    skipped: Vec<u32>,
+}
+
+/// `file_pages` tables created before per-page hashes lack the column.
+async fn ensure_page_hash_column(conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    let present: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('file_pages') WHERE name = 'sha256'",
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if present == 0 {
+        sqlx::query("ALTER TABLE file_pages ADD COLUMN sha256 TEXT")
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Replaces the stored pages, outline and checksum for `file_id` and
+/// mirrors the joined text into `files.content`, which keeps `file_fts` in
+/// sync through its triggers. `pages` starts at page `first_page` and
+/// `outline` is the serialized [`OutlineEntry`] tree. A partial index passes
+/// no checksum, so the next full index is not skipped as unchanged.
+///
+/// Only pages whose text hash differs from the stored one are rewritten,
+/// and stored pages that are no longer present are dropped. A full index
+/// clears any extraction checkpoint for `file_id`, since it is now
+/// complete; a partial one leaves it for the full index to resume.
+async fn store_pages(
+    pool: &SqlitePool,
+    file_id: &str,
+    path: &Path,
+    pages: &[String],
+    first_page: u32,
+    outline: &str,
+    sha256: Option<&str>,
+) -> Result<PageChanges, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(CHECKSUM_SCHEMA).execute(&mut *tx).await?;
+    match sha256 {
+        Some(sha256) => {
+            sqlx::query(
+                "INSERT INTO file_checksums (file_id, sha256) VALUES (?, ?)
+                 ON CONFLICT(file_id) DO UPDATE SET sha256 = excluded.sha256",
+            )
+            .bind(file_id)
+            .bind(sha256)
+            .execute(&mut *tx)
+            .await?;
+        }
+        None => {
+            sqlx::query("DELETE FROM file_checksums WHERE file_id = ?")
+                .bind(file_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    sqlx::query(PAGE_SCHEMA).execute(&mut *tx).await?;
+    sqlx::query(PAGE_FTS_SCHEMA).execute(&mut *tx).await?;
+    sqlx::query(OUTLINE_SCHEMA).execute(&mut *tx).await?;
+    sqlx::query(
+        "INSERT INTO file_outlines (file_id, outline) VALUES (?, ?)
+         ON CONFLICT(file_id) DO UPDATE SET outline = excluded.outline",
+    )
+    .bind(file_id)
+    .bind(outline)
+    .execute(&mut *tx)
+    .await?;
+    ensure_page_hash_column(&mut tx).await?;
+
+    let last_page = i64::from(first_page) + pages.len() as i64 - 1;
+    for table in ["file_pages", "file_page_fts"] {
+        sqlx::query(&format!(
+            "DELETE FROM {table} WHERE file_id = ? AND (page < ? OR page > ?)"
+        ))
+        .bind(file_id)
+        .bind(i64::from(first_page))
+        .bind(last_page)
+        .execute(&mut *tx)
+        .await?;
+    }
+    let stored: HashMap<i64, Option<String>> =
+        sqlx::query_as("SELECT page, sha256 FROM file_pages WHERE file_id = ?")
+            .bind(file_id)
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .collect();
+
+    let mut changes = PageChanges::default();
+    for (page, content) in (first_page..).zip(pages) {
+        let hash = checksum::sha256_bytes(content.as_bytes());
+        if stored.get(&i64::from(page)).and_then(Option::as_deref) == Some(hash.as_str()) {
+            changes.skipped.push(page);
+            continue;
+        }
+        sqlx::query(
+            "INSERT INTO file_pages (file_id, page, content, sha256) VALUES (?, ?, ?, ?)
+             ON CONFLICT(file_id, page) DO UPDATE SET
+               content = excluded.content,
+               sha256 = excluded.sha256",
+        )
+        .bind(file_id)
+        .bind(page)
+        .bind(content)
+        .bind(&hash)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM file_page_fts WHERE file_id = ? AND page = ?")
+            .bind(file_id)
+            .bind(page)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("INSERT INTO file_page_fts (content, file_id, page) VALUES (?, ?, ?)")
+            .bind(content)
+            .bind(file_id)
+            .bind(page)
+            .execute(&mut *tx)
+            .await?;
+        changes.updated.push(page);
+    }
+
+    let stats = text_stats(pages);
+    sqlx::query(STATS_SCHEMA).execute(&mut *tx).await?;
+    sqlx::query(
+        "INSERT INTO file_stats (file_id, words, characters, reading_minutes)
+         VALUES (?, ?, ?, ?)
+         ON CONFLICT(file_id) DO UPDATE SET
+           words = excluded.words,
+           characters = excluded.characters,
+           reading_minutes = excluded.reading_minutes",
+    )
+    .bind(file_id)
+    .bind(stats.words)
+    .bind(stats.characters)
+    .bind(stats.reading_minutes)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(CHECKPOINT_SCHEMA).execute(&mut *tx).await?;
+    if sha256.is_some() {
+        sqlx::query("DELETE FROM file_index_checkpoints WHERE file_id = ?")
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let original_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    sqlx::query(
+        "INSERT INTO files (id, kind, path, original_name, content, indexed_at)
+         VALUES (?, 'pdf', ?, ?, ?, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+         ON CONFLICT(id) DO UPDATE SET
+           content = excluded.content,
+           indexed_at = excluded.indexed_at",
+    )
+    .bind(file_id)
+    .bind(path.to_string_lossy().into_owned())
+    .bind(original_name)
+    .bind(pages.join("\n\n"))
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(changes)
+}
+
+/// Replaces the links stored for `file_id`.
+async fn store_links(pool: &SqlitePool, file_id: &str, links: &[PdfLink]) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(LINK_SCHEMA).execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM file_links WHERE file_id = ?")
+        .bind(file_id)
+        .execute(&mut *tx)
+        .await?;
+    for link in links {
+        sqlx::query("INSERT INTO file_links (file_id, page, rect, target) VALUES (?, ?, ?, ?)")
+            .bind(file_id)
+            .bind(link.page)
+            .bind(serde_json::to_string(&link.rect)?)
+            .bind(serde_json::to_string(&link.target)?)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// The stats recorded at the last index. Files indexed before stats were
+/// recorded are counted from their stored pages; files without text, or
+/// not indexed at all, come back as zeros.
+async fn fetch_stats(pool: &SqlitePool, file_id: &str) -> AppResult<PdfStats> {
+    sqlx::query(STATS_SCHEMA).execute(pool).await?;
+    let stored: Option<(u32, u32, u32)> = sqlx::query_as(
+        "SELECT words, characters, reading_minutes FROM file_stats WHERE file_id = ?",
+    )
+    .bind(file_id)
+    .fetch_optional(pool)
+    .await?;
+    if let Some((words, characters, reading_minutes)) = stored {
+        return Ok(PdfStats {
+            words,
+            characters,
+            reading_minutes,
+        });
+    }
+    let pages: Vec<String> = fetch_pages(pool, file_id)
+        .await?
+        .into_iter()
+        .map(|(_, content)| content)
+        .collect();
+    Ok(text_stats(&pages))
+}
+
+async fn fetch_links(pool: &SqlitePool, file_id: &str) -> AppResult<Vec<PdfLink>> {
+    sqlx::query(LINK_SCHEMA).execute(pool).await?;
+    let rows: Vec<(u32, String, String)> = sqlx::query_as(
+        "SELECT page, rect, target FROM file_links WHERE file_id = ? ORDER BY page, rowid",
+    )
+    .bind(file_id)
+    .fetch_all(pool)
+    .await?;
+    rows.into_iter()
+        .map(|(page, rect, target)| -> AppResult<PdfLink> {
+            Ok(PdfLink {
+                page,
+                rect: serde_json::from_str(&rect)?,
+                target: serde_json::from_str(&target)?,
+            })
+        })
+        .collect()
+}
+
+/// Drops the stored pages, outline, links, checksum and any checkpoint for
+/// `file_id` and
+/// clears the text mirrored into `files`, all in one transaction. The
+/// `files` row itself stays, since it belongs to whatever the document is
+/// attached to.
+/// Returns the bytes of text removed; unknown ids remove nothing.
+async fn purge_index(pool: &SqlitePool, file_id: &str) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for schema in [
+        PAGE_SCHEMA,
+        PAGE_FTS_SCHEMA,
+        OUTLINE_SCHEMA,
+        CHECKSUM_SCHEMA,
+        LINK_SCHEMA,
+        STATS_SCHEMA,
+        CHECKPOINT_SCHEMA,
+    ] {
+        sqlx::query(schema).execute(&mut *tx).await?;
+    }
+
+    let bytes: i64 = sqlx::query_scalar(
+        "SELECT
+           (SELECT COALESCE(SUM(LENGTH(CAST(content AS BLOB))), 0)
+              FROM file_pages WHERE file_id = ?1)
+         + (SELECT COALESCE(SUM(LENGTH(CAST(outline AS BLOB))), 0)
+              FROM file_outlines WHERE file_id = ?1)
+         + (SELECT COALESCE(SUM(LENGTH(CAST(content AS BLOB))), 0)
+              FROM files WHERE id = ?1 AND indexed_at IS NOT NULL)",
+    )
+    .bind(file_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    for statement in [
+        "DELETE FROM file_pages WHERE file_id = ?",
+        "DELETE FROM file_page_fts WHERE file_id = ?",
+        "DELETE FROM file_outlines WHERE file_id = ?",
+        "DELETE FROM file_checksums WHERE file_id = ?",
+        "DELETE FROM file_links WHERE file_id = ?",
+        "DELETE FROM file_stats WHERE file_id = ?",
+        "DELETE FROM file_index_checkpoints WHERE file_id = ?",
+        "UPDATE files SET content = '', indexed_at = NULL
+         WHERE id = ? AND indexed_at IS NOT NULL",
+    ] {
+        sqlx::query(statement)
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(bytes as u64)
+}
+
+/// Another file fully indexed with the checksum `sha256`, if any.
+async fn duplicate_of(
+    pool: &SqlitePool,
+    file_id: &str,
+    sha256: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query(CHECKSUM_SCHEMA).execute(pool).await?;
+    sqlx::query_scalar(
+        "SELECT file_id FROM file_checksums WHERE sha256 = ? AND file_id <> ?
+         ORDER BY file_id LIMIT 1",
+    )
+    .bind(sha256)
+    .bind(file_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Gives `file_id` a copy of the pages, outline, links and checksum stored
+/// for `existing_file_id`, replacing anything it had, and records it in
+/// `files` under `path`. Both ids end up searchable on their own.
+async fn copy_index(
+    pool: &SqlitePool,
+    existing_file_id: &str,
+    file_id: &str,
+    path: &Path,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for schema in [
+        PAGE_SCHEMA,
+        PAGE_FTS_SCHEMA,
+        OUTLINE_SCHEMA,
+        CHECKSUM_SCHEMA,
+        LINK_SCHEMA,
+        STATS_SCHEMA,
+        CHECKPOINT_SCHEMA,
+    ] {
+        sqlx::query(schema).execute(&mut *tx).await?;
+    }
+    ensure_page_hash_column(&mut tx).await?;
+
+    for statement in [
+        "DELETE FROM file_pages WHERE file_id = ?1",
+        "DELETE FROM file_page_fts WHERE file_id = ?1",
+        "DELETE FROM file_links WHERE file_id = ?1",
+        "DELETE FROM file_index_checkpoints WHERE file_id = ?1",
+        "INSERT INTO file_links (file_id, page, rect, target)
+         SELECT ?1, page, rect, target FROM file_links WHERE file_id = ?2 ORDER BY rowid",
+        "INSERT INTO file_pages (file_id, page, content, sha256)
+         SELECT ?1, page, content, sha256 FROM file_pages WHERE file_id = ?2",
+        "INSERT INTO file_page_fts (content, file_id, page)
+         SELECT content, ?1, page FROM file_page_fts WHERE file_id = ?2",
+        "INSERT INTO file_outlines (file_id, outline)
+         SELECT ?1, outline FROM file_outlines WHERE file_id = ?2
+         ON CONFLICT(file_id) DO UPDATE SET outline = excluded.outline",
+        "INSERT INTO file_checksums (file_id, sha256)
+         SELECT ?1, sha256 FROM file_checksums WHERE file_id = ?2
+         ON CONFLICT(file_id) DO UPDATE SET sha256 = excluded.sha256",
+        "INSERT INTO file_stats (file_id, words, characters, reading_minutes)
+         SELECT ?1, words, characters, reading_minutes FROM file_stats WHERE file_id = ?2
+         ON CONFLICT(file_id) DO UPDATE SET
+           words = excluded.words,
+           characters = excluded.characters,
+           reading_minutes = excluded.reading_minutes",
+    ] {
+        sqlx::query(statement)
+            .bind(file_id)
+            .bind(existing_file_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let original_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    sqlx::query(
+        "INSERT INTO files (id, kind, path, original_name, content, indexed_at)
+         SELECT ?, 'pdf', ?, ?, content, strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+         FROM files WHERE id = ?
+         ON CONFLICT(id) DO UPDATE SET
+           content = excluded.content,
+           indexed_at = excluded.indexed_at",
+    )
+    .bind(file_id)
+    .bind(path.to_string_lossy().into_owned())
+    .bind(original_name)
+    .bind(existing_file_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await
+}
+
+/// Normalizes and stores `metadata` for `file_id`, replacing what it had.
+/// Fails with `not_found` unless `file_id` is an indexed PDF.
+async fn store_metadata(
+    pool: &SqlitePool,
+    file_id: &str,
+    metadata: PdfMetadata,
+) -> AppResult<PdfMetadata> {
+    let metadata = metadata.normalized();
+    let mut tx = pool.begin().await?;
+    let known: Option<i64> =
+        sqlx::query_scalar("SELECT 1 FROM files WHERE id = ? AND kind = 'pdf'")
+            .bind(file_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+    if known.is_none() {
+        return Err(AppError::NotFound(format!("No indexed PDF {file_id}")));
+    }
+    sqlx::query(METADATA_SCHEMA).execute(&mut *tx).await?;
+    sqlx::query(
+        "INSERT INTO file_metadata (file_id, title, series, tags) VALUES (?, ?, ?, ?)
+         ON CONFLICT(file_id) DO UPDATE SET
+           title = excluded.title,
+           series = excluded.series,
+           tags = excluded.tags",
+    )
+    .bind(file_id)
+    .bind(&metadata.title)
+    .bind(&metadata.series)
+    .bind(serde_json::to_string(&metadata.tags)?)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(metadata)
+}
+
+/// Adds extracted `(page, text)` pairs to the checkpoint of `file_id`, which
+/// is replaced outright if it was taken from a file with another checksum.
+async fn save_checkpoint(
+    pool: &SqlitePool,
+    file_id: &str,
+    sha256: &str,
+    pages: &[(u32, String)],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(CHECKPOINT_SCHEMA).execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM file_index_checkpoints WHERE file_id = ? AND sha256 <> ?")
+        .bind(file_id)
+        .bind(sha256)
+        .execute(&mut *tx)
+        .await?;
+    for (page, content) in pages {
+        sqlx::query(
+            "INSERT INTO file_index_checkpoints (file_id, sha256, page, content)
+             VALUES (?, ?, ?, ?)
+             ON CONFLICT(file_id, page) DO UPDATE SET content = excluded.content",
+        )
+        .bind(file_id)
+        .bind(sha256)
+        .bind(page)
+        .bind(content)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+/// Text of the consecutive pages from `first_page` on, up to `last_page`
+/// when given, that an interrupted index of `file_id` already extracted. A
+/// checkpoint taken from a file with another checksum is discarded and
+/// nothing is resumed.
+async fn load_checkpoint(
+    pool: &SqlitePool,
+    file_id: &str,
+    sha256: &str,
+    first_page: u32,
+    last_page: Option<u32>,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query(CHECKPOINT_SCHEMA).execute(pool).await?;
+    sqlx::query("DELETE FROM file_index_checkpoints WHERE file_id = ? AND sha256 <> ?")
+        .bind(file_id)
+        .bind(sha256)
+        .execute(pool)
+        .await?;
+    let rows: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT page, content FROM file_index_checkpoints
+         WHERE file_id = ? AND page >= ? AND page <= ? ORDER BY page",
+    )
+    .bind(file_id)
+    .bind(first_page)
+    .bind(last_page.unwrap_or(u32::MAX))
+    .fetch_all(pool)
+    .await?;
+    Ok((i64::from(first_page)..)
+        .zip(rows)
+        .take_while(|(expected, (page, _))| expected == page)
+        .map(|(_, (_, content))| content)
+        .collect())
+}
+
+/// Checksum recorded the last time `file_id` was indexed, if it was.
+async fn stored_checksum(pool: &SqlitePool, file_id: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query(CHECKSUM_SCHEMA).execute(pool).await?;
+    sqlx::query_scalar("SELECT sha256 FROM file_checksums WHERE file_id = ?")
+        .bind(file_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Every indexed PDF, most recently indexed first.
+async fn list_indexed(pool: &SqlitePool) -> AppResult<Vec<IndexedFile>> {
+    sqlx::query(PAGE_SCHEMA).execute(pool).await?;
+    sqlx::query(CHECKSUM_SCHEMA).execute(pool).await?;
+    sqlx::query(METADATA_SCHEMA).execute(pool).await?;
+    let rows = sqlx::query(
+        "SELECT files.id, files.path, files.indexed_at, file_checksums.sha256,
+                (SELECT COUNT(*) FROM file_pages WHERE file_pages.file_id = files.id)
+                  AS page_count,
+                file_metadata.title, file_metadata.series, file_metadata.tags
+         FROM files
+         LEFT JOIN file_checksums ON file_checksums.file_id = files.id
+         LEFT JOIN file_metadata ON file_metadata.file_id = files.id
+         WHERE files.kind = 'pdf' AND files.indexed_at IS NOT NULL
+         ORDER BY files.indexed_at DESC",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut files = Vec::with_capacity(rows.len());
+    for row in rows {
+        let path: String = row
+            .try_get::<Option<String>, _>("path")?
+            .unwrap_or_default();
+        let tags = row
+            .try_get::<Option<String>, _>("tags")?
+            .and_then(|tags| serde_json::from_str(&tags).ok())
+            .unwrap_or_default();
+        files.push(IndexedFile {
+            file_id: row.try_get("id")?,
+            available: !path.is_empty() && Path::new(&path).is_file(),
+            path,
+            page_count: row.try_get("page_count")?,
+            indexed_at: row.try_get("indexed_at")?,
+            checksum: row.try_get("sha256")?,
+            title: row.try_get("title")?,
+            series: row.try_get("series")?,
+            tags,
+        });
+    }
+    Ok(files)
+}
+
+/// The stored outline for `file_id`; files indexed without bookmarks, or
+/// before outlines were recorded, have an empty one.
+async fn fetch_outline(pool: &SqlitePool, file_id: &str) -> AppResult<Vec<OutlineEntry>> {
+    sqlx::query(OUTLINE_SCHEMA).execute(pool).await?;
+    let outline: Option<String> =
+        sqlx::query_scalar("SELECT outline FROM file_outlines WHERE file_id = ?")
+            .bind(file_id)
+            .fetch_optional(pool)
+            .await?;
+    match outline {
+        Some(outline) => serde_json::from_str(&outline)
+            .map_err(|err| AppError::Internal(format!("Stored outline is corrupt: {err}"))),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Absolute path recorded for `file_id` when it was indexed.
+async fn stored_path(pool: &SqlitePool, file_id: &str) -> AppResult<PathBuf> {
+    let path: Option<String> = sqlx::query_scalar("SELECT path FROM files WHERE id = ?")
+        .bind(file_id)
+        .fetch_optional(pool)
+        .await?;
+    path.filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| AppError::NotFound(format!("No path is recorded for {file_id}")))
+}
+
+/// File URL handed to the system opener, pointing at `page` when the
+/// viewer can take it.
+fn viewer_target(path: &Path, page: Option<u32>, with_fragment: bool) -> AppResult<String> {
+    let mut url = Url::from_file_path(path)
+        .map_err(|_| AppError::Validation(format!("{} is not an absolute path", path.display())))?;
+    if let Some(page) = page.filter(|_| with_fragment) {
+        url.set_fragment(Some(&format!("page={page}")));
+    }
+    Ok(url.into())
+}
+
+/// Stored page text for `file_id` as `(page, text)`, in page order.
+async fn fetch_pages(pool: &SqlitePool, file_id: &str) -> AppResult<Vec<(i64, String)>> {
+    sqlx::query(PAGE_SCHEMA).execute(pool).await?;
+    Ok(
+        sqlx::query_as("SELECT page, content FROM file_pages WHERE file_id = ? ORDER BY page")
+            .bind(file_id)
+            .fetch_all(pool)
+            .await?,
+    )
+}
+
+/// The highest stored page of `file_id`, so pages `1..=count` can be
+/// requested; `NotFound` when the file has no stored text.
+async fn stored_page_count(pool: &SqlitePool, file_id: &str) -> AppResult<u32> {
+    sqlx::query(PAGE_SCHEMA).execute(pool).await?;
+    let last: Option<i64> =
+        sqlx::query_scalar("SELECT MAX(page) FROM file_pages WHERE file_id = ?")
+            .bind(file_id)
+            .fetch_one(pool)
+            .await?;
+    match last {
+        Some(last) => Ok(last.try_into().unwrap_or(0)),
+        None => Err(AppError::NotFound(format!(
+            "{file_id} has not been indexed"
+        ))),
+    }
+}
+
+/// The stored text of one page. Pages inside the document without a row,
+/// such as those before an indexed range, read as empty.
+async fn stored_page_text(pool: &SqlitePool, file_id: &str, page: u32) -> AppResult<String> {
+    let count = stored_page_count(pool, file_id).await?;
+    if page == 0 || page > count {
+        return Err(AppError::Validation(format!(
+            "Page {page} is outside {file_id}'s {count} pages"
+        )));
+    }
+    let content: Option<String> =
+        sqlx::query_scalar("SELECT content FROM file_pages WHERE file_id = ? AND page = ?")
+            .bind(file_id)
+            .bind(page)
+            .fetch_optional(pool)
+            .await?;
+    Ok(content.unwrap_or_default())
+}
+
+/// Collects `(depth, title)` headings by the page they point at, keeping
+/// document order within a page.
+fn headings_by_page(
+    entries: &[OutlineEntry],
+    depth: usize,
+    headings: &mut BTreeMap<u32, Vec<(usize, String)>>,
+) {
+    for entry in entries {
+        let title = entry.title.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !title.is_empty() {
+            headings.entry(entry.page).or_default().push((depth, title));
+        }
+        headings_by_page(&entry.children, depth + 1, headings);
+    }
+}
+
+/// Joins the pages into one document. Every page starts with a marker line;
+/// markdown also places each outline heading before the page it points at.
+fn render_pages(pages: &[(i64, String)], outline: &[OutlineEntry], markdown: bool) -> String {
+    let mut headings = BTreeMap::new();
+    if markdown {
+        headings_by_page(outline, 1, &mut headings);
+    }
+    let marker = if markdown {
+        MARKDOWN_PAGE_MARKER
+    } else {
+        TEXT_PAGE_MARKER
+    };
+
+    let mut out = String::new();
+    for (page, text) in pages {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&marker.replace("{page}", &page.to_string()));
+        out.push_str("\n\n");
+        let page_headings = u32::try_from(*page)
+            .ok()
+            .and_then(|page| headings.get(&page));
+        for (depth, title) in page_headings.into_iter().flatten() {
+            out.push_str(&"#".repeat((*depth).min(6)));
+            out.push(' ');
+            out.push_str(title);
+            out.push_str("\n\n");
+        }
+        if !text.is_empty() {
+            out.push_str(text);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Turns free text into an FTS5 match expression. Double-quoted segments stay
+/// phrases, every other word becomes its own quoted term, and the terms are
+/// joined with FTS5's implicit AND. Quoting everything keeps operators and
+/// punctuation in user input from being parsed as query syntax.
+fn fts_query(input: &str) -> Option<String> {
+    let mut terms = Vec::new();
+    for (index, segment) in input.split('"').enumerate() {
+        let words: Vec<&str> = if index % 2 == 1 {
+            vec![segment.trim()]
+        } else {
+            segment.split_whitespace().collect()
+        };
+        terms.extend(
+            words
+                .into_iter()
+                .filter(|word| !word.is_empty())
+                .map(|word| format!("\"{word}\"")),
+        );
+    }
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Ranks indexed pages against `query` with BM25; lower `rank` is a better
+/// match. Matched terms in the snippet are wrapped in `<mark>` tags.
+pub(crate) async fn search_pages(
+    pool: &SqlitePool,
+    query: &str,
+    limit: u32,
+) -> Result<Vec<PdfSearchHit>, sqlx::Error> {
+    let Some(expression) = fts_query(query) else {
+        return Ok(Vec::new());
+    };
+
+    sqlx::query(PAGE_FTS_SCHEMA).execute(pool).await?;
+    let rows = sqlx::query(
+        "SELECT file_id, page,
+                snippet(file_page_fts, 0, '<mark>', '</mark>', '…', 12) AS snippet,
+                bm25(file_page_fts) AS rank
+         FROM file_page_fts
+         WHERE file_page_fts MATCH ?
+         ORDER BY rank
+         LIMIT ?",
+    )
+    .bind(expression)
+    .bind(i64::from(limit))
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter()
+        .map(|row| {
+            Ok(PdfSearchHit {
+                file_id: row.try_get("file_id")?,
+                page: row.try_get("page")?,
+                snippet: row.try_get("snippet")?,
+                rank: row.try_get("rank")?,
+            })
+        })
+        .collect()
+}
+
+/// [`search_pages`] limited to `file_id`, every matching page in page
+/// order. Hits are counted from FTS5's `highlight`, which marks each
+/// matched term or phrase once.
+async fn find_in_file(
+    pool: &SqlitePool,
+    file_id: &str,
+    query: &str,
+) -> Result<PdfFindResult, sqlx::Error> {
+    let Some(expression) = fts_query(query) else {
+        return Ok(PdfFindResult::default());
+    };
+
+    sqlx::query(PAGE_FTS_SCHEMA).execute(pool).await?;
+    let rows: Vec<(i64, String, String)> = sqlx::query_as(
+        "SELECT CAST(page AS INTEGER) AS page,
+                snippet(file_page_fts, 0, '<mark>', '</mark>', '…', 12),
+                highlight(file_page_fts, 0, '<mark>', '</mark>')
+         FROM file_page_fts
+         WHERE file_page_fts MATCH ? AND file_id = ?
+         ORDER BY page",
+    )
+    .bind(expression)
+    .bind(file_id)
+    .fetch_all(pool)
+    .await?;
+
+    let matches: Vec<PdfPageMatch> = rows
+        .into_iter()
+        .map(|(page, snippet, highlighted)| PdfPageMatch {
+            page,
+            hits: highlighted.matches("<mark>").count() as u32,
+            snippet,
+        })
+        .collect();
+    Ok(PdfFindResult {
+        total_hits: matches.iter().map(|found| found.hits).sum(),
+        matches,
+    })
+}
+
+/// Runs extraction and storage for one file. A file whose checksum matches
+/// the one stored for `file_id` is not extracted again, and one matching
+/// another file is handled as `duplicates` says. Extracted text is
+/// checkpointed every [`CHECKPOINT_INTERVAL`] pages, so an interrupted
+/// index of the same file resumes after the last checkpointed page.
+async fn index_file(
+    window: &Window,
+    file_id: &str,
+    path: &Path,
+    cancelled: Arc<AtomicBool>,
+    options: IndexOptions,
+) -> AppResult<IndexOutcome> {
+    let IndexOptions {
+        page_range,
+        ocr,
+        duplicates,
+        sha256,
+    } = options;
+    check_pdf_file(path)?;
+
+    let sha256 = match sha256 {
+        Some(sha256) => sha256,
+        None => {
+            let hashed = path.to_path_buf();
+            tauri::async_runtime::spawn_blocking(move || checksum::sha256_file(&hashed)).await??
+        }
+    };
+    let pool = db::pool(window.app_handle()).await?;
+    let first_page = page_range.map_or(1, |(first, _)| first);
+    let last_page = page_range.map(|(_, last)| last);
+    let resumed = load_checkpoint(&pool, file_id, &sha256, first_page, last_page).await?;
+    emit_progress(
+        window,
+        file_id,
+        PdfIndexPayload::Started {
+            resume_from: (!resumed.is_empty()).then(|| first_page + resumed.len() as u32),
+        },
+    );
+    // The checksum only vouches for a full index.
+    if page_range.is_none()
+        && stored_checksum(&pool, file_id).await?.as_deref() == Some(sha256.as_str())
+    {
+        emit_progress(window, file_id, PdfIndexPayload::Unchanged);
+        return Ok(IndexOutcome::Indexed);
+    }
+    if page_range.is_none() && duplicates != DuplicateMode::Index {
+        if let Some(existing_file_id) = duplicate_of(&pool, file_id, &sha256).await? {
+            if duplicates == DuplicateMode::Report {
+                return Ok(IndexOutcome::Duplicate { existing_file_id });
+            }
+            copy_index(&pool, &existing_file_id, file_id, path).await?;
+            emit_progress(
+                window,
+                file_id,
+                PdfIndexPayload::Reused { existing_file_id },
+            );
+            return Ok(IndexOutcome::Indexed);
+        }
+    }
+
+    let progress_window = window.clone();
+    let progress_id = file_id.to_string();
+    let source = path.to_path_buf();
+    let resource_dir = window.path().resource_dir().ok();
+    let checkpoint_pool = pool.clone();
+    let checkpoint_sha256 = sha256.clone();
+    let pages = tauri::async_runtime::spawn_blocking(move || {
+        // A checkpoint only saves work, so failing to write one is not an
+        // error for the index itself.
+        let checkpoint = |pending: &mut Vec<(u32, String)>| {
+            if !pending.is_empty() {
+                let _ = tauri::async_runtime::block_on(save_checkpoint(
+                    &checkpoint_pool,
+                    &progress_id,
+                    &checkpoint_sha256,
+                    pending,
+                ));
+                pending.clear();
+            }
+        };
+        let mut pending = Vec::with_capacity(CHECKPOINT_INTERVAL);
+        let mut rate = ProgressRate::default();
+        let extracted = extract_pages(
+            &source,
+            page_range,
+            resumed.len(),
+            &cancelled,
+            |current, total, text| {
+                emit_progress(
+                    &progress_window,
+                    &progress_id,
+                    PdfIndexPayload::Page {
+                        current,
+                        total,
+                        eta_ms: rate.update(Instant::now(), current, total),
+                    },
+                );
+                pending.push((first_page + current - 1, text.to_string()));
+                if pending.len() >= CHECKPOINT_INTERVAL {
+                    checkpoint(&mut pending);
+                }
+            },
+        );
+        checkpoint(&mut pending);
+        let Some(mut extraction) = extracted? else {
+            return Ok(None);
+        };
+        let mut texts = resumed;
+        texts.append(&mut extraction.texts);
+        extraction.texts = texts;
+
+        let texts = &mut extraction.texts;
+        let blank = blank_pages(texts);
+        if ocr && !blank.is_empty() {
+            let engine = ocr::PageOcr::new(resource_dir)?;
+            let offset = first_page as usize - 1;
+            let in_document: Vec<usize> = blank.iter().map(|index| index + offset).collect();
+            let recognized =
+                engine.recognize(&source, &in_document, &cancelled, |current, total, page| {
+                    emit_progress(
+                        &progress_window,
+                        &progress_id,
+                        PdfIndexPayload::Ocr {
+                            page,
+                            current,
+                            total,
+                        },
+                    );
+                })?;
+            let Some(recognized) = recognized else {
+                return Ok(None);
+            };
+            for (index, text) in blank.into_iter().zip(recognized) {
+                texts[index] = text;
+            }
+        }
+
+        if texts.iter().all(String::is_empty) {
+            return Err(no_text_error(ocr));
+        }
+        Ok(Some(extraction))
+    })
+    .await??;
+
+    let Some(Extraction {
+        texts: pages,
+        outline,
+        links,
+    }) = pages
+    else {
+        return Ok(IndexOutcome::Aborted);
+    };
+
+    let outline =
+        serde_json::to_string(&outline).map_err(|err| AppError::Internal(err.to_string()))?;
+    let sha256 = page_range.is_none().then_some(sha256.as_str());
+    let changes = store_pages(&pool, file_id, path, &pages, first_page, &outline, sha256).await?;
+    store_links(&pool, file_id, &links).await?;
+    emit_progress(
+        window,
+        file_id,
+        PdfIndexPayload::Pages {
+            updated: changes.updated,
+            skipped: changes.skipped,
+        },
+    );
+
+    // Thumbnails are a convenience; a missing renderer must not fail the
+    // index, and `get_pdf_thumbnails` retries on demand.
+    let _ = thumbnails::generate(
+        window.app_handle(),
+        file_id,
+        path,
+        Some(thumbnails::PREFETCH_PAGES),
+    )
+    .await;
+    Ok(IndexOutcome::Indexed)
+}
+
+/// Extracts and stores the text of a PDF, reporting progress on
+/// `pdf://index`. With `ocr`, pages without a text layer are rasterized and
+/// run through tesseract, which is much slower, so it is off unless the
+/// preferences turn it on.
+/// `page_range` limits indexing to an inclusive, 1-based `[first, last]`;
+/// pages outside it are not stored at all. `on_duplicate` decides what
+/// happens when another file with the same contents is already indexed;
+/// by default the file is extracted anyway.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn index_pdf(
+    window: Window,
+    state: State<'_, PdfIndexState>,
+    file_id: String,
+    absolute_path: String,
+    ocr: Option<bool>,
+    page_range: Option<(u32, u32)>,
+    on_duplicate: Option<DuplicateMode>,
+) -> AppResult<IndexOutcome> {
+    run_index_job(
+        &window,
+        &state,
+        &file_id,
+        Path::new(&absolute_path),
+        IndexOptions {
+            page_range,
+            ocr: ocr.unwrap_or_else(|| preferences::current(window.app_handle()).ocr_by_default),
+            duplicates: on_duplicate.unwrap_or_default(),
+            sha256: None,
+        },
+    )
+    .await
+}
+
+/// One `index_pdf` job: registers it, waits for a worker, indexes and
+/// emits the terminal event.
+async fn run_index_job(
+    window: &Window,
+    state: &PdfIndexState,
+    file_id: &str,
+    path: &Path,
+    options: IndexOptions,
+) -> AppResult<IndexOutcome> {
+    let (cancelled, done) = state.register(file_id)?;
+
+    let permit = match Arc::clone(&state.workers).try_acquire_owned() {
+        Ok(permit) => Ok(permit),
+        Err(_) => {
+            emit_progress(window, file_id, PdfIndexPayload::Queued);
+            Arc::clone(&state.workers).acquire_owned().await
+        }
+    };
+
+    let outcome = match permit {
+        Ok(_permit) => index_file(window, file_id, path, cancelled, options).await,
+        Err(err) => Err(AppError::Internal(err.to_string())),
+    };
+
+    if let Ok(mut jobs) = state.jobs.lock() {
+        jobs.remove(file_id);
+    }
+
+    let result = match outcome {
+        Ok(outcome) => {
+            let payload = match &outcome {
+                IndexOutcome::Indexed => PdfIndexPayload::Completed,
+                IndexOutcome::Aborted => PdfIndexPayload::Aborted,
+                IndexOutcome::Duplicate { existing_file_id } => PdfIndexPayload::Duplicate {
+                    existing_file_id: existing_file_id.clone(),
+                },
+            };
+            emit_progress(window, file_id, payload);
+            Ok(outcome)
+        }
+        Err(error) => {
+            emit_progress(
+                window,
+                file_id,
+                PdfIndexPayload::Error {
+                    message: error.to_string(),
+                },
+            );
+            Err(error)
+        }
+    };
+    done.send_replace(true);
+    result
+}
+
+/// The `.pdf` files under `folder`, in path order. Only real directories
+/// are descended into, so a symlink loop cannot trap the walk.
+fn folder_pdfs(folder: &Path, recursive: bool) -> AppResult<Vec<PathBuf>> {
+    let mut pending = vec![folder.to_path_buf()];
+    let mut found = Vec::new();
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let path = entry.path();
+            if file_type.is_dir() {
+                if recursive {
+                    pending.push(path);
+                }
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+            {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// The `file_id` a PDF at `path` was indexed under before, or a new one.
+async fn folder_file_id(pool: &SqlitePool, path: &Path) -> Result<String, sqlx::Error> {
+    let existing: Option<String> = sqlx::query_scalar(
+        "SELECT id FROM files WHERE kind = 'pdf' AND path = ?
+         ORDER BY indexed_at DESC LIMIT 1",
+    )
+    .bind(path.to_string_lossy().into_owned())
+    .fetch_optional(pool)
+    .await?;
+    match existing {
+        Some(file_id) => Ok(file_id),
+        None => {
+            sqlx::query_scalar(&format!("SELECT {}", crate::export::NEW_UUID_SQL))
+                .fetch_one(pool)
+                .await
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum FolderFileStatus {
+    Indexed,
+    /// Already indexed with the same checksum.
+    Skipped,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct FolderFailure {
+    path: String,
+    message: String,
+}
+
+/// Totals for an `index_pdf_folder` run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub(crate) struct FolderIndexSummary {
+    indexed: u32,
+    skipped: u32,
+    failed: u32,
+    cancelled: u32,
+    failures: Vec<FolderFailure>,
+}
+
+impl FolderIndexSummary {
+    fn record(&mut self, path: &Path, result: &AppResult<FolderFileStatus>) {
+        match result {
+            Ok(FolderFileStatus::Indexed) => self.indexed += 1,
+            Ok(FolderFileStatus::Skipped) => self.skipped += 1,
+            Ok(FolderFileStatus::Cancelled) => self.cancelled += 1,
+            Ok(FolderFileStatus::Failed) => self.failed += 1,
+            Err(error) => {
+                self.failed += 1;
+                self.failures.push(FolderFailure {
+                    path: path.to_string_lossy().into_owned(),
+                    message: error.message().to_string(),
+                });
+            }
+        }
+    }
+}
+
+/// Aggregate progress on `pdf://index-folder`; each file also reports on
+/// `pdf://index` under its `file_id` as a single `index_pdf` would.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum FolderIndexPayload {
+    Found {
+        total: u32,
+    },
+    /// `eta_ms` estimates the time left for the whole run from the recent
+    /// rate at which files finish, and is left out until there is one.
+    File {
+        path: String,
+        file_id: Option<String>,
+        status: FolderFileStatus,
+        done: u32,
+        total: u32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        eta_ms: Option<u64>,
+    },
+    Completed(FolderIndexSummary),
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct FolderIndexEvent<'a> {
+    folder: &'a str,
+    #[serde(flatten)]
+    payload: FolderIndexPayload,
+}
+
+/// Indexes one file of a folder run, skipping it when the checksum stored
+/// for its `file_id` still matches.
+async fn index_folder_file(
+    window: &Window,
+    pool: &SqlitePool,
+    file_id: &str,
+    path: &Path,
+    ocr: bool,
+) -> AppResult<FolderFileStatus> {
+    check_pdf_file(path)?;
+    let hashed = path.to_path_buf();
+    let sha256 =
+        tauri::async_runtime::spawn_blocking(move || checksum::sha256_file(&hashed)).await??;
+    if stored_checksum(pool, file_id).await?.as_deref() == Some(sha256.as_str()) {
+        return Ok(FolderFileStatus::Skipped);
+    }
+    let state = window.state::<PdfIndexState>();
+    // The checksum goes along so the file is not read a second time for it.
+    let options = IndexOptions {
+        ocr,
+        sha256: Some(sha256),
+        ..IndexOptions::default()
+    };
+    let outcome = run_index_job(window, &state, file_id, path, options).await?;
+    Ok(match outcome {
+        IndexOutcome::Indexed => FolderFileStatus::Indexed,
+        IndexOutcome::Aborted => FolderFileStatus::Cancelled,
+        IndexOutcome::Duplicate { .. } => FolderFileStatus::Skipped,
+    })
+}
+
+/// Indexes every PDF in `folder_path`, and its subfolders with `recursive`.
+/// Files keep the `file_id` they were indexed under before; new ones get a
+/// fresh id. All files are queued at once and share the worker pool with
+/// `index_pdf`, so `cancel_all_pdf_indexing` stops the run. Files whose
+/// checksum is unchanged are skipped without being opened by the parser.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn index_pdf_folder(
+    window: Window,
+    folder_path: String,
+    recursive: Option<bool>,
+) -> AppResult<FolderIndexSummary> {
+    let folder = PathBuf::from(&folder_path);
+    if !folder.is_dir() {
+        return Err(AppError::NotFound(format!("{folder_path} is not a folder")));
+    }
+    let paths = folder_pdfs(&folder, recursive.unwrap_or(false))?;
+    let total = paths.len() as u32;
+    let emit = |payload: FolderIndexPayload| {
+        let event = FolderIndexEvent {
+            folder: &folder_path,
+            payload,
+        };
+        let _ = window.emit(PDF_FOLDER_EVENT, &event);
+    };
+    emit(FolderIndexPayload::Found { total });
+
+    let pool = db::pool(window.app_handle()).await?;
+    let ocr = preferences::current(window.app_handle()).ocr_by_default;
+    let (results, mut finished) = tokio::sync::mpsc::unbounded_channel();
+    for path in paths {
+        let window = window.clone();
+        let pool = pool.clone();
+        let results = results.clone();
+        tauri::async_runtime::spawn(async move {
+            let file_id = folder_file_id(&pool, &path).await.map_err(AppError::from);
+            let result = match &file_id {
+                Ok(file_id) => index_folder_file(&window, &pool, file_id, &path, ocr).await,
+                Err(error) => Err(error.clone()),
+            };
+            let _ = results.send((path, file_id.ok(), result));
+        });
+    }
+    drop(results);
+
+    let mut summary = FolderIndexSummary::default();
+    let mut done = 0;
+    // Every file starts at once, so the run's start is the first sample.
+    let mut rate = ProgressRate::default();
+    rate.update(Instant::now(), done, total);
+    while let Some((path, file_id, result)) = finished.recv().await {
+        done += 1;
+        summary.record(&path, &result);
+        let eta_ms = rate.update(Instant::now(), done, total);
+        emit(FolderIndexPayload::File {
+            path: path.to_string_lossy().into_owned(),
+            file_id,
+            status: result.unwrap_or(FolderFileStatus::Failed),
+            done,
+            total,
+            eta_ms,
+        });
+    }
+    emit(FolderIndexPayload::Completed(summary.clone()));
+    Ok(summary)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn cancel_index_pdf(
+    state: State<'_, PdfIndexState>,
+    file_id: String,
+) -> AppResult<()> {
+    let jobs = state
+        .jobs
+        .lock()
+        .map_err(|_| AppError::state("PDF indexing"))?;
+    if let Some(job) = jobs.get(&file_id) {
+        job.cancelled.store(true, Ordering::SeqCst);
+    }
+    Ok(())
+}
+
+/// Deletes the extracted text, outline, checksum and cached thumbnails of
+/// `file_id`, returning the bytes freed. Fails with `already_running` while
+/// the file is being indexed, and `index_pdf` is refused while this runs.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn remove_indexed_file(
+    app: AppHandle,
+    state: State<'_, PdfIndexState>,
+    file_id: String,
+) -> AppResult<u64> {
+    // Holding the job slot keeps `index_pdf` from writing pages back
+    // halfway through the purge.
+    let (_, done) = state.register(&file_id)?;
+    let removed = async {
+        let pool = db::pool(&app).await?;
+        let text = purge_index(&pool, &file_id).await?;
+        let images = thumbnails::remove(&app, &file_id)?;
+        Ok::<_, AppError>(text + images)
+    }
+    .await;
+
+    if let Ok(mut jobs) = state.jobs.lock() {
+        jobs.remove(&file_id);
+    }
+    done.send_replace(true);
+    removed
+}
+
+/// Cancels every queued or running `index_pdf` job and waits for each to
+/// emit its terminal event. Returns the number cancelled, 0 when idle.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn cancel_all_pdf_indexing(state: State<'_, PdfIndexState>) -> AppResult<usize> {
+    state.cancel_all_and_wait().await
+}
+
+/// Resizes the extraction worker pool. Shrinking never interrupts running
+/// jobs; the surplus permits are retired as those jobs finish.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn set_pdf_concurrency(
+    state: State<'_, PdfIndexState>,
+    n: usize,
+) -> AppResult<usize> {
+    state.resize(n)
+}
+
+/// Lists the PDFs that have been indexed, for the library view.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn get_indexed_files(app: AppHandle) -> AppResult<Vec<IndexedFile>> {
+    let pool = db::pool(&app).await?;
+    list_indexed(&pool).await
+}
+
+/// Sets the title, series and tags the library shows for `file_id` and
+/// returns them as stored.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn set_pdf_metadata(
+    app: AppHandle,
+    file_id: String,
+    metadata: PdfMetadata,
+) -> AppResult<PdfMetadata> {
+    let pool = db::pool(&app).await?;
+    store_metadata(&pool, &file_id, metadata).await
+}
+
+/// The extracted text of `file_id` as `"txt"` or `"md"`, ready to hand to
+/// `file_save`. Markdown keeps the outline as headings.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn export_pdf_text(
+    app: AppHandle,
+    file_id: String,
+    format: String,
+) -> AppResult<String> {
+    let markdown = match format.as_str() {
+        "txt" => false,
+        "md" => true,
+        other => {
+            return Err(AppError::Validation(format!(
+                "Unsupported export format {other}; expected txt or md"
+            )))
+        }
+    };
+    let pool = db::pool(&app).await?;
+    let pages = fetch_pages(&pool, &file_id).await?;
+    if pages.is_empty() {
+        return Err(AppError::NotFound(format!(
+            "{file_id} has not been indexed"
+        )));
+    }
+    let outline = if markdown {
+        fetch_outline(&pool, &file_id).await?
+    } else {
+        Vec::new()
+    };
+    Ok(render_pages(&pages, &outline, markdown))
+}
+
+/// The number of pages the viewer can request from `get_pdf_page_text`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn get_pdf_page_count(app: AppHandle, file_id: String) -> AppResult<u32> {
+    let pool = db::pool(&app).await?;
+    stored_page_count(&pool, &file_id).await
+}
+
+/// The stored text of a single 1-based `page`, so the viewer can fetch
+/// pages as they scroll into view. Empty for a page without text.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn get_pdf_page_text(
+    app: AppHandle,
+    file_id: String,
+    page: u32,
+) -> AppResult<String> {
+    let pool = db::pool(&app).await?;
+    stored_page_text(&pool, &file_id, page).await
+}
+
+/// Returns the bookmark tree recorded when `file_id` was indexed as
+/// `[{ title, page, children }]`, or an empty list when there is none.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn get_pdf_outline(
+    app: AppHandle,
+    file_id: String,
+) -> AppResult<Vec<OutlineEntry>> {
+    let pool = db::pool(&app).await?;
+    fetch_outline(&pool, &file_id).await
+}
+
+/// Word and character counts and an estimated reading time for `file_id`,
+/// updated on every index. Zeros when the file has no stored text.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn get_pdf_stats(app: AppHandle, file_id: String) -> AppResult<PdfStats> {
+    let pool = db::pool(&app).await?;
+    fetch_stats(&pool, &file_id).await
+}
+
+/// The link annotations found when `file_id` was indexed, in page order.
+/// Files indexed before links were recorded have none until re-indexed.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn get_pdf_links(app: AppHandle, file_id: String) -> AppResult<Vec<PdfLink>> {
+    let pool = db::pool(&app).await?;
+    fetch_links(&pool, &file_id).await
+}
+
+/// Opens an indexed PDF in the system's default viewer, at `page` (1-based)
+/// where the platform supports it.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn open_pdf_external(
+    app: AppHandle,
+    file_id: String,
+    page: Option<u32>,
+) -> AppResult<()> {
+    if page == Some(0) {
+        return Err(AppError::Validation("Pages are numbered from 1".into()));
+    }
+    let pool = db::pool(&app).await?;
+    let recorded = stored_path(&pool, &file_id).await?;
+    let path = crate::scoped_path(&app, &recorded.to_string_lossy())?;
+    if !path.is_file() {
+        return Err(AppError::NotFound(format!(
+            "{} no longer exists",
+            path.display()
+        )));
+    }
+    let target = viewer_target(&path, page, PAGE_FRAGMENT_SUPPORTED)?;
+    // The opener plugin is not a dependency; the shell plugin's `open`
+    // still covers this and is limited by the `plugins.shell.open` scope,
+    // which only lets `file://` URLs through when they name a `.pdf`.
+    #[allow(deprecated)]
+    app.shell().open(target, None)?;
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn search_pdf_text(
+    app: AppHandle,
+    query: String,
+    limit: Option<u32>,
+) -> AppResult<Vec<PdfSearchHit>> {
+    let limit = limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+    let pool = db::pool(&app).await?;
+    Ok(search_pages(&pool, &query, limit).await?)
+}
+
+/// Find within one indexed PDF for the reader. No matches, or a query with
+/// no searchable words, is an empty result rather than an error.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn search_in_pdf(
+    app: AppHandle,
+    file_id: String,
+    query: String,
+) -> AppResult<PdfFindResult> {
+    let pool = db::pool(&app).await?;
+    Ok(find_in_file(&pool, &file_id, &query).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blank_pages_drive_the_ocr_pass() {
+        let texts = vec![
+            "Perks".to_string(),
+            String::new(),
+            "Items".into(),
+            String::new(),
+        ];
+        assert_eq!(blank_pages(&texts), vec![1, 3]);
+        assert!(no_text_error(false).message().contains("enable OCR"));
+        assert!(!no_text_error(true).message().contains("enable OCR"));
+    }
+
+    #[tokio::test]
+    async fn cancel_all_waits_for_terminal_events() {
+        let state = PdfIndexState::default();
+        assert_eq!(state.cancel_all_and_wait().await.unwrap(), 0);
+
+        let (cancelled, done) = state.register("naruto").unwrap();
+        assert_eq!(
+            state.register("naruto").err().unwrap().code(),
+            "already_running"
+        );
+        let jobs = Arc::clone(&state.jobs);
+        let job = tokio::spawn(async move {
+            while !cancelled.load(Ordering::SeqCst) {
+                tokio::task::yield_now().await;
+            }
+            jobs.lock().unwrap().remove("naruto");
+            done.send_replace(true);
+        });
+
+        assert_eq!(state.cancel_all_and_wait().await.unwrap(), 1);
+        assert!(state.jobs.lock().unwrap().is_empty());
+        job.await.unwrap();
+    }
+
+    #[test]
+    fn outline_rows_nest_by_level() {
+        let outline = nest_outline(vec![
+            (1, "Origins".into(), 2),
+            (2, "Drop-In".into(), 2),
+            (2, "Ninja ".into(), 3),
+            (3, "Clans".into(), 4),
+            (1, "Perks".into(), 6),
+            (3, "Deep Cut".into(), 7),
+        ]);
+        let json = serde_json::to_value(&outline).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                { "title": "Origins", "page": 2, "children": [
+                    { "title": "Drop-In", "page": 2, "children": [] },
+                    { "title": "Ninja", "page": 3, "children": [
+                        { "title": "Clans", "page": 4, "children": [] },
+                    ] },
+                ] },
+                { "title": "Perks", "page": 6, "children": [
+                    { "title": "Deep Cut", "page": 7, "children": [] },
+                ] },
+            ])
+        );
+        assert!(nest_outline(Vec::new()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn store_pages_records_the_checksum() {
+        let pool = db::memory_pool().await;
+        sqlx::query(
+            "CREATE TABLE files (id TEXT PRIMARY KEY, kind TEXT, path TEXT, original_name TEXT,
+             content TEXT, indexed_at TEXT)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert_eq!(stored_checksum(&pool, "naruto").await.unwrap(), None);
+
+        let pages = vec!["Perks".to_string()];
+        let path = Path::new("/docs/naruto.pdf");
+        store_pages(&pool, "naruto", path, &pages, 1, "[]", Some("abc123"))
+            .await
+            .unwrap();
+        store_pages(&pool, "naruto", path, &pages, 1, "[]", Some("def456"))
+            .await
+            .unwrap();
+        assert_eq!(
+            stored_checksum(&pool, "naruto").await.unwrap().as_deref(),
+            Some("def456")
+        );
+
+        let range = vec!["Drawbacks".to_string(), "Companions".into()];
+        store_pages(&pool, "naruto", path, &range, 40, "[]", None)
+            .await
+            .unwrap();
+        assert_eq!(stored_checksum(&pool, "naruto").await.unwrap(), None);
+        let stored: Vec<i64> = sqlx::query_scalar("SELECT page FROM file_pages ORDER BY page")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, vec![40, 41]);
+    }
+
+    #[tokio::test]
+    async fn checkpoints_resume_matching_files_until_stored() {
+        let pool = db::memory_pool().await;
+        sqlx::query(
+            "CREATE TABLE files (id TEXT PRIMARY KEY, kind TEXT, path TEXT, original_name TEXT,
+             content TEXT, indexed_at TEXT)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert!(load_checkpoint(&pool, "naruto", "abc123", 1, None)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let extracted = vec![
+            (1, "Perks".to_string()),
+            (2, "Items".to_string()),
+            (4, "Companions".to_string()),
+        ];
+        save_checkpoint(&pool, "naruto", "abc123", &extracted)
+            .await
+            .unwrap();
+        // Page 3 is missing, so only the run up to it can be resumed.
+        assert_eq!(
+            load_checkpoint(&pool, "naruto", "abc123", 1, None)
+                .await
+                .unwrap(),
+            vec!["Perks", "Items"]
+        );
+        assert_eq!(
+            load_checkpoint(&pool, "naruto", "abc123", 2, None)
+                .await
+                .unwrap(),
+            vec!["Items"]
+        );
+
+        // The file changed on disk: the old checkpoint is worthless.
+        assert!(load_checkpoint(&pool, "naruto", "def456", 1, None)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(load_checkpoint(&pool, "naruto", "abc123", 1, None)
+            .await
+            .unwrap()
+            .is_empty());
+
+        save_checkpoint(&pool, "naruto", "def456", &extracted[..1])
+            .await
+            .unwrap();
+        let pages = vec!["Perks".to_string(), "Items".into()];
+        store_pages(
+            &pool,
+            "naruto",
+            Path::new("/docs/naruto.pdf"),
+            &pages,
+            1,
+            "[]",
+            Some("def456"),
+        )
+        .await
+        .unwrap();
+        assert!(load_checkpoint(&pool, "naruto", "def456", 1, None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn page_ranges_resume_only_their_own_pages() {
+        let pool = db::memory_pool().await;
+        sqlx::query(
+            "CREATE TABLE files (id TEXT PRIMARY KEY, kind TEXT, path TEXT, original_name TEXT,
+             content TEXT, indexed_at TEXT)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        // An interrupted full index got through pages 1 to 6.
+        let extracted: Vec<(u32, String)> =
+            (1..=6).map(|page| (page, format!("Page {page}"))).collect();
+        save_checkpoint(&pool, "naruto", "abc123", &extracted)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            load_checkpoint(&pool, "naruto", "abc123", 2, Some(3))
+                .await
+                .unwrap(),
+            vec!["Page 2", "Page 3"]
+        );
+        assert_eq!(
+            load_checkpoint(&pool, "naruto", "abc123", 5, Some(9))
+                .await
+                .unwrap(),
+            vec!["Page 5", "Page 6"]
+        );
+
+        // Storing the range leaves the full index its checkpoint.
+        let pages = vec!["Page 2".to_string(), "Page 3".into()];
+        store_pages(
+            &pool,
+            "naruto",
+            Path::new("/docs/naruto.pdf"),
+            &pages,
+            2,
+            "[]",
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            load_checkpoint(&pool, "naruto", "abc123", 1, None)
+                .await
+                .unwrap()
+                .len(),
+            6
+        );
+    }
+
+    fn dict(entries: Vec<(&str, Object)>) -> Dictionary {
+        let mut dict = Dictionary::new();
+        for (key, value) in entries {
+            dict.set(key, value);
+        }
+        dict
+    }
+
+    /// A two-page document whose first page links out, to page 2 directly
+    /// and by name, and to a `javascript:` URI that must be dropped.
+    fn linked_document() -> Document {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let second_id = document.add_object(dict(vec![
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", Object::Reference(pages_id)),
+        ]));
+        let link = |rect: [i64; 4], key: &str, value: Object| {
+            Object::Dictionary(dict(vec![
+                ("Type", Object::Name(b"Annot".to_vec())),
+                ("Subtype", Object::Name(b"Link".to_vec())),
+                (
+                    "Rect",
+                    Object::Array(rect.into_iter().map(Object::Integer).collect()),
+                ),
+                (key, value),
+            ]))
+        };
+        let action = |kind: &str, key: &str, value: Object| {
+            Object::Dictionary(dict(vec![
+                ("S", Object::Name(kind.as_bytes().to_vec())),
+                (key, value),
+            ]))
+        };
+        let annotations = vec![
+            link(
+                [300, 700, 72, 720],
+                "A",
+                action(
+                    "URI",
+                    "URI",
+                    Object::string_literal(" https://example.com/companion "),
+                ),
+            ),
+            link(
+                [72, 600, 300, 620],
+                "Dest",
+                Object::Array(vec![
+                    Object::Reference(second_id),
+                    Object::Name(b"Fit".to_vec()),
+                ]),
+            ),
+            link(
+                [72, 500, 300, 520],
+                "A",
+                action("GoTo", "D", Object::string_literal("drawbacks")),
+            ),
+            link(
+                [72, 400, 300, 420],
+                "A",
+                action("URI", "URI", Object::string_literal("javascript:alert(1)")),
+            ),
+            Object::Dictionary(dict(vec![
+                ("Subtype", Object::Name(b"Text".to_vec())),
+                ("Rect", Object::Array(vec![Object::Integer(0); 4])),
+            ])),
+        ];
+        let first_id = document.add_object(dict(vec![
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", Object::Reference(pages_id)),
+            ("Annots", Object::Array(annotations)),
+        ]));
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dict(vec![
+                ("Type", Object::Name(b"Pages".to_vec())),
+                (
+                    "Kids",
+                    Object::Array(vec![
+                        Object::Reference(first_id),
+                        Object::Reference(second_id),
+                    ]),
+                ),
+                ("Count", Object::Integer(2)),
+            ])),
+        );
+        let dests = dict(vec![(
+            "Names",
+            Object::Array(vec![
+                Object::string_literal("drawbacks"),
+                Object::Array(vec![
+                    Object::Reference(second_id),
+                    Object::Name(b"XYZ".to_vec()),
+                ]),
+            ]),
+        )]);
+        let names = dict(vec![("Dests", Object::Dictionary(dests))]);
+        let catalog_id = document.add_object(dict(vec![
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages_id)),
+            ("Names", Object::Dictionary(names)),
+        ]));
+        document.trailer.set("Root", Object::Reference(catalog_id));
+        document
+    }
+
+    #[test]
+    fn links_resolve_pages_and_sanitize_uris() {
+        let document = linked_document();
+        let links = read_links(&document, &[1, 2]);
+        assert_eq!(
+            links,
+            vec![
+                PdfLink {
+                    page: 1,
+                    rect: [72.0, 700.0, 300.0, 720.0],
+                    target: LinkTarget::Uri {
+                        uri: "https://example.com/companion".into()
+                    },
+                },
+                PdfLink {
+                    page: 1,
+                    rect: [72.0, 600.0, 300.0, 620.0],
+                    target: LinkTarget::Page { page: 2 },
+                },
+                PdfLink {
+                    page: 1,
+                    rect: [72.0, 500.0, 300.0, 520.0],
+                    target: LinkTarget::Page { page: 2 },
+                },
+            ]
+        );
+        assert!(read_links(&document, &[2]).is_empty());
+
+        assert_eq!(
+            sanitize_link_uri(b"mailto:jumper@example.com").as_deref(),
+            Some("mailto:jumper@example.com")
+        );
+        assert_eq!(sanitize_link_uri(b"file:///etc/passwd"), None);
+        assert_eq!(sanitize_link_uri(b"/relative/path"), None);
+        assert_eq!(sanitize_link_uri(b"https://example.com/\nfake"), None);
+    }
+
+    #[test]
+    fn text_stats_count_words_and_characters() {
+        assert_eq!(text_stats::<&str>(&[]), PdfStats::default());
+        assert_eq!(text_stats(&["", "  \n "]).words, 0);
+
+        let stats = text_stats(&["Perk: Sharingan", "", "Drawback  — Curse\tof Hatred"]);
+        assert_eq!(stats.words, 7);
+        assert_eq!(stats.characters, 15 + 27);
+        assert_eq!(stats.reading_minutes, 1);
+
+        let long = vec!["word ".repeat(WORDS_PER_MINUTE as usize); 2];
+        assert_eq!(text_stats(&long).reading_minutes, 2);
+        let longer = [long.concat(), "one more".to_string()];
+        assert_eq!(text_stats(&longer).reading_minutes, 3);
+    }
+
+    #[tokio::test]
+    async fn stats_are_stored_with_the_pages() {
+        let pool = db::memory_pool().await;
+        sqlx::query(
+            "CREATE TABLE files (id TEXT PRIMARY KEY, kind TEXT, path TEXT, original_name TEXT,
+             content TEXT, indexed_at TEXT)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            fetch_stats(&pool, "naruto").await.unwrap(),
+            PdfStats::default()
+        );
+
+        let path = Path::new("/docs/naruto.pdf");
+        let pages = vec!["Ninja perks".to_string(), "Chakra".into()];
+        store_pages(&pool, "naruto", path, &pages, 1, "[]", None)
+            .await
+            .unwrap();
+        let stats = fetch_stats(&pool, "naruto").await.unwrap();
+        assert_eq!((stats.words, stats.characters), (3, 17));
+
+        // Re-indexing recounts; pages dropped from the document stop counting.
+        store_pages(&pool, "naruto", path, &pages[..1], 1, "[]", None)
+            .await
+            .unwrap();
+        let stats = fetch_stats(&pool, "naruto").await.unwrap();
+        assert_eq!((stats.words, stats.characters), (2, 11));
+
+        // Pages stored before stats existed are counted on demand.
+        sqlx::query("DELETE FROM file_stats")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(fetch_stats(&pool, "naruto").await.unwrap(), stats);
+    }
+
+    #[tokio::test]
+    async fn stored_links_are_replaced_per_file() {
+        let pool = db::memory_pool().await;
+        assert!(fetch_links(&pool, "naruto").await.unwrap().is_empty());
+
+        let links = read_links(&linked_document(), &[1]);
+        store_links(&pool, "naruto", &links).await.unwrap();
+        store_links(&pool, "bleach", &links[..1]).await.unwrap();
+        assert_eq!(fetch_links(&pool, "naruto").await.unwrap(), links);
+
+        store_links(&pool, "naruto", &links[1..]).await.unwrap();
+        assert_eq!(fetch_links(&pool, "naruto").await.unwrap(), links[1..]);
+        assert_eq!(fetch_links(&pool, "bleach").await.unwrap(), links[..1]);
+
+        let json = serde_json::to_value(&links[1]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "page": 1,
+                "rect": [72.0, 600.0, 300.0, 620.0],
+                "target": { "kind": "page", "page": 2 },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn duplicates_are_found_by_checksum_and_copied() {
+        let pool = db::memory_pool().await;
+        sqlx::query(
+            "CREATE TABLE files (id TEXT PRIMARY KEY, kind TEXT, path TEXT, original_name TEXT,
+             content TEXT, indexed_at TEXT)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert_eq!(duplicate_of(&pool, "copy", "abc123").await.unwrap(), None);
+
+        let pages = vec!["Perks".to_string(), "Drawbacks".into()];
+        let outline = r#"[{"title":"Perks","page":1,"children":[]}]"#;
+        let original = Path::new("/docs/naruto.pdf");
+        store_pages(
+            &pool,
+            "naruto",
+            original,
+            &pages,
+            1,
+            outline,
+            Some("abc123"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(duplicate_of(&pool, "naruto", "abc123").await.unwrap(), None);
+        assert_eq!(
+            duplicate_of(&pool, "copy", "abc123")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("naruto")
+        );
+
+        let renamed = Path::new("/downloads/Naruto (1).pdf");
+        copy_index(&pool, "naruto", "copy", renamed).await.unwrap();
+        assert_eq!(
+            stored_checksum(&pool, "copy").await.unwrap().as_deref(),
+            Some("abc123")
+        );
+        assert_eq!(
+            fetch_pages(&pool, "copy").await.unwrap(),
+            fetch_pages(&pool, "naruto").await.unwrap()
+        );
+        assert_eq!(
+            fetch_outline(&pool, "copy").await.unwrap(),
+            fetch_outline(&pool, "naruto").await.unwrap()
+        );
+        let (name, content): (String, String) =
+            sqlx::query_as("SELECT original_name, content FROM files WHERE id = 'copy'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(name, "Naruto (1).pdf");
+        assert_eq!(content, "Perks\n\nDrawbacks");
+
+        let hits = search_pages(&pool, "drawbacks", 10).await.unwrap();
+        let mut files: Vec<&str> = hits.iter().map(|hit| hit.file_id.as_str()).collect();
+        files.sort_unstable();
+        assert_eq!(files, ["copy", "naruto"]);
+    }
+
+    #[tokio::test]
+    async fn store_pages_rewrites_only_changed_pages() {
+        let pool = db::memory_pool().await;
+        sqlx::query(
+            "CREATE TABLE files (id TEXT PRIMARY KEY, kind TEXT, path TEXT, original_name TEXT,
+             content TEXT, indexed_at TEXT)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let path = Path::new("/docs/naruto.pdf");
+        let pages = |texts: &[&str]| {
+            texts
+                .iter()
+                .map(|text| text.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let first = store_pages(
+            &pool,
+            "naruto",
+            path,
+            &pages(&["Origins", "Perks", "Items"]),
+            1,
+            "[]",
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(first.updated, vec![1, 2, 3]);
+
+        let edited = store_pages(
+            &pool,
+            "naruto",
+            path,
+            &pages(&["Origins", "Perks v2", "Items"]),
+            1,
+            "[]",
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            edited,
+            PageChanges {
+                updated: vec![2],
+                skipped: vec![1, 3],
+            }
+        );
+        let hits = search_pages(&pool, "v2", 10).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(search_pages(&pool, "perks", 10).await.unwrap().len(), 1);
+
+        let shorter = store_pages(
+            &pool,
+            "naruto",
+            path,
+            &pages(&["Origins", "Perks v2"]),
+            1,
+            "[]",
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(shorter.skipped, vec![1, 2]);
+        let fts_rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM file_page_fts")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(fts_rows, 2);
+    }
+
+    #[test]
+    fn exports_mark_pages_and_keep_markdown_headings() {
+        let pages = vec![
+            (1, "Welcome, Jumper.".to_string()),
+            (2, String::new()),
+            (3, "Gain chakra.".to_string()),
+        ];
+        let outline = nest_outline(vec![
+            (1, "Origins".into(), 1),
+            (1, "Perks".into(), 3),
+            (2, "Body\nMod".into(), 3),
+        ]);
+
+        assert_eq!(
+            render_pages(&pages, &outline, false),
+            "--- page 1 ---\n\nWelcome, Jumper.\n\n--- page 2 ---\n\n\n--- page 3 ---\n\nGain chakra.\n"
+        );
+        assert_eq!(
+            render_pages(&pages, &outline, true),
+            "<!-- page 1 -->\n\n# Origins\n\nWelcome, Jumper.\n\n<!-- page 2 -->\n\n\n\
+             <!-- page 3 -->\n\n# Perks\n\n## Body Mod\n\nGain chakra.\n"
+        );
+    }
+
+    #[test]
+    fn page_span_validates_the_range() {
+        assert_eq!(page_span(None, 12).unwrap(), 0..12);
+        assert_eq!(page_span(Some((3, 5)), 12).unwrap(), 2..5);
+        assert_eq!(page_span(Some((12, 12)), 12).unwrap(), 11..12);
+        assert!(page_span(Some((5, 3)), 12)
+            .unwrap_err()
+            .message()
+            .contains("must run forward"));
+        assert!(page_span(Some((0, 3)), 12).is_err());
+        assert!(page_span(Some((10, 13)), 12)
+            .unwrap_err()
+            .message()
+            .contains("outside the document's 12 pages"));
+    }
+
+    #[tokio::test]
+    async fn indexed_files_flag_missing_sources() {
+        let pool = db::memory_pool().await;
+        sqlx::query(
+            "CREATE TABLE files (id TEXT PRIMARY KEY, kind TEXT, path TEXT, original_name TEXT,
+             content TEXT, indexed_at TEXT)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let dir = std::env::temp_dir().join(format!("jumpchain-library-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let present = dir.join("naruto.pdf");
+        std::fs::write(&present, b"%PDF-1.7").unwrap();
+
+        let pages = vec!["Perks".to_string(), "Drawbacks".into()];
+        store_pages(&pool, "naruto", &present, &pages, 1, "[]", Some("abc123"))
+            .await
+            .unwrap();
+        store_pages(
+            &pool,
+            "bleach",
+            &dir.join("gone.pdf"),
+            &pages[..1],
+            1,
+            "[]",
+            None,
+        )
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO files (id, kind, path) VALUES ('notes', 'text', '/notes.txt')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let metadata = PdfMetadata {
+            title: Some("  Naruto Jump  ".into()),
+            tags: vec![
+                " Anime".into(),
+                "anime".into(),
+                String::new(),
+                "Ninja ".into(),
+            ],
+            series: Some(" ".into()),
+        };
+        let stored = store_metadata(&pool, "naruto", metadata).await.unwrap();
+        assert_eq!(
+            stored,
+            PdfMetadata {
+                title: Some("Naruto Jump".into()),
+                tags: vec!["anime".into(), "ninja".into()],
+                series: None,
+            }
+        );
+        let missing = store_metadata(&pool, "notes", PdfMetadata::default()).await;
+        assert_eq!(missing.unwrap_err().code(), "not_found");
+
+        let mut files = list_indexed(&pool).await.unwrap();
+        files.sort_by(|a, b| a.file_id.cmp(&b.file_id));
+        assert_eq!(files[0].title, None);
+        assert!(files[0].tags.is_empty());
+        assert_eq!(files[1].title.as_deref(), Some("Naruto Jump"));
+        assert_eq!(files[1].tags, ["anime", "ninja"]);
+        let summary: Vec<_> = files
+            .iter()
+            .map(|file| {
+                (
+                    file.file_id.as_str(),
+                    file.page_count,
+                    file.checksum.as_deref(),
+                    file.available,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("bleach", 1, None, false),
+                ("naruto", 2, Some("abc123"), true)
+            ]
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn purge_index_removes_text_but_keeps_the_file_row() {
+        let pool = db::memory_pool().await;
+        sqlx::query(
+            "CREATE TABLE files (id TEXT PRIMARY KEY, kind TEXT, path TEXT, original_name TEXT,
+             content TEXT, indexed_at TEXT)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert_eq!(purge_index(&pool, "naruto").await.unwrap(), 0);
+
+        let pages = vec!["Perks".to_string(), "Items".into()];
+        let path = Path::new("/docs/naruto.pdf");
+        store_pages(&pool, "naruto", path, &pages, 1, "[]", Some("abc123"))
+            .await
+            .unwrap();
+
+        // Page text, the "[]" outline and the joined "Perks\n\nItems" copy.
+        assert_eq!(purge_index(&pool, "naruto").await.unwrap(), 10 + 2 + 12);
+        assert!(list_indexed(&pool).await.unwrap().is_empty());
+        let remaining: i64 = sqlx::query_scalar(
+            "SELECT (SELECT COUNT(*) FROM file_pages) + (SELECT COUNT(*) FROM file_page_fts)
+                  + (SELECT COUNT(*) FROM file_checksums) + (SELECT COUNT(*) FROM files)",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(remaining, 1);
+        assert_eq!(purge_index(&pool, "naruto").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn missing_outline_is_empty() {
+        let pool = db::memory_pool().await;
+        assert!(fetch_outline(&pool, "naruto").await.unwrap().is_empty());
+
+        sqlx::query("INSERT INTO file_outlines (file_id, outline) VALUES (?, ?)")
+            .bind("naruto")
+            .bind(r#"[{"title":"Perks","page":5,"children":[]}]"#)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let outline = fetch_outline(&pool, "naruto").await.unwrap();
+        assert_eq!(outline.len(), 1);
+        assert_eq!((outline[0].title.as_str(), outline[0].page), ("Perks", 5));
+    }
+
+    #[tokio::test]
+    async fn stored_path_requires_a_recorded_file() {
+        let pool = db::memory_pool().await;
+        sqlx::query("CREATE TABLE files (id TEXT PRIMARY KEY, path TEXT)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO files (id, path) VALUES ('naruto', '/docs/naruto.pdf')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            stored_path(&pool, "naruto").await.unwrap(),
+            PathBuf::from("/docs/naruto.pdf")
+        );
+        let error = stored_path(&pool, "bleach").await.unwrap_err();
+        assert_eq!(error.code(), "not_found");
+    }
+
+    #[test]
+    fn check_pdf_file_rejects_mislabeled_files() {
+        let dir = std::env::temp_dir().join(format!("jumpchain-signature-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fake = dir.join("notes.pdf");
+        std::fs::write(&fake, "just some notes\n").unwrap();
+        let real = dir.join("real.pdf");
+        std::fs::write(&real, b"\n%PDF-1.7\n").unwrap();
+
+        let error = check_pdf_file(&fake).unwrap_err();
+        assert_eq!(error.code(), "validation");
+        assert!(error.message().contains("is not a PDF"));
+        assert!(check_pdf_file(&real).is_ok());
+        assert_eq!(
+            check_pdf_file(&dir.join("missing.pdf")).unwrap_err().code(),
+            "not_found"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn page_text_is_served_one_page_at_a_time() {
+        let pool = db::memory_pool().await;
+        sqlx::query(
+            "CREATE TABLE files (id TEXT PRIMARY KEY, kind TEXT, path TEXT, original_name TEXT,
+             content TEXT, indexed_at TEXT)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            stored_page_count(&pool, "naruto").await.unwrap_err().code(),
+            "not_found"
+        );
+
+        let pages = vec!["Chakra".to_string(), String::new()];
+        let path = Path::new("/docs/naruto.pdf");
+        store_pages(&pool, "naruto", path, &pages, 2, "[]", None)
+            .await
+            .unwrap();
+
+        assert_eq!(stored_page_count(&pool, "naruto").await.unwrap(), 3);
+        assert_eq!(stored_page_text(&pool, "naruto", 1).await.unwrap(), "");
+        assert_eq!(
+            stored_page_text(&pool, "naruto", 2).await.unwrap(),
+            "Chakra"
+        );
+        assert_eq!(stored_page_text(&pool, "naruto", 3).await.unwrap(), "");
+        for page in [0, 4] {
+            let error = stored_page_text(&pool, "naruto", page).await.unwrap_err();
+            assert_eq!(error.code(), "validation");
+        }
+    }
+
+    #[test]
+    fn folder_pdfs_walks_subfolders_only_when_recursive() {
+        let dir = std::env::temp_dir().join(format!("jumpchain-folder-{}", std::process::id()));
+        let nested = dir.join("Supplements");
+        std::fs::create_dir_all(&nested).unwrap();
+        for path in [
+            dir.join("Naruto.pdf"),
+            dir.join("Bleach.PDF"),
+            dir.join("notes.txt"),
+            nested.join("Body Mod.pdf"),
+        ] {
+            std::fs::write(path, b"%PDF-1.7\n").unwrap();
+        }
+
+        assert_eq!(
+            folder_pdfs(&dir, false).unwrap(),
+            vec![dir.join("Bleach.PDF"), dir.join("Naruto.pdf")]
+        );
+        assert_eq!(
+            folder_pdfs(&dir, true).unwrap(),
+            vec![
+                dir.join("Bleach.PDF"),
+                dir.join("Naruto.pdf"),
+                nested.join("Body Mod.pdf"),
+            ]
+        );
+
+        let mut summary = FolderIndexSummary::default();
+        summary.record(&dir.join("Naruto.pdf"), &Ok(FolderFileStatus::Indexed));
+        summary.record(&dir.join("Bleach.PDF"), &Ok(FolderFileStatus::Skipped));
+        summary.record(
+            &dir.join("notes.pdf"),
+            &Err(AppError::Validation("notes.pdf is not a PDF".into())),
+        );
+        assert_eq!(
+            (summary.indexed, summary.skipped, summary.failed),
+            (1, 1, 1)
+        );
+        assert_eq!(summary.failures[0].message, "notes.pdf is not a PDF");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn folder_files_keep_their_previous_id() {
+        let pool = db::memory_pool().await;
+        sqlx::query(
+            "CREATE TABLE files (id TEXT PRIMARY KEY, kind TEXT, path TEXT, original_name TEXT,
+             content TEXT, indexed_at TEXT)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let path = Path::new("/docs/naruto.pdf");
+        store_pages(&pool, "naruto", path, &["Perks".to_string()], 1, "[]", None)
+            .await
+            .unwrap();
+
+        assert_eq!(folder_file_id(&pool, path).await.unwrap(), "naruto");
+        let fresh = folder_file_id(&pool, Path::new("/docs/bleach.pdf"))
+            .await
+            .unwrap();
+        assert_eq!(fresh.len(), 36);
+        assert_ne!(fresh, "naruto");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn viewer_target_adds_the_page_when_supported() {
+        let path = Path::new("/docs/jump docs/naruto.pdf");
+        assert_eq!(
+            viewer_target(path, Some(12), true).unwrap(),
+            "file:///docs/jump%20docs/naruto.pdf#page=12"
+        );
+        assert_eq!(
+            viewer_target(path, Some(12), false).unwrap(),
+            "file:///docs/jump%20docs/naruto.pdf"
+        );
+        assert!(viewer_target(Path::new("naruto.pdf"), None, true).is_err());
+    }
+
+    #[test]
+    fn fts_query_quotes_terms_and_keeps_phrases() {
+        assert_eq!(fts_query("  "), None);
+        assert_eq!(fts_query("fire ball"), Some("\"fire\" \"ball\"".into()));
+        assert_eq!(
+            fts_query("\"body mod\" OR drawback*"),
+            Some("\"body mod\" \"OR\" \"drawback*\"".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn search_ranks_pages_and_highlights_matches() {
+        let pool = db::memory_pool().await;
+        sqlx::query(PAGE_FTS_SCHEMA).execute(&pool).await.unwrap();
+        for (file_id, page, content) in [
+            ("naruto", 1, "The Body Mod grants chakra reserves."),
+            ("naruto", 2, "Drawbacks: a rival who hates you."),
+            ("bleach", 4, "Body armour is not a body mod."),
+        ] {
+            sqlx::query("INSERT INTO file_page_fts (content, file_id, page) VALUES (?, ?, ?)")
+                .bind(content)
+                .bind(file_id)
+                .bind(page)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let hits = search_pages(&pool, "\"body mod\" chakra", 10)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].file_id.as_str(), hits[0].page), ("naruto", 1));
+        assert!(hits[0].snippet.contains("<mark>Body Mod</mark>"));
+
+        let hits = search_pages(&pool, "body", 10).await.unwrap();
+        assert_eq!(hits.len(), 2);
+        assert!(search_pages(&pool, "summoning", 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn find_in_file_stays_in_one_document() {
+        let pool = db::memory_pool().await;
+        sqlx::query(PAGE_FTS_SCHEMA).execute(&pool).await.unwrap();
+        for (file_id, page, content) in [
+            ("naruto", 12, "Chakra control. More chakra than a Kage."),
+            ("naruto", 3, "Chakra reserves grow with training."),
+            ("naruto", 7, "Drawbacks: a rival who hates you."),
+            ("bleach", 1, "Reiatsu is not chakra."),
+        ] {
+            sqlx::query("INSERT INTO file_page_fts (content, file_id, page) VALUES (?, ?, ?)")
+                .bind(content)
+                .bind(file_id)
+                .bind(page)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let found = find_in_file(&pool, "naruto", "chakra").await.unwrap();
+        let pages: Vec<(i64, u32)> = found
+            .matches
+            .iter()
+            .map(|found| (found.page, found.hits))
+            .collect();
+        assert_eq!(pages, [(3, 1), (12, 2)]);
+        assert_eq!(found.total_hits, 3);
+        assert!(found.matches[0].snippet.contains("<mark>Chakra</mark>"));
+
+        assert_eq!(
+            find_in_file(&pool, "naruto", "reiatsu").await.unwrap(),
+            PdfFindResult::default()
+        );
+        assert_eq!(
+            find_in_file(&pool, "naruto", "  ").await.unwrap(),
+            PdfFindResult::default()
+        );
+    }
+
+    #[test]
+    fn progress_rate_estimates_from_recent_samples() {
+        let start = Instant::now();
+        let at = |millis| start + std::time::Duration::from_millis(millis);
+        let mut rate = ProgressRate::default();
+        assert_eq!(rate.update(at(0), 1, 10), None);
+        assert_eq!(rate.update(at(0), 2, 10), None);
+        assert_eq!(rate.update(at(200), 3, 10), Some(700));
+
+        // Only the last ETA_WINDOW samples count, so a slow start fades.
+        for done in 4..=12 {
+            rate.update(at(200 + (done as u64 - 3) * 50), done, 20);
+        }
+        assert_eq!(rate.update(at(700), 13, 20), Some(350));
+        assert_eq!(rate.update(at(750), 20, 20), Some(0));
+    }
+
+    #[tokio::test]
+    async fn regrowing_cancels_a_pending_shrink() {
+        let state = PdfIndexState::default();
+        state.resize(2).unwrap();
+        let busy = Arc::clone(&state.workers)
+            .acquire_many_owned(2)
+            .await
+            .unwrap();
+
+        // Both workers are busy, so the shrink can only retire one later.
+        state.resize(1).unwrap();
+        state.resize(3).unwrap();
+        assert_eq!(state.sizing.lock().unwrap().debt, 0);
+
+        drop(busy);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(state.workers.available_permits(), 3);
+
+        let busy = Arc::clone(&state.workers)
+            .acquire_many_owned(3)
+            .await
+            .unwrap();
+        state.resize(1).unwrap();
+        drop(busy);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(state.workers.available_permits(), 1);
+        assert_eq!(state.sizing.lock().unwrap().debt, 0);
+    }
+}
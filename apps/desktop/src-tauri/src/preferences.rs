@@ -0,0 +1,301 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::{AppError, AppResult};
+use crate::logging::{self, LogLevel};
+use crate::pdf::{self, PdfIndexState};
+use crate::snapshots;
+
+const PREFERENCES_FILE_NAME: &str = "preferences.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// Settings the backend acts on, kept in the app config dir so they
+/// survive a reinstall of the frontend. Missing fields take their defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Preferences {
+    pub(crate) theme: Theme,
+    pub(crate) pdf_concurrency: usize,
+    /// Where `file_pick` opens when the caller gives no `start_dir`.
+    pub(crate) default_pick_dir: Option<String>,
+    /// Package manager used when `run_full_test_suite` names none.
+    pub(crate) test_runner: Option<String>,
+    /// Whether `index_pdf` runs OCR when the caller does not say.
+    pub(crate) ocr_by_default: bool,
+    /// Seconds between autosave snapshots of the open character; 0 turns
+    /// autosave off.
+    pub(crate) autosave_interval_secs: u64,
+    /// How much the trace log records.
+    pub(crate) log_level: LogLevel,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            pdf_concurrency: pdf::DEFAULT_PDF_CONCURRENCY,
+            default_pick_dir: None,
+            test_runner: None,
+            ocr_by_default: false,
+            autosave_interval_secs: snapshots::DEFAULT_AUTOSAVE_INTERVAL_SECS,
+            log_level: LogLevel::default(),
+        }
+    }
+}
+
+impl Preferences {
+    /// Checks every value and returns the preferences as they will be
+    /// stored, with blank strings cleared and the runner name normalized.
+    fn validated(self) -> AppResult<Self> {
+        if self.pdf_concurrency == 0 {
+            return Err(AppError::Validation(
+                "PDF concurrency must be at least 1".into(),
+            ));
+        }
+        if self.autosave_interval_secs != 0
+            && self.autosave_interval_secs < snapshots::MIN_AUTOSAVE_INTERVAL_SECS
+        {
+            return Err(AppError::Validation(format!(
+                "Autosave interval must be 0 (off) or at least {}s",
+                snapshots::MIN_AUTOSAVE_INTERVAL_SECS
+            )));
+        }
+        let default_pick_dir = match non_blank(self.default_pick_dir) {
+            Some(dir) if !Path::new(&dir).is_dir() => {
+                return Err(AppError::Validation(format!(
+                    "Default pick directory {dir} is not a directory"
+                )));
+            }
+            dir => dir,
+        };
+        let test_runner = match non_blank(self.test_runner) {
+            Some(runner) => Some(crate::resolve_test_runner(Some(runner))?.to_string()),
+            None => None,
+        };
+        Ok(Self {
+            default_pick_dir,
+            test_runner,
+            ..self
+        })
+    }
+}
+
+fn non_blank(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// The preferences in effect, loaded once at startup.
+#[derive(Default)]
+pub(crate) struct PreferencesState {
+    current: Mutex<Preferences>,
+}
+
+fn store_path(app: &AppHandle) -> AppResult<PathBuf> {
+    let config_dir = app.path().app_config_dir()?;
+    Ok(config_dir.join(PREFERENCES_FILE_NAME))
+}
+
+/// A missing, unreadable or invalid file yields the defaults, so a bad edit
+/// by hand never keeps the app from starting.
+fn load_preferences(path: &Path) -> Preferences {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<Preferences>(&contents).ok())
+        .and_then(|prefs| prefs.validated().ok())
+        .unwrap_or_default()
+}
+
+fn save_preferences(path: &Path, prefs: &Preferences) -> AppResult<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let contents = serde_json::to_string_pretty(prefs)?;
+    Ok(std::fs::write(path, contents)?)
+}
+
+/// A copy of the preferences in effect; defaults if the state is poisoned.
+pub(crate) fn current(app: &AppHandle) -> Preferences {
+    app.state::<PreferencesState>()
+        .current
+        .lock()
+        .map(|prefs| prefs.clone())
+        .unwrap_or_default()
+}
+
+/// Reads the stored preferences and applies the ones that configure
+/// long-lived state, such as the PDF worker pool and the trace log level.
+pub(crate) fn load(app: &AppHandle) -> AppResult<()> {
+    let prefs = load_preferences(&store_path(app)?);
+    app.state::<PdfIndexState>().resize(prefs.pdf_concurrency)?;
+    logging::set_level(prefs.log_level)?;
+    let state = app.state::<PreferencesState>();
+    let mut current = state
+        .current
+        .lock()
+        .map_err(|_| AppError::state("preferences"))?;
+    *current = prefs;
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn get_preferences(state: State<'_, PreferencesState>) -> AppResult<Preferences> {
+    state
+        .current
+        .lock()
+        .map(|prefs| prefs.clone())
+        .map_err(|_| AppError::state("preferences"))
+}
+
+/// Validates and stores `prefs`, applying them at once, and returns them as
+/// stored. Nothing is written when a value is rejected.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn set_preferences(
+    app: AppHandle,
+    state: State<'_, PreferencesState>,
+    pdf_state: State<'_, PdfIndexState>,
+    prefs: Preferences,
+) -> AppResult<Preferences> {
+    let prefs = prefs.validated()?;
+    let mut current = state
+        .current
+        .lock()
+        .map_err(|_| AppError::state("preferences"))?;
+    save_preferences(&store_path(&app)?, &prefs)?;
+    if prefs.pdf_concurrency != current.pdf_concurrency {
+        pdf_state.resize(prefs.pdf_concurrency)?;
+    }
+    if prefs.log_level != current.log_level {
+        logging::set_level(prefs.log_level)?;
+    }
+    *current = prefs.clone();
+    Ok(prefs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "jumpchain-preferences-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn preferences_are_validated_and_normalized() {
+        let dir = temp_dir("validate");
+        let prefs = Preferences {
+            theme: Theme::Dark,
+            pdf_concurrency: 4,
+            default_pick_dir: Some(format!("  {}  ", dir.display())),
+            test_runner: Some(" PNPM ".into()),
+            ocr_by_default: true,
+            autosave_interval_secs: 0,
+            log_level: LogLevel::Debug,
+        }
+        .validated()
+        .unwrap();
+        assert_eq!(prefs.default_pick_dir, Some(dir.display().to_string()));
+        assert_eq!(prefs.test_runner.as_deref(), Some("pnpm"));
+
+        let blank = Preferences {
+            default_pick_dir: Some(" ".into()),
+            test_runner: Some(String::new()),
+            ..Preferences::default()
+        }
+        .validated()
+        .unwrap();
+        assert_eq!(blank, Preferences::default());
+
+        let invalid = [
+            Preferences {
+                pdf_concurrency: 0,
+                ..Preferences::default()
+            },
+            Preferences {
+                default_pick_dir: Some(dir.join("missing").display().to_string()),
+                ..Preferences::default()
+            },
+            Preferences {
+                test_runner: Some("bash".into()),
+                ..Preferences::default()
+            },
+            Preferences {
+                autosave_interval_secs: 5,
+                ..Preferences::default()
+            },
+        ];
+        for prefs in invalid {
+            assert_eq!(prefs.validated().unwrap_err().code(), "validation");
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn stored_preferences_round_trip_and_fall_back() {
+        let dir = temp_dir("store");
+        let path = dir.join("nested").join(PREFERENCES_FILE_NAME);
+        assert_eq!(load_preferences(&path), Preferences::default());
+
+        let prefs = Preferences {
+            theme: Theme::Light,
+            pdf_concurrency: 3,
+            ocr_by_default: true,
+            ..Preferences::default()
+        };
+        save_preferences(&path, &prefs).unwrap();
+        assert_eq!(load_preferences(&path), prefs);
+
+        // Fields added later read as defaults from an older file.
+        std::fs::write(&path, r#"{"theme":"dark"}"#).unwrap();
+        let partial = load_preferences(&path);
+        assert_eq!(partial.theme, Theme::Dark);
+        assert_eq!(partial.pdf_concurrency, pdf::DEFAULT_PDF_CONCURRENCY);
+
+        std::fs::write(&path, r#"{"pdf_concurrency":0}"#).unwrap();
+        assert_eq!(load_preferences(&path), Preferences::default());
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(load_preferences(&path), Preferences::default());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::{AppError, AppResult};
+
+const RECENT_FILES_NAME: &str = "recent-files.json";
+const MAX_RECENT_FILES: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RecentFile {
+    path: String,
+    opened_at: u64,
+}
+
+/// In-memory copy of the recent-files list, loaded from disk on first use.
+#[derive(Default)]
+pub(crate) struct RecentFilesState {
+    entries: Mutex<Option<Vec<RecentFile>>>,
+}
+
+fn store_path(app: &AppHandle) -> AppResult<PathBuf> {
+    let data_dir = app.path().app_data_dir()?;
+    Ok(data_dir.join(RECENT_FILES_NAME))
+}
+
+/// A missing or unreadable store is treated as an empty list rather than an
+/// error, so a corrupt file never blocks the picker.
+fn load_entries(path: &Path) -> Vec<RecentFile> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_entries(path: &Path, entries: &[RecentFile]) -> AppResult<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let contents = serde_json::to_string_pretty(entries)?;
+    Ok(std::fs::write(path, contents)?)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn record_recent_file(
+    app: AppHandle,
+    state: State<'_, RecentFilesState>,
+    path: String,
+) -> AppResult<()> {
+    let canonical = std::fs::canonicalize(&path)
+        .map_err(|err| AppError::NotFound(format!("Unable to resolve {path}: {err}")))?
+        .to_string_lossy()
+        .into_owned();
+
+    let store = store_path(&app)?;
+    let mut guard = state
+        .entries
+        .lock()
+        .map_err(|_| AppError::state("recent files"))?;
+    let entries = guard.get_or_insert_with(|| load_entries(&store));
+
+    entries.retain(|entry| entry.path != canonical);
+    entries.insert(
+        0,
+        RecentFile {
+            path: canonical,
+            opened_at: now_millis(),
+        },
+    );
+    entries.truncate(MAX_RECENT_FILES);
+
+    save_entries(&store, entries)
+}
+
+/// Returns the most recent entries first, pruning any whose file has since
+/// been moved or deleted.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn list_recent_files(
+    app: AppHandle,
+    state: State<'_, RecentFilesState>,
+    limit: Option<usize>,
+) -> AppResult<Vec<RecentFile>> {
+    let store = store_path(&app)?;
+    let mut guard = state
+        .entries
+        .lock()
+        .map_err(|_| AppError::state("recent files"))?;
+    let entries = guard.get_or_insert_with(|| load_entries(&store));
+
+    let before = entries.len();
+    entries.retain(|entry| Path::new(&entry.path).exists());
+    if entries.len() != before {
+        save_entries(&store, entries)?;
+    }
+
+    let limit = limit.unwrap_or(MAX_RECENT_FILES);
+    Ok(entries.iter().take(limit).cloned().collect())
+}
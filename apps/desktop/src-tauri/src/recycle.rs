@@ -0,0 +1,271 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use tauri::AppHandle;
+
+use crate::db;
+use crate::error::{AppError, AppResult};
+
+/// Soft-deletable entity types: `(entity_type, table, label column, parent
+/// column)`. Deleting sets `deleted_at` to an ISO timestamp, which the
+/// main UI queries filter out.
+const RECYCLED_ENTITIES: &[(&str, &str, &str, &str)] = &[
+    ("character", "character_profiles", "name", "NULL"),
+    ("jump", "jumps", "title", "NULL"),
+    ("jump_asset", "jump_assets", "name", "jump_id"),
+];
+
+/// A tombstoned row as shown in the recycle bin.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct DeletedEntry {
+    id: String,
+    label: Option<String>,
+    /// The jump an asset belongs to; `None` for characters and jumps.
+    parent_id: Option<String>,
+    deleted_at: String,
+}
+
+fn recycled_entity(entity_type: &str) -> AppResult<(&'static str, &'static str, &'static str)> {
+    RECYCLED_ENTITIES
+        .iter()
+        .find(|(name, ..)| *name == entity_type)
+        .map(|(_, table, label, parent)| (*table, *label, *parent))
+        .ok_or_else(|| {
+            AppError::Validation(format!("{entity_type} does not support the recycle bin"))
+        })
+}
+
+/// Databases created before soft deletes lack `deleted_at`; add it the way
+/// the frontend's `ensureColumn` does. Tables the database does not have
+/// yet are left for their migration to create.
+pub(crate) async fn ensure_tombstone_columns(pool: &SqlitePool) -> AppResult<()> {
+    for (_, table, ..) in RECYCLED_ENTITIES {
+        let exists: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?",
+        )
+        .bind(table)
+        .fetch_one(pool)
+        .await?;
+        if exists == 0 {
+            continue;
+        }
+        let present: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = 'deleted_at'",
+        )
+        .bind(table)
+        .fetch_one(pool)
+        .await?;
+        if present == 0 {
+            sqlx::query(&format!("ALTER TABLE {table} ADD COLUMN deleted_at TEXT"))
+                .execute(pool)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) async fn deleted_entries(
+    pool: &SqlitePool,
+    entity_type: &str,
+) -> AppResult<Vec<DeletedEntry>> {
+    let (table, label, parent) = recycled_entity(entity_type)?;
+    ensure_tombstone_columns(pool).await?;
+    let rows = sqlx::query(&format!(
+        "SELECT id, {label} AS label, {parent} AS parent_id, deleted_at FROM {table}
+         WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC"
+    ))
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|row| DeletedEntry {
+            id: row.get("id"),
+            label: row.get("label"),
+            parent_id: row.get("parent_id"),
+            deleted_at: row.get("deleted_at"),
+        })
+        .collect())
+}
+
+pub(crate) async fn restore_entry(pool: &SqlitePool, entity_type: &str, id: &str) -> AppResult<()> {
+    let (table, ..) = recycled_entity(entity_type)?;
+    ensure_tombstone_columns(pool).await?;
+    let restored = sqlx::query(&format!(
+        "UPDATE {table} SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL"
+    ))
+    .bind(id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+    if restored == 0 {
+        return Err(AppError::NotFound(format!(
+            "No deleted {entity_type} with id {id}"
+        )));
+    }
+    Ok(())
+}
+
+/// Permanently removes rows deleted more than `older_than_days` ago, in one
+/// transaction. Purging a jump cascades to its assets.
+pub(crate) async fn purge_entries(pool: &SqlitePool, older_than_days: u32) -> AppResult<u64> {
+    ensure_tombstone_columns(pool).await?;
+    let age = format!("-{older_than_days} days");
+    let mut tx = pool.begin().await?;
+    let mut purged = 0;
+    // Children first, so the count is not skewed by cascades.
+    for (_, table, ..) in RECYCLED_ENTITIES.iter().rev() {
+        purged += sqlx::query(&format!(
+            "DELETE FROM {table} WHERE deleted_at IS NOT NULL
+             AND deleted_at <= strftime('%Y-%m-%dT%H:%M:%fZ', 'now', ?)"
+        ))
+        .bind(&age)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+    tx.commit().await?;
+    Ok(purged)
+}
+
+/// Newest deletions first.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn list_deleted(
+    app: AppHandle,
+    entity_type: String,
+) -> AppResult<Vec<DeletedEntry>> {
+    let pool = db::pool(&app).await?;
+    deleted_entries(&pool, &entity_type).await
+}
+
+/// Restoring an asset does not restore its jump; a jump still in the bin
+/// keeps hiding it.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn restore_deleted(
+    app: AppHandle,
+    entity_type: String,
+    id: String,
+) -> AppResult<()> {
+    let pool = db::pool(&app).await?;
+    restore_entry(&pool, &entity_type, &id).await
+}
+
+/// Returns how many rows were removed.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn purge_deleted(app: AppHandle, older_than_days: u32) -> AppResult<u64> {
+    let pool = db::pool(&app).await?;
+    purge_entries(&pool, older_than_days).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::{apply_migrations, MIGRATIONS};
+
+    #[tokio::test]
+    async fn recycle_bin_lists_restores_and_purges() {
+        let pool = db::memory_pool().await;
+        apply_migrations(&pool, MIGRATIONS).await.unwrap();
+        db::run_batch(
+            &pool,
+            &[
+                (
+                    "INSERT INTO jumps (id, title, deleted_at)
+                     VALUES ('j1', 'Naruto', NULL),
+                            ('j2', 'Bleach', '2000-01-01T00:00:00.000Z'),
+                            ('j3', 'Worm', strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))"
+                        .into(),
+                    None,
+                ),
+                (
+                    "INSERT INTO jump_assets (id, jump_id, asset_type, name, deleted_at)
+                     VALUES ('a1', 'j1', 'perk', 'Shadow Clone', '2001-01-01T00:00:00.000Z'),
+                            ('a2', 'j2', 'perk', 'Bankai', NULL)"
+                        .into(),
+                    None,
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let jumps = deleted_entries(&pool, "jump").await.unwrap();
+        let ids: Vec<&str> = jumps.iter().map(|entry| entry.id.as_str()).collect();
+        assert_eq!(ids, ["j3", "j2"]);
+        let assets = deleted_entries(&pool, "jump_asset").await.unwrap();
+        assert_eq!(assets[0].parent_id.as_deref(), Some("j1"));
+        assert_eq!(
+            deleted_entries(&pool, "story").await.unwrap_err().code(),
+            "validation"
+        );
+
+        restore_entry(&pool, "jump", "j3").await.unwrap();
+        assert_eq!(
+            restore_entry(&pool, "jump", "j1").await.unwrap_err().code(),
+            "not_found"
+        );
+
+        // j2 and its live asset go; a1 goes on its own.
+        assert_eq!(purge_entries(&pool, 30).await.unwrap(), 2);
+        let remaining: Vec<String> = sqlx::query_scalar(
+            "SELECT id FROM jumps UNION ALL SELECT id FROM jump_assets ORDER BY id",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(remaining, ["j1", "j3"]);
+    }
+
+    #[tokio::test]
+    async fn older_tables_gain_the_tombstone_column() {
+        let pool = db::memory_pool().await;
+        db::run_batch(
+            &pool,
+            &[
+                (
+                    "CREATE TABLE jumps (id TEXT PRIMARY KEY, title TEXT)".into(),
+                    None,
+                ),
+                (
+                    "CREATE TABLE jump_assets (id TEXT PRIMARY KEY, jump_id TEXT, name TEXT)"
+                        .into(),
+                    None,
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(deleted_entries(&pool, "jump").await.unwrap(), []);
+        let columns: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pragma_table_info('jump_assets') WHERE name = 'deleted_at'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(columns, 1);
+    }
+}
@@ -0,0 +1,474 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use serde::Serialize;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqlitePool};
+use std::collections::HashSet;
+use tauri::AppHandle;
+
+use crate::db;
+use crate::error::AppResult;
+use crate::pdf::{self, DEFAULT_SEARCH_LIMIT, MAX_SEARCH_LIMIT};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ResultType {
+    Perk,
+    Jump,
+    Item,
+    PdfPage,
+}
+
+/// How a result matched, best first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchTier {
+    ExactTitle,
+    TitlePrefix,
+    Title,
+    Body,
+    Document,
+    /// Close to the query but not containing it, as "Fire Ball" is for
+    /// "fireball".
+    Fuzzy,
+}
+
+/// One hit from `global_search`. `id` is the entity id, or the file id for
+/// `pdf_page` results, which also carry the page and a highlighted snippet.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct SearchResult {
+    #[serde(rename = "type")]
+    kind: ResultType,
+    id: String,
+    title: String,
+    jump_id: Option<String>,
+    page: Option<i64>,
+    snippet: Option<String>,
+    #[serde(skip)]
+    tier: MatchTier,
+}
+
+/// Live jumps and their perks and items, matched on title or body text. The
+/// tier is computed here so the limit never drops an exact title in favour
+/// of a body hit: `?1` is the lowercased query, `?2` and `?3` the prefix and
+/// substring LIKE patterns.
+const ENTITY_SEARCH_SQL: &str = "SELECT id, kind, title, jump_id,
+        CASE
+            WHEN lower(title) = ?1 THEN 0
+            WHEN title LIKE ?2 ESCAPE '\\' THEN 1
+            WHEN title LIKE ?3 ESCAPE '\\' THEN 2
+            ELSE 3
+        END AS tier
+    FROM (
+        SELECT id, 'jump' AS kind, title, NULL AS jump_id, world AS body
+        FROM jumps WHERE deleted_at IS NULL
+        UNION ALL
+        SELECT a.id, a.asset_type, a.name, a.jump_id, a.notes
+        FROM jump_assets a JOIN jumps j ON j.id = a.jump_id
+        WHERE a.asset_type IN ('perk', 'item')
+          AND a.deleted_at IS NULL AND j.deleted_at IS NULL
+    )
+    WHERE title LIKE ?3 ESCAPE '\\' OR body LIKE ?3 ESCAPE '\\'
+    ORDER BY tier, title COLLATE NOCASE
+    LIMIT ?4";
+
+/// Live jumps, perks and items whose titles contain any of the `{filter}`
+/// LIKE clauses, the candidates for fuzzy ranking.
+const FUZZY_CANDIDATES_SQL: &str = "SELECT id, kind, title, jump_id
+    FROM (
+        SELECT id, 'jump' AS kind, title, NULL AS jump_id
+        FROM jumps WHERE deleted_at IS NULL
+        UNION ALL
+        SELECT a.id, a.asset_type, a.name, a.jump_id
+        FROM jump_assets a JOIN jumps j ON j.id = a.jump_id
+        WHERE a.asset_type IN ('perk', 'item')
+          AND a.deleted_at IS NULL AND j.deleted_at IS NULL
+    )
+    WHERE {filter}
+    LIMIT ?";
+
+/// Candidate rows ranked per fuzzy search, so a huge perk table is never
+/// read in full.
+const FUZZY_SCAN_LIMIT: u32 = 500;
+/// Query trigrams used to narrow the fuzzy candidates in SQL.
+const MAX_FUZZY_TRIGRAMS: usize = 16;
+/// Trigram similarity, from 0 to 1, a title needs to count as a fuzzy match.
+const FUZZY_THRESHOLD: f64 = 0.45;
+
+/// Escapes LIKE wildcards so user input only ever matches literally.
+fn like_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Lowercased letters and digits only, so "Fire Ball" and "fireball" fold
+/// to the same text.
+fn fold(text: &str) -> String {
+    text.chars()
+        .filter(|ch| ch.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn trigrams(folded: &str) -> HashSet<[char; 3]> {
+    let padded: Vec<char> = "  ".chars().chain(folded.chars()).chain([' ']).collect();
+    padded
+        .windows(3)
+        .map(|window| [window[0], window[1], window[2]])
+        .collect()
+}
+
+/// Dice coefficient over the padded trigrams of the folded texts: 1 when
+/// they fold to the same text, 0 when they share nothing.
+fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (fold(a), fold(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let (a, b) = (trigrams(&a), trigrams(&b));
+    2.0 * a.intersection(&b).count() as f64 / (a.len() + b.len()) as f64
+}
+
+fn result_type(kind: &str) -> ResultType {
+    match kind {
+        "jump" => ResultType::Jump,
+        "perk" => ResultType::Perk,
+        _ => ResultType::Item,
+    }
+}
+
+fn entity_result(row: &SqliteRow, tier: MatchTier) -> Result<SearchResult, sqlx::Error> {
+    Ok(SearchResult {
+        kind: result_type(&row.try_get::<String, _>("kind")?),
+        id: row.try_get("id")?,
+        title: row.try_get("title")?,
+        jump_id: row.try_get("jump_id")?,
+        page: None,
+        snippet: None,
+        tier,
+    })
+}
+
+async fn search_entities(
+    pool: &SqlitePool,
+    query: &str,
+    limit: u32,
+) -> Result<Vec<SearchResult>, sqlx::Error> {
+    let escaped = like_escape(query);
+    let rows = sqlx::query(ENTITY_SEARCH_SQL)
+        .bind(query.to_lowercase())
+        .bind(format!("{escaped}%"))
+        .bind(format!("%{escaped}%"))
+        .bind(i64::from(limit))
+        .fetch_all(pool)
+        .await?;
+
+    let mut results = Vec::with_capacity(rows.len());
+    for row in &rows {
+        let tier = match row.try_get::<i64, _>("tier")? {
+            0 => MatchTier::ExactTitle,
+            1 => MatchTier::TitlePrefix,
+            2 => MatchTier::Title,
+            _ => MatchTier::Body,
+        };
+        results.push(entity_result(row, tier)?);
+    }
+    Ok(results)
+}
+
+/// Titles similar to `query` that `found` does not already hold, most
+/// similar first. Candidates must share a trigram with the query, and at
+/// most [`FUZZY_SCAN_LIMIT`] of them are ranked.
+async fn search_fuzzy(
+    pool: &SqlitePool,
+    query: &str,
+    limit: u32,
+    found: &HashSet<String>,
+) -> Result<Vec<SearchResult>, sqlx::Error> {
+    let folded: Vec<char> = fold(query).chars().collect();
+    let mut patterns: Vec<String> = Vec::new();
+    for window in folded.windows(3) {
+        let trigram: String = window.iter().collect();
+        if !patterns.contains(&trigram) && patterns.len() < MAX_FUZZY_TRIGRAMS {
+            patterns.push(trigram);
+        }
+    }
+    if patterns.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let filter = vec!["title LIKE ? ESCAPE '\\'"; patterns.len()].join(" OR ");
+    let sql = FUZZY_CANDIDATES_SQL.replace("{filter}", &filter);
+    let mut candidates = sqlx::query(&sql);
+    for pattern in &patterns {
+        candidates = candidates.bind(format!("%{}%", like_escape(pattern)));
+    }
+    let rows = candidates
+        .bind(i64::from(FUZZY_SCAN_LIMIT))
+        .fetch_all(pool)
+        .await?;
+
+    let mut scored = Vec::new();
+    for row in &rows {
+        let result = entity_result(row, MatchTier::Fuzzy)?;
+        let score = similarity(query, &result.title);
+        if score >= FUZZY_THRESHOLD && !found.contains(&result.id) {
+            scored.push((score, result));
+        }
+    }
+    scored.sort_by(|(a, left), (b, right)| {
+        b.total_cmp(a)
+            .then_with(|| left.title.to_lowercase().cmp(&right.title.to_lowercase()))
+    });
+    scored.truncate(limit as usize);
+    Ok(scored.into_iter().map(|(_, result)| result).collect())
+}
+
+/// Indexed PDF pages, titled with the file's original name where known.
+async fn search_documents(
+    pool: &SqlitePool,
+    query: &str,
+    limit: u32,
+) -> Result<Vec<SearchResult>, sqlx::Error> {
+    let mut results = Vec::new();
+    for hit in pdf::search_pages(pool, query, limit).await? {
+        let name: Option<String> =
+            sqlx::query_scalar("SELECT COALESCE(original_name, path) FROM files WHERE id = ?")
+                .bind(&hit.file_id)
+                .fetch_optional(pool)
+                .await?
+                .flatten();
+        results.push(SearchResult {
+            kind: ResultType::PdfPage,
+            title: name.unwrap_or_else(|| hit.file_id.clone()),
+            id: hit.file_id,
+            jump_id: None,
+            page: Some(hit.page),
+            snippet: Some(hit.snippet),
+            tier: MatchTier::Document,
+        });
+    }
+    Ok(results)
+}
+
+/// Merges entity and document hits: exact titles, then title prefixes, then
+/// other title matches, then body text, then PDF pages by BM25. With
+/// `fuzzy`, titles that only resemble the query follow all of those.
+pub(crate) async fn search_everything(
+    pool: &SqlitePool,
+    query: &str,
+    limit: u32,
+    fuzzy: bool,
+) -> AppResult<Vec<SearchResult>> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut results = search_entities(pool, query, limit).await?;
+    results.extend(search_documents(pool, query, limit).await?);
+    if fuzzy && results.len() < limit as usize {
+        let found: HashSet<String> = results
+            .iter()
+            .filter(|result| result.kind != ResultType::PdfPage)
+            .map(|result| result.id.clone())
+            .collect();
+        results.extend(search_fuzzy(pool, query, limit, &found).await?);
+    }
+    // Stable, so each source keeps its own ordering within a tier.
+    results.sort_by_key(|result| result.tier);
+    results.truncate(limit as usize);
+    Ok(results)
+}
+
+/// `fuzzy` also ranks jump, perk and item titles by similarity, so a typo
+/// or different spacing still finds them; it is off by default.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn global_search(
+    app: AppHandle,
+    query: String,
+    limit: Option<u32>,
+    fuzzy: Option<bool>,
+) -> AppResult<Vec<SearchResult>> {
+    let limit = limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+    let pool = db::pool(&app).await?;
+    search_everything(&pool, &query, limit, fuzzy.unwrap_or(false)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::{apply_migrations, MIGRATIONS};
+
+    #[test]
+    fn like_escape_neutralizes_wildcards() {
+        assert_eq!(like_escape("100%_off\\"), "100\\%\\_off\\\\");
+    }
+
+    #[test]
+    fn similarity_ignores_case_and_spacing() {
+        assert_eq!(similarity("fireball", "Fire Ball"), 1.0);
+        assert!(similarity("fireblal", "Fire Ball") > FUZZY_THRESHOLD);
+        assert!(similarity("fireball", "Fireproof Skin") < FUZZY_THRESHOLD);
+        assert_eq!(similarity("fireball", "Toad Sage"), 0.0);
+        assert_eq!(similarity("--", "--"), 0.0);
+    }
+
+    #[tokio::test]
+    async fn fuzzy_search_ranks_near_misses_after_exact_hits() {
+        let pool = db::memory_pool().await;
+        apply_migrations(&pool, MIGRATIONS).await.unwrap();
+        db::run_batch(
+            &pool,
+            &[
+                (
+                    "INSERT INTO jumps (id, title) VALUES ('j1', 'Fireball Academy')".into(),
+                    None,
+                ),
+                (
+                    "INSERT INTO jump_assets (id, jump_id, asset_type, name, deleted_at)
+                     VALUES ('p1', 'j1', 'perk', 'Fire Ball', NULL),
+                            ('p2', 'j1', 'perk', 'Fire Bal', NULL),
+                            ('p3', 'j1', 'perk', 'Fireproof Skin', NULL),
+                            ('p4', 'j1', 'perk', 'Fire Balls', '2000-01-01T00:00:00.000Z')"
+                        .into(),
+                    None,
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let exact = search_everything(&pool, "fireball", 10, false)
+            .await
+            .unwrap();
+        let ids: Vec<&str> = exact.iter().map(|result| result.id.as_str()).collect();
+        assert_eq!(ids, ["j1"]);
+
+        let fuzzy = search_everything(&pool, "fireball", 10, true)
+            .await
+            .unwrap();
+        let ranked: Vec<(&str, MatchTier)> = fuzzy
+            .iter()
+            .map(|result| (result.id.as_str(), result.tier))
+            .collect();
+        assert_eq!(
+            ranked,
+            [
+                ("j1", MatchTier::TitlePrefix),
+                ("p1", MatchTier::Fuzzy),
+                ("p2", MatchTier::Fuzzy),
+            ]
+        );
+
+        let capped = search_everything(&pool, "fireball", 2, true).await.unwrap();
+        assert_eq!(capped.len(), 2);
+        assert!(search_everything(&pool, "fi", 10, true)
+            .await
+            .unwrap()
+            .iter()
+            .all(|result| result.tier != MatchTier::Fuzzy));
+    }
+
+    #[tokio::test]
+    async fn global_search_ranks_titles_above_body_text() {
+        let pool = db::memory_pool().await;
+        apply_migrations(&pool, MIGRATIONS).await.unwrap();
+        db::run_batch(
+            &pool,
+            &[
+                (
+                    "INSERT INTO jumps (id, title, world, deleted_at)
+                     VALUES ('j1', 'Sage', 'Elemental Nations', NULL),
+                            ('j2', 'Sage Mode Academy', NULL, '2000-01-01T00:00:00.000Z')"
+                        .into(),
+                    None,
+                ),
+                (
+                    "INSERT INTO jump_assets (id, jump_id, asset_type, name, notes)
+                     VALUES ('p1', 'j1', 'perk', 'Sage Mode', NULL),
+                            ('i1', 'j1', 'item', 'Scroll', 'Teaches sage arts'),
+                            ('d1', 'j1', 'drawback', 'Sage Rival', NULL),
+                            ('p2', 'j1', 'perk', 'Toad Sage', NULL)"
+                        .into(),
+                    None,
+                ),
+                (
+                    "INSERT INTO files (id, kind, path, original_name)
+                     VALUES ('f1', 'pdf', '/docs/naruto.pdf', 'Naruto.pdf')"
+                        .into(),
+                    None,
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+        sqlx::query(pdf::PAGE_FTS_SCHEMA)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO file_page_fts (content, file_id, page)
+             VALUES ('Sage chakra draws on nature.', 'f1', 3)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let results = search_everything(&pool, " sage ", 10, false).await.unwrap();
+        let found: Vec<(ResultType, &str)> = results
+            .iter()
+            .map(|result| (result.kind, result.id.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (ResultType::Jump, "j1"),
+                (ResultType::Perk, "p1"),
+                (ResultType::Perk, "p2"),
+                (ResultType::Item, "i1"),
+                (ResultType::PdfPage, "f1"),
+            ]
+        );
+        assert_eq!(results[4].title, "Naruto.pdf");
+        assert_eq!(results[4].page, Some(3));
+
+        assert_eq!(
+            search_everything(&pool, "sage", 2, false)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(search_everything(&pool, "  ", 10, false)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(search_everything(&pool, "%", 10, false)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,428 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::sqlite::{SqliteConnection, SqlitePool};
+use std::io::{Cursor, Read, Write};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::checksum;
+use crate::db;
+use crate::error::{AppError, AppResult};
+use crate::export::{self, ParsedBundle};
+use crate::{preferences, recycle};
+
+const SNAPSHOT_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS snapshots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    character_id TEXT NOT NULL,
+    sha256 TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    data BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+)";
+const SNAPSHOT_INDEX: &str = "CREATE INDEX IF NOT EXISTS idx_snapshots_character
+    ON snapshots (character_id, id)";
+
+/// Snapshots kept per character; older ones are pruned as new ones land.
+const MAX_SNAPSHOTS: i64 = 20;
+
+/// Name of the single deflated entry a snapshot's zip holds.
+const SNAPSHOT_ENTRY: &str = "bundle.json";
+
+/// Autosave period unless the preferences say otherwise; 0 turns it off.
+pub(crate) const DEFAULT_AUTOSAVE_INTERVAL_SECS: u64 = 300;
+pub(crate) const MIN_AUTOSAVE_INTERVAL_SECS: u64 = 30;
+/// How often a disabled autosave checks whether it was turned back on.
+const AUTOSAVE_IDLE_POLL: Duration = Duration::from_secs(60);
+
+/// A stored snapshot, without its data.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct SnapshotInfo {
+    id: i64,
+    character_id: String,
+    created_at: String,
+    /// Compressed size as stored.
+    size_bytes: i64,
+}
+
+/// The character the frontend has open, which autosave snapshots.
+#[derive(Default)]
+pub(crate) struct SnapshotState {
+    active: Mutex<Option<String>>,
+}
+
+/// A deflated zip holding `json`, which shrinks bundle JSON several times
+/// over.
+fn compress(json: &[u8]) -> AppResult<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file(SNAPSHOT_ENTRY, deflated)?;
+    zip.write_all(json)?;
+    Ok(zip.finish()?.into_inner())
+}
+
+fn decompress(data: &[u8]) -> AppResult<Value> {
+    let mut archive = ZipArchive::new(Cursor::new(data))?;
+    let mut json = String::new();
+    archive.by_name(SNAPSHOT_ENTRY)?.read_to_string(&mut json)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// Stores the character's current bundle as a snapshot and prunes the
+/// oldest past [`MAX_SNAPSHOTS`]. Returns `None` without writing when
+/// nothing changed since the latest snapshot.
+pub(crate) async fn take_snapshot(pool: &SqlitePool, character_id: &str) -> AppResult<Option<i64>> {
+    let mut tx = pool.begin().await?;
+    let id = take_snapshot_on(&mut tx, character_id).await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// [`take_snapshot`] inside the caller's transaction, so the snapshot lands
+/// or rolls back with the changes made alongside it.
+pub(crate) async fn take_snapshot_on(
+    tx: &mut SqliteConnection,
+    character_id: &str,
+) -> AppResult<Option<i64>> {
+    let bundle = export::character_bundle_on(tx, character_id).await?;
+    let json = serde_json::to_vec(&bundle)?;
+    let sha256 = checksum::sha256_bytes(&json);
+    let data = compress(&json)?;
+
+    sqlx::query(SNAPSHOT_SCHEMA).execute(&mut *tx).await?;
+    sqlx::query(SNAPSHOT_INDEX).execute(&mut *tx).await?;
+    let latest: Option<String> = sqlx::query_scalar(
+        "SELECT sha256 FROM snapshots WHERE character_id = ? ORDER BY id DESC LIMIT 1",
+    )
+    .bind(character_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if latest.as_deref() == Some(sha256.as_str()) {
+        return Ok(None);
+    }
+
+    let id: i64 = sqlx::query_scalar(
+        "INSERT INTO snapshots (character_id, sha256, size_bytes, data) VALUES (?, ?, ?, ?)
+         RETURNING id",
+    )
+    .bind(character_id)
+    .bind(&sha256)
+    .bind(data.len() as i64)
+    .bind(&data)
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query(
+        "DELETE FROM snapshots WHERE character_id = ?1 AND id NOT IN
+           (SELECT id FROM snapshots WHERE character_id = ?1 ORDER BY id DESC LIMIT ?2)",
+    )
+    .bind(character_id)
+    .bind(MAX_SNAPSHOTS)
+    .execute(&mut *tx)
+    .await?;
+    Ok(Some(id))
+}
+
+pub(crate) async fn snapshots_for(
+    pool: &SqlitePool,
+    character_id: &str,
+) -> AppResult<Vec<SnapshotInfo>> {
+    sqlx::query(SNAPSHOT_SCHEMA).execute(pool).await?;
+    let rows: Vec<(i64, String, String, i64)> = sqlx::query_as(
+        "SELECT id, character_id, created_at, size_bytes FROM snapshots
+         WHERE character_id = ? ORDER BY id DESC",
+    )
+    .bind(character_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(id, character_id, created_at, size_bytes)| SnapshotInfo {
+            id,
+            character_id,
+            created_at,
+            size_bytes,
+        })
+        .collect())
+}
+
+/// Inserts `row` into `table` under its own id, or overwrites the row that
+/// has it. Keys that are not columns of `table` are skipped.
+async fn upsert_row(
+    conn: &mut SqliteConnection,
+    table: &str,
+    row: &Map<String, Value>,
+) -> AppResult<()> {
+    let columns = export::table_columns(conn, table).await?;
+    let fields: Vec<(&String, &Value)> = row
+        .iter()
+        .filter(|(name, _)| columns.contains(name.as_str()))
+        .collect();
+    let names: Vec<String> = fields
+        .iter()
+        .map(|(name, _)| format!("\"{name}\""))
+        .collect();
+    let updates: Vec<String> = names
+        .iter()
+        .filter(|name| name.as_str() != "\"id\"")
+        .map(|name| format!("{name} = excluded.{name}"))
+        .collect();
+    let placeholders = vec!["?"; fields.len()].join(", ");
+    let conflict = if updates.is_empty() {
+        "DO NOTHING".to_string()
+    } else {
+        format!("DO UPDATE SET {}", updates.join(", "))
+    };
+    let query = format!(
+        "INSERT INTO {table} ({}) VALUES ({placeholders}) ON CONFLICT(id) {conflict}",
+        names.join(", ")
+    );
+    let values: Vec<Value> = fields.iter().map(|(_, value)| (*value).clone()).collect();
+    db::bind_values(sqlx::query(&query), &values)
+        .execute(&mut *conn)
+        .await
+        .map_err(|err| AppError::Db(format!("Unable to restore into {table}: {err}")))?;
+    Ok(())
+}
+
+/// Puts the character's profile back the way `snapshot_id` recorded it,
+/// under the same id and out of the recycle bin if it was there. Snapshots
+/// also record the chain's jumps, but those are shared by every character,
+/// so restoring one character's snapshot leaves them alone. The profile
+/// being replaced is snapshotted in the same transaction, so a restore can
+/// be undone. Returns the character id.
+pub(crate) async fn restore(pool: &SqlitePool, snapshot_id: i64) -> AppResult<String> {
+    sqlx::query(SNAPSHOT_SCHEMA).execute(pool).await?;
+    recycle::ensure_tombstone_columns(pool).await?;
+    let stored: Option<(String, Vec<u8>)> =
+        sqlx::query_as("SELECT character_id, data FROM snapshots WHERE id = ?")
+            .bind(snapshot_id)
+            .fetch_optional(pool)
+            .await?;
+    let (character_id, data) =
+        stored.ok_or_else(|| AppError::NotFound(format!("Snapshot {snapshot_id} not found")))?;
+    let bundle = decompress(&data)?;
+    let ParsedBundle { character, .. } = export::parse_bundle(&bundle)?;
+
+    let mut tx = pool.begin().await?;
+    // A character purged since has nothing to keep.
+    match take_snapshot_on(&mut tx, &character_id).await {
+        Ok(_) | Err(AppError::NotFound(_)) => {}
+        Err(err) => return Err(err),
+    }
+    upsert_row(&mut tx, "character_profiles", character).await?;
+    sqlx::query("UPDATE character_profiles SET deleted_at = NULL WHERE id = ?")
+        .bind(&character_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(character_id)
+}
+
+/// Snapshots the active character every `autosave_interval_secs`, read
+/// afresh each time so a preference change applies from the next period.
+/// Failures are skipped; the next period tries again.
+pub(crate) fn spawn_autosave(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let interval = preferences::current(&app).autosave_interval_secs;
+            if interval == 0 {
+                tokio::time::sleep(AUTOSAVE_IDLE_POLL).await;
+                continue;
+            }
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+            let active = app
+                .state::<SnapshotState>()
+                .active
+                .lock()
+                .ok()
+                .and_then(|active| active.clone());
+            let Some(character_id) = active else {
+                continue;
+            };
+            if let Ok(pool) = db::pool(&app).await {
+                let _ = take_snapshot(&pool, &character_id).await;
+            }
+        }
+    });
+}
+
+/// Names the character autosave should snapshot, or stops it with `None`.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn set_autosave_character(
+    state: State<'_, SnapshotState>,
+    character_id: Option<String>,
+) -> AppResult<()> {
+    let mut active = state
+        .active
+        .lock()
+        .map_err(|_| AppError::state("snapshots"))?;
+    *active = character_id.filter(|id| !id.trim().is_empty());
+    Ok(())
+}
+
+/// The character's snapshots, newest first.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn list_snapshots(
+    app: AppHandle,
+    character_id: String,
+) -> AppResult<Vec<SnapshotInfo>> {
+    let pool = db::pool(&app).await?;
+    snapshots_for(&pool, &character_id).await
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn restore_snapshot(app: AppHandle, snapshot_id: i64) -> AppResult<String> {
+    let pool = db::pool(&app).await?;
+    restore(&pool, snapshot_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn snapshots_skip_unchanged_state_and_are_capped() {
+        let pool = db::seeded_chain_pool().await;
+        let first = take_snapshot(&pool, "hero").await.unwrap().unwrap();
+        assert_eq!(take_snapshot(&pool, "hero").await.unwrap(), None);
+
+        let stored: Vec<u8> = sqlx::query_scalar("SELECT data FROM snapshots WHERE id = ?")
+            .bind(first)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            decompress(&stored).unwrap(),
+            export::character_bundle(&pool, "hero").await.unwrap()
+        );
+
+        for cost in 0..MAX_SNAPSHOTS {
+            db::run_batch(
+                &pool,
+                &[(
+                    "UPDATE jump_assets SET cost = ? WHERE id = 'a1'".into(),
+                    Some(vec![json!(cost)]),
+                )],
+            )
+            .await
+            .unwrap();
+            take_snapshot(&pool, "hero").await.unwrap().unwrap();
+        }
+        let listed = snapshots_for(&pool, "hero").await.unwrap();
+        assert_eq!(listed.len(), MAX_SNAPSHOTS as usize);
+        assert!(listed.windows(2).all(|pair| pair[0].id > pair[1].id));
+        assert!(listed.iter().all(|snapshot| snapshot.id != first));
+        assert!(snapshots_for(&pool, "nobody").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn restore_rewinds_only_that_characters_profile() {
+        let pool = db::seeded_chain_pool().await;
+        db::run_batch(
+            &pool,
+            &[(
+                "INSERT INTO character_profiles (id, name) VALUES ('rival', 'Rin')".into(),
+                None,
+            )],
+        )
+        .await
+        .unwrap();
+        let snapshot = take_snapshot(&pool, "hero").await.unwrap().unwrap();
+        db::run_batch(
+            &pool,
+            &[
+                (
+                    "UPDATE character_profiles SET name = name || ' II'".into(),
+                    None,
+                ),
+                (
+                    "UPDATE jump_assets SET cost = 100 WHERE id = 'a1'".into(),
+                    None,
+                ),
+                (
+                    "INSERT INTO jumps (id, title, sort_order) VALUES ('j2', 'Bleach', 1)".into(),
+                    None,
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(restore(&pool, snapshot).await.unwrap(), "hero");
+        let rows = db::fetch_rows(
+            &pool,
+            "SELECT
+               (SELECT name FROM character_profiles WHERE id = 'hero') AS hero,
+               (SELECT name FROM character_profiles WHERE id = 'rival') AS rival,
+               (SELECT cost FROM jump_assets WHERE id = 'a1') AS cost,
+               (SELECT COUNT(*) FROM jumps WHERE deleted_at IS NULL) AS live_jumps",
+            &[],
+        )
+        .await
+        .unwrap();
+        // The other character and the shared chain are untouched.
+        assert_eq!(
+            rows,
+            vec![json!({ "hero": "Kaede", "rival": "Rin II", "cost": 100, "live_jumps": 2 })]
+        );
+
+        // The state it replaced was kept, so the restore can be undone.
+        let listed = snapshots_for(&pool, "hero").await.unwrap();
+        assert_eq!(listed.len(), 2);
+        restore(&pool, listed[0].id).await.unwrap();
+        let name: String =
+            sqlx::query_scalar("SELECT name FROM character_profiles WHERE id = 'hero'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(name, "Kaede II");
+
+        // A character in the recycle bin comes back out.
+        db::run_batch(
+            &pool,
+            &[(
+                "UPDATE character_profiles SET deleted_at = '2000-01-01' WHERE id = 'hero'".into(),
+                None,
+            )],
+        )
+        .await
+        .unwrap();
+        restore(&pool, snapshot).await.unwrap();
+        let deleted: Option<String> =
+            sqlx::query_scalar("SELECT deleted_at FROM character_profiles WHERE id = 'hero'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(deleted, None);
+
+        assert_eq!(restore(&pool, 999).await.unwrap_err().code(), "not_found");
+    }
+}
@@ -0,0 +1,771 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How many runs keep their log file; older ones are deleted.
+const KEPT_RUN_LOGS: usize = 10;
+const RUN_LOG_PREFIX: &str = "test-run-";
+const RUN_LOG_EXIT: &str = "Exited with code ";
+/// Bytes read from the end of a saved log when looking for its exit line.
+const RUN_LOG_TAIL_BYTES: u64 = 1024;
+
+/// Totals from the runner's closing summary block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub(crate) struct TestSummary {
+    pub passed: u32,
+    pub failed: u32,
+    pub skipped: u32,
+    pub duration_ms: Option<u64>,
+}
+
+/// Watches output line by line for the summary block. Vitest and Jest both
+/// print the counts line before the duration line, so the summary is held
+/// until its duration arrives (or the run ends) and then yielded once.
+#[derive(Debug, Default)]
+pub(crate) struct SummaryParser {
+    pending: Option<TestSummary>,
+    emitted: bool,
+}
+
+impl SummaryParser {
+    pub fn observe(&mut self, line: &str) -> Option<TestSummary> {
+        if self.emitted {
+            return None;
+        }
+
+        if let Some(counts) = parse_counts(line) {
+            self.pending = Some(counts);
+            return None;
+        }
+
+        let duration_ms = parse_duration(line)?;
+        let mut summary = self.pending.take()?;
+        summary.duration_ms = Some(duration_ms);
+        self.emitted = true;
+        Some(summary)
+    }
+
+    /// Flushes counts that never got a duration line.
+    pub fn finish(&mut self) -> Option<TestSummary> {
+        if self.emitted {
+            return None;
+        }
+        self.emitted = true;
+        self.pending.take()
+    }
+}
+
+/// Strips a leading label such as `Tests:` or `Duration` (matched
+/// case-sensitively, as the runners print them) and returns the rest.
+fn strip_label<'a>(line: &'a str, label: &str) -> Option<&'a str> {
+    let rest = line.trim().strip_prefix(label)?;
+    let rest = rest.strip_prefix(':').unwrap_or(rest);
+    if rest.starts_with(char::is_whitespace) {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+/// Parses `Tests:  3 failed, 1 skipped, 42 passed, 46 total` (Jest) and
+/// `Tests  3 failed | 42 passed (45)` (Vitest).
+fn parse_counts(line: &str) -> Option<TestSummary> {
+    let rest = strip_label(line, "Tests")?;
+    let rest = match rest.rfind('(') {
+        Some(index) if rest.ends_with(')') => rest[..index].trim_end(),
+        _ => rest,
+    };
+
+    let mut summary = TestSummary::default();
+    let mut recognized = false;
+    for part in rest.split([',', '|']) {
+        let mut words = part.split_whitespace();
+        let (Some(count), Some(label)) = (words.next(), words.next()) else {
+            continue;
+        };
+        let Ok(count) = count.parse::<u32>() else {
+            continue;
+        };
+        let slot = match label {
+            "passed" => &mut summary.passed,
+            "failed" => &mut summary.failed,
+            "skipped" | "todo" | "pending" => &mut summary.skipped,
+            _ => continue,
+        };
+        *slot += count;
+        recognized = true;
+    }
+
+    recognized.then_some(summary)
+}
+
+/// Parses `Duration  1.23s (transform 40ms, ...)` (Vitest) and
+/// `Time:        2.345 s, estimated 3 s` (Jest) into milliseconds.
+fn parse_duration(line: &str) -> Option<u64> {
+    let rest = strip_label(line, "Duration").or_else(|| strip_label(line, "Time"))?;
+    let end = rest
+        .find(|ch: char| !ch.is_ascii_digit() && ch != '.')
+        .unwrap_or(rest.len());
+    let value: f64 = rest[..end].parse().ok()?;
+    let unit = rest[end..].trim_start();
+
+    let millis = if unit.starts_with("ms") {
+        value
+    } else if unit.starts_with('s') {
+        value * 1000.0
+    } else if unit.starts_with('m') {
+        value * 60_000.0
+    } else {
+        return None;
+    };
+    Some(millis.round() as u64)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TestStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// One test case from the runner's JSON report.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct TestReportEntry {
+    pub name: String,
+    pub status: TestStatus,
+    pub duration_ms: Option<u64>,
+    pub message: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonReport {
+    test_results: Vec<JsonFileResult>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonFileResult {
+    #[serde(default)]
+    assertion_results: Vec<JsonAssertion>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonAssertion {
+    #[serde(default)]
+    full_name: String,
+    #[serde(default)]
+    title: String,
+    status: String,
+    duration: Option<f64>,
+    #[serde(default)]
+    failure_messages: Vec<String>,
+}
+
+/// Parses the Jest-compatible JSON that both Vitest's `json` reporter and
+/// Jest's `--json` flag write.
+pub(crate) fn parse_json_report(contents: &str) -> Result<Vec<TestReportEntry>, String> {
+    let report: JsonReport = serde_json::from_str(contents).map_err(|err| err.to_string())?;
+    let entries = report
+        .test_results
+        .into_iter()
+        .flat_map(|file| file.assertion_results)
+        .map(|assertion| TestReportEntry {
+            name: if assertion.full_name.is_empty() {
+                assertion.title
+            } else {
+                assertion.full_name
+            },
+            status: match assertion.status.as_str() {
+                "passed" => TestStatus::Passed,
+                "failed" => TestStatus::Failed,
+                _ => TestStatus::Skipped,
+            },
+            duration_ms: assertion
+                .duration
+                .filter(|ms| ms.is_finite() && *ms >= 0.0)
+                .map(|ms| ms.round() as u64),
+            message: (!assertion.failure_messages.is_empty())
+                .then(|| assertion.failure_messages.join("\n")),
+        })
+        .collect();
+    Ok(entries)
+}
+
+/// Iteration boundary found in watch-mode output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WatchEvent {
+    Started { iteration: u32 },
+    Finished { iteration: u32, code: Option<i32> },
+}
+
+/// Splits a watch-mode run into iterations at Vitest's markers: `RERUN`
+/// starts one, "Waiting/Watching for file changes" ends it. The first
+/// iteration starts with the process.
+#[derive(Debug)]
+pub(crate) struct WatchIterations {
+    current: u32,
+    open: bool,
+}
+
+impl Default for WatchIterations {
+    fn default() -> Self {
+        Self {
+            current: 1,
+            open: true,
+        }
+    }
+}
+
+impl WatchIterations {
+    pub fn current(&self) -> u32 {
+        self.current
+    }
+
+    /// Boundaries `line` marks. A re-run that interrupts an unfinished
+    /// iteration closes it first, without an exit code.
+    pub fn observe(&mut self, line: &str) -> Vec<WatchEvent> {
+        let trimmed = line.trim_start();
+        let mut events = Vec::new();
+        if trimmed.starts_with("RERUN") || trimmed.starts_with("Re-running tests") {
+            if self.open {
+                events.push(WatchEvent::Finished {
+                    iteration: self.current,
+                    code: None,
+                });
+            }
+            self.current += 1;
+            self.open = true;
+            events.push(WatchEvent::Started {
+                iteration: self.current,
+            });
+        } else if self.open
+            && (trimmed.contains("Waiting for file changes")
+                || trimmed.contains("Watching for file changes"))
+        {
+            let failed = trimmed.starts_with("FAIL") || trimmed.contains("failed");
+            self.open = false;
+            events.push(WatchEvent::Finished {
+                iteration: self.current,
+                code: Some(i32::from(failed)),
+            });
+        }
+        events
+    }
+}
+
+/// Temp file the JSON reporter writes into. Removed on drop, so the file is
+/// cleaned up whether the run finishes, fails, times out or is cancelled.
+pub(crate) struct ReportFile {
+    path: PathBuf,
+}
+
+impl ReportFile {
+    pub fn new() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or_default();
+        let name = format!("jumpchain-test-report-{}-{nanos}.json", std::process::id());
+        Self {
+            path: std::env::temp_dir().join(name),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn read(&self) -> Result<Vec<TestReportEntry>, String> {
+        let contents = std::fs::read_to_string(&self.path).map_err(|err| err.to_string())?;
+        parse_json_report(&contents)
+    }
+}
+
+impl Drop for ReportFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Where Istanbul's `json-summary` reporter writes, relative to the
+/// workspace, unless the run names another file.
+pub(crate) const DEFAULT_COVERAGE_SUMMARY: &str = "coverage/coverage-summary.json";
+
+/// Overall coverage percentages from a coverage summary. A metric with
+/// nothing to measure is reported by Istanbul as "Unknown" and comes
+/// through as `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub(crate) struct CoverageSummary {
+    pub lines_pct: Option<f64>,
+    pub branches_pct: Option<f64>,
+    pub funcs_pct: Option<f64>,
+    pub statements_pct: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct JsonCoverage {
+    total: JsonCoverageTotals,
+}
+
+#[derive(Deserialize)]
+struct JsonCoverageTotals {
+    lines: JsonCoverageMetric,
+    branches: JsonCoverageMetric,
+    functions: JsonCoverageMetric,
+    statements: JsonCoverageMetric,
+}
+
+#[derive(Deserialize)]
+struct JsonCoverageMetric {
+    pct: serde_json::Value,
+}
+
+impl JsonCoverageMetric {
+    fn pct(&self) -> Option<f64> {
+        self.pct.as_f64().filter(|pct| pct.is_finite())
+    }
+}
+
+/// Parses the `total` block of the `coverage-summary.json` that Istanbul's
+/// `json-summary` reporter writes for both Vitest and Jest.
+pub(crate) fn parse_coverage_summary(contents: &str) -> Result<CoverageSummary, String> {
+    let summary: JsonCoverage = serde_json::from_str(contents).map_err(|err| err.to_string())?;
+    let total = summary.total;
+    Ok(CoverageSummary {
+        lines_pct: total.lines.pct(),
+        branches_pct: total.branches.pct(),
+        funcs_pct: total.functions.pct(),
+        statements_pct: total.statements.pct(),
+    })
+}
+
+/// Coverage summary a run is expected to write. Unlike [`ReportFile`] it
+/// lives in the workspace and is left in place, so a summary older than
+/// the run is a previous run's and is ignored.
+pub(crate) struct CoverageFile {
+    path: PathBuf,
+    since: SystemTime,
+}
+
+impl CoverageFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            since: SystemTime::now(),
+        }
+    }
+
+    pub fn read(&self) -> Result<CoverageSummary, String> {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|err| err.to_string())?;
+        if modified < self.since {
+            return Err(format!("{} predates the run", self.path.display()));
+        }
+        let contents = std::fs::read_to_string(&self.path).map_err(|err| err.to_string())?;
+        parse_coverage_summary(&contents)
+    }
+}
+
+/// A run's output, saved to `test-run-<millis>.log` in the app log dir so
+/// it survives a reload. Write failures are ignored; the event stream is
+/// the primary output.
+pub(crate) struct RunLog {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl RunLog {
+    /// Starts a new log in `dir` and deletes all but the newest
+    /// `KEPT_RUN_LOGS` there, this one included.
+    pub fn create(dir: &Path) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or_default();
+        // Zero-padded so the names sort in the order the runs started.
+        let path = dir.join(format!("{RUN_LOG_PREFIX}{millis:015}.log"));
+        let writer = BufWriter::new(File::create(&path)?);
+        prune_run_logs(dir, KEPT_RUN_LOGS);
+        Ok(Self { path, writer })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write_line(&mut self, line: &str) {
+        let _ = writeln!(self.writer, "{line}");
+    }
+
+    /// Closes the log with the line [`run_log_exit_code`] reads back.
+    pub fn write_exit(&mut self, code: Option<i32>) {
+        let code = code.map_or_else(|| "unknown".to_string(), |code| code.to_string());
+        self.write_line(&format!("{RUN_LOG_EXIT}{code}"));
+    }
+
+    pub fn flush(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+/// Saved run logs in `dir`, oldest first.
+pub(crate) fn run_logs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut logs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_run_log(path))
+        .collect();
+    logs.sort();
+    logs
+}
+
+/// Whether `path` is named like a saved run log.
+pub(crate) fn is_run_log(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with(RUN_LOG_PREFIX) && name.ends_with(".log"))
+}
+
+/// When the run behind a saved log started, from its file name.
+pub(crate) fn run_log_started(path: &Path) -> Option<SystemTime> {
+    let millis = path
+        .file_stem()?
+        .to_str()?
+        .strip_prefix(RUN_LOG_PREFIX)?
+        .parse()
+        .ok()?;
+    Some(UNIX_EPOCH + Duration::from_millis(millis))
+}
+
+/// Exit code from a saved log's closing line. `None` when the run has not
+/// finished, was killed, or ended without a code.
+pub(crate) fn run_log_exit_code(path: &Path) -> Option<i32> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(RUN_LOG_TAIL_BYTES)))
+        .ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+    String::from_utf8_lossy(&tail)
+        .lines()
+        .last()?
+        .strip_prefix(RUN_LOG_EXIT)?
+        .parse()
+        .ok()
+}
+
+fn prune_run_logs(dir: &Path, keep: usize) {
+    let logs = run_logs(dir);
+    let stale = logs.len().saturating_sub(keep);
+    for path in &logs[..stale] {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summarize(lines: &[&str]) -> Option<TestSummary> {
+        let mut parser = SummaryParser::default();
+        lines
+            .iter()
+            .find_map(|line| parser.observe(line))
+            .or_else(|| parser.finish())
+    }
+
+    #[test]
+    fn parses_vitest_summary() {
+        let summary = summarize(&[
+            " ✓ src/db/dao.spec.ts (12 tests) 40ms",
+            " Test Files  1 failed | 5 passed (6)",
+            "      Tests  3 failed | 42 passed | 1 skipped (46)",
+            "   Start at  10:42:01",
+            "   Duration  1.23s (transform 40ms, setup 0ms, collect 300ms)",
+        ]);
+        assert_eq!(
+            summary,
+            Some(TestSummary {
+                passed: 42,
+                failed: 3,
+                skipped: 1,
+                duration_ms: Some(1230),
+            })
+        );
+    }
+
+    #[test]
+    fn parses_jest_summary() {
+        let summary = summarize(&[
+            "Test Suites: 1 failed, 4 passed, 5 total",
+            "Tests:       2 failed, 1 todo, 40 passed, 43 total",
+            "Snapshots:   0 total",
+            "Time:        2.345 s, estimated 3 s",
+        ]);
+        assert_eq!(
+            summary,
+            Some(TestSummary {
+                passed: 40,
+                failed: 2,
+                skipped: 1,
+                duration_ms: Some(2345),
+            })
+        );
+    }
+
+    #[test]
+    fn flushes_counts_without_duration() {
+        let summary = summarize(&["      Tests  12 passed (12)"]);
+        assert_eq!(
+            summary,
+            Some(TestSummary {
+                passed: 12,
+                ..TestSummary::default()
+            })
+        );
+    }
+
+    #[test]
+    fn ignores_lines_that_only_mention_tests() {
+        assert_eq!(parse_counts("Tests are running in watch mode"), None);
+        assert_eq!(parse_counts("Test Files  2 passed (2)"), None);
+        assert_eq!(summarize(&["Duration  1.2s"]), None);
+    }
+
+    #[test]
+    fn parses_json_report() {
+        let entries = parse_json_report(
+            r#"{
+                "numTotalTests": 3,
+                "testResults": [
+                    {
+                        "name": "/repo/src/db/dao.spec.ts",
+                        "assertionResults": [
+                            {
+                                "fullName": "dao inserts a jump",
+                                "title": "inserts a jump",
+                                "status": "passed",
+                                "duration": 4.6,
+                                "failureMessages": []
+                            },
+                            {
+                                "fullName": "dao rejects empty titles",
+                                "title": "rejects empty titles",
+                                "status": "failed",
+                                "duration": 12,
+                                "failureMessages": ["expected 1 to be 2", "at dao.spec.ts:40"]
+                            },
+                            {
+                                "title": "exports to pdf",
+                                "status": "todo",
+                                "duration": null,
+                                "failureMessages": []
+                            }
+                        ]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            entries,
+            vec![
+                TestReportEntry {
+                    name: "dao inserts a jump".into(),
+                    status: TestStatus::Passed,
+                    duration_ms: Some(5),
+                    message: None,
+                },
+                TestReportEntry {
+                    name: "dao rejects empty titles".into(),
+                    status: TestStatus::Failed,
+                    duration_ms: Some(12),
+                    message: Some("expected 1 to be 2\nat dao.spec.ts:40".into()),
+                },
+                TestReportEntry {
+                    name: "exports to pdf".into(),
+                    status: TestStatus::Skipped,
+                    duration_ms: None,
+                    message: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_json_report() {
+        assert!(parse_json_report("").is_err());
+        assert!(parse_json_report("{\"numTotalTests\": 0}").is_err());
+    }
+
+    #[test]
+    fn run_logs_keep_only_the_newest() {
+        let dir = std::env::temp_dir().join(format!("jumpchain-run-logs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for millis in 1..=KEPT_RUN_LOGS + 2 {
+            let name = format!("{RUN_LOG_PREFIX}{millis:015}.log");
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+        std::fs::write(dir.join("app.log"), "").unwrap();
+
+        let mut log = RunLog::create(&dir).unwrap();
+        log.write_line("STDOUT INFO: ✓ src/db/dao.spec.ts");
+        log.flush();
+
+        let logs = run_logs(&dir);
+        assert_eq!(logs.len(), KEPT_RUN_LOGS);
+        assert_eq!(logs.last().map(PathBuf::as_path), Some(log.path()));
+        assert!(!dir.join(format!("{RUN_LOG_PREFIX}{:015}.log", 3)).exists());
+        assert!(dir.join("app.log").exists());
+        assert_eq!(
+            std::fs::read_to_string(log.path()).unwrap(),
+            "STDOUT INFO: ✓ src/db/dao.spec.ts\n"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn run_log_details_come_from_name_and_exit_line() {
+        let dir = std::env::temp_dir().join(format!("jumpchain-run-log-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut log = RunLog::create(&dir).unwrap();
+        log.write_line("STDOUT INFO: Tests  3 passed (3)");
+        log.flush();
+        assert_eq!(run_log_exit_code(log.path()), None);
+        log.write_exit(Some(1));
+        log.flush();
+        assert_eq!(run_log_exit_code(log.path()), Some(1));
+
+        let named = Path::new("/logs/test-run-001700000000000.log");
+        assert!(is_run_log(named));
+        assert_eq!(
+            run_log_started(named),
+            Some(UNIX_EPOCH + Duration::from_millis(1_700_000_000_000))
+        );
+        assert!(!is_run_log(Path::new("/logs/app.log")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn watch_markers_split_iterations() {
+        let mut watch = WatchIterations::default();
+        assert!(watch
+            .observe(" ✓ src/jumps/parser.test.ts (4 tests)")
+            .is_empty());
+        assert_eq!(
+            watch.observe(" PASS  Waiting for file changes..."),
+            [WatchEvent::Finished {
+                iteration: 1,
+                code: Some(0)
+            }]
+        );
+        assert!(watch
+            .observe(" PASS  Waiting for file changes...")
+            .is_empty());
+        assert_eq!(
+            watch.observe(" RERUN  src/jumps/parser.ts x1"),
+            [WatchEvent::Started { iteration: 2 }]
+        );
+        assert_eq!(
+            watch.observe(" RERUN  src/jumps/parser.ts x2"),
+            [
+                WatchEvent::Finished {
+                    iteration: 2,
+                    code: None
+                },
+                WatchEvent::Started { iteration: 3 }
+            ]
+        );
+        assert_eq!(
+            watch.observe(" FAIL  Tests failed. Watching for file changes..."),
+            [WatchEvent::Finished {
+                iteration: 3,
+                code: Some(1)
+            }]
+        );
+        assert_eq!(watch.current(), 3);
+    }
+
+    #[test]
+    fn coverage_summary_reads_totals_and_skips_stale_files() {
+        let contents = r#"{
+            "total": {
+                "lines": { "total": 200, "covered": 150, "skipped": 0, "pct": 75 },
+                "statements": { "total": 210, "covered": 160, "skipped": 0, "pct": 76.19 },
+                "functions": { "total": 40, "covered": 30, "skipped": 0, "pct": 75 },
+                "branches": { "total": 0, "covered": 0, "skipped": 0, "pct": "Unknown" }
+            },
+            "/repo/src/main.ts": {}
+        }"#;
+        assert_eq!(
+            parse_coverage_summary(contents),
+            Ok(CoverageSummary {
+                lines_pct: Some(75.0),
+                branches_pct: None,
+                funcs_pct: Some(75.0),
+                statements_pct: Some(76.19),
+            })
+        );
+        assert!(parse_coverage_summary("{}").is_err());
+
+        let path = std::env::temp_dir().join(format!(
+            "jumpchain-coverage-summary-{}.json",
+            std::process::id()
+        ));
+        std::fs::write(&path, contents).unwrap();
+        let stale = CoverageFile {
+            path: path.clone(),
+            since: SystemTime::now() + Duration::from_secs(60),
+        };
+        assert!(stale.read().is_err());
+        let fresh = CoverageFile {
+            path: path.clone(),
+            since: SystemTime::now() - Duration::from_secs(60),
+        };
+        assert_eq!(fresh.read().unwrap().lines_pct, Some(75.0));
+        std::fs::remove_file(&path).unwrap();
+        assert!(fresh.read().is_err());
+    }
+
+    #[test]
+    fn report_file_is_removed_on_drop() {
+        let report = ReportFile::new();
+        let path = report.path().to_path_buf();
+        std::fs::write(&path, "{}").unwrap();
+        drop(report);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,228 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use pdfium_render::prelude::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Manager};
+
+use crate::db;
+use crate::error::{AppError, AppResult};
+
+const THUMBNAIL_DIR: &str = "pdf-thumbnails";
+const THUMBNAIL_WIDTH: i32 = 200;
+const SOURCE_STAMP: &str = "source.mtime";
+
+/// Pages rendered straight after indexing so the thumbnail strip has
+/// something to show before the viewer asks for the rest.
+pub(crate) const PREFETCH_PAGES: u32 = 8;
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct PdfThumbnail {
+    page: u32,
+    path: String,
+}
+
+/// File ids become directory names, so anything that could escape the cache
+/// directory is refused.
+fn valid_file_id(file_id: &str) -> bool {
+    !file_id.is_empty()
+        && file_id
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_'))
+}
+
+/// Directory holding one thumbnail directory per file id.
+pub(crate) fn cache_root(app: &AppHandle) -> AppResult<PathBuf> {
+    Ok(app.path().app_cache_dir()?.join(THUMBNAIL_DIR))
+}
+
+fn thumbnail_dir(app: &AppHandle, file_id: &str) -> AppResult<PathBuf> {
+    if !valid_file_id(file_id) {
+        return Err(AppError::Validation(format!("Invalid file id: {file_id}")));
+    }
+    Ok(cache_root(app)?.join(file_id))
+}
+
+fn source_stamp(source: &Path) -> AppResult<String> {
+    let modified = std::fs::metadata(source).and_then(|meta| meta.modified())?;
+    let elapsed = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+    Ok(format!(
+        "{}.{:09}",
+        elapsed.as_secs(),
+        elapsed.subsec_nanos()
+    ))
+}
+
+/// Prefers a PDFium library bundled next to the app resources and falls back
+/// to one installed on the system.
+pub(crate) fn bind_pdfium(resource_dir: Option<PathBuf>) -> AppResult<Pdfium> {
+    let bundled = resource_dir.and_then(|dir| {
+        Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(&dir)).ok()
+    });
+    let bindings = match bundled {
+        Some(bindings) => bindings,
+        None => Pdfium::bind_to_system_library()
+            .map_err(|err| AppError::Internal(format!("PDF renderer unavailable: {err}")))?,
+    };
+    Ok(Pdfium::new(bindings))
+}
+
+/// Renders the first `max_pages` pages (every page when `None`) of `source`
+/// into `dir` as `page-N.png`. Existing images are reused while the source
+/// modification time matches the stamp written alongside them; a changed
+/// source clears the directory and starts over.
+fn render_thumbnails(
+    pdfium: &Pdfium,
+    source: &Path,
+    dir: &Path,
+    max_pages: Option<u32>,
+) -> AppResult<Vec<PdfThumbnail>> {
+    let stamp = source_stamp(source)?;
+    let stamp_path = dir.join(SOURCE_STAMP);
+    let fresh = std::fs::read_to_string(&stamp_path).ok().as_deref() == Some(stamp.as_str());
+    if !fresh {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+    std::fs::create_dir_all(dir)?;
+
+    let document = pdfium
+        .load_pdf_from_file(source, None)
+        .map_err(|err| AppError::Validation(format!("Unable to read PDF: {err}")))?;
+    let config = PdfRenderConfig::new().set_target_width(THUMBNAIL_WIDTH);
+    let limit = max_pages.map_or(usize::MAX, |max| max as usize);
+
+    let mut thumbnails = Vec::new();
+    for (index, page) in document.pages().iter().enumerate().take(limit) {
+        let number = index as u32 + 1;
+        let path = dir.join(format!("page-{number}.png"));
+        if !path.is_file() {
+            page.render_with_config(&config)
+                .map_err(|err| {
+                    AppError::Internal(format!("Unable to render page {number}: {err}"))
+                })?
+                .as_image()
+                .save_with_format(&path, image::ImageFormat::Png)
+                .map_err(|err| AppError::Io(err.to_string()))?;
+        }
+        thumbnails.push(PdfThumbnail {
+            page: number,
+            path: path.to_string_lossy().into_owned(),
+        });
+    }
+
+    if !fresh {
+        std::fs::write(&stamp_path, stamp)?;
+    }
+    Ok(thumbnails)
+}
+
+/// Renders (or reuses) thumbnails for `file_id` off the async runtime.
+pub(crate) async fn generate(
+    app: &AppHandle,
+    file_id: &str,
+    source: &Path,
+    max_pages: Option<u32>,
+) -> AppResult<Vec<PdfThumbnail>> {
+    let dir = thumbnail_dir(app, file_id)?;
+    let resource_dir = app.path().resource_dir().ok();
+    let source = source.to_path_buf();
+    tauri::async_runtime::spawn_blocking(move || {
+        let pdfium = bind_pdfium(resource_dir)?;
+        render_thumbnails(&pdfium, &source, &dir, max_pages)
+    })
+    .await?
+}
+
+/// Deletes a thumbnail directory and returns the bytes its images took up;
+/// a directory that does not exist frees nothing.
+fn remove_dir(dir: &Path) -> AppResult<u64> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err.into()),
+    };
+    let bytes = entries
+        .filter_map(|entry| entry.ok()?.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum();
+    std::fs::remove_dir_all(dir)?;
+    Ok(bytes)
+}
+
+/// Deletes the cached thumbnails for `file_id`, returning the bytes freed.
+/// Ids that could never have had a cache directory free nothing.
+pub(crate) fn remove(app: &AppHandle, file_id: &str) -> AppResult<u64> {
+    if !valid_file_id(file_id) {
+        return Ok(0);
+    }
+    remove_dir(&thumbnail_dir(app, file_id)?)
+}
+
+/// Returns page thumbnails for an indexed PDF, rendering any that are
+/// missing. `max_pages` caps the work so the first pages can be shown while
+/// the rest are requested later.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn get_pdf_thumbnails(
+    app: AppHandle,
+    file_id: String,
+    max_pages: Option<u32>,
+) -> AppResult<Vec<PdfThumbnail>> {
+    let pool = db::pool(&app).await?;
+    let source: Option<String> = sqlx::query_scalar("SELECT path FROM files WHERE id = ?")
+        .bind(&file_id)
+        .fetch_optional(&pool)
+        .await?;
+    let source =
+        source.ok_or_else(|| AppError::NotFound(format!("{file_id} has not been indexed")))?;
+    generate(&app, &file_id, Path::new(&source), max_pages).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_ids_cannot_escape_the_cache_dir() {
+        assert!(valid_file_id("3f2a9c1e-7b4d-4e8a-9c2f-1a2b3c4d5e6f"));
+        assert!(valid_file_id("jump_doc_12"));
+        assert!(!valid_file_id(""));
+        assert!(!valid_file_id(".."));
+        assert!(!valid_file_id("../../etc"));
+        assert!(!valid_file_id("a/b"));
+    }
+
+    #[test]
+    fn remove_dir_reports_the_bytes_freed() {
+        let dir = std::env::temp_dir().join(format!("jumpchain-thumbs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("page-1.png"), [0u8; 300]).unwrap();
+        std::fs::write(dir.join(SOURCE_STAMP), b"12").unwrap();
+
+        assert_eq!(remove_dir(&dir).unwrap(), 302);
+        assert!(!dir.exists());
+        assert_eq!(remove_dir(&dir).unwrap(), 0);
+    }
+}
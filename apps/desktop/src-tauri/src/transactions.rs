@@ -0,0 +1,430 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use serde_json::Value;
+use sqlx::sqlite::SqlitePool;
+use sqlx::{Sqlite, Transaction};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use tokio::sync::{Mutex, MutexGuard};
+use tokio::time::Instant;
+
+use crate::db::{self, QueryResult};
+use crate::error::{AppError, AppResult};
+
+/// A transaction left untouched this long is rolled back so a forgotten
+/// `tx_id` cannot hold the database write lock indefinitely.
+const TX_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+const TX_IDLE_CHECK: Duration = Duration::from_secs(5);
+/// How many finished transactions are remembered, so a stale `tx_id` gets
+/// a useful error instead of "unknown".
+const CLOSED_HISTORY: usize = 256;
+
+#[derive(Debug, Clone, Copy)]
+enum TxOutcome {
+    Committed,
+    RolledBack,
+    TimedOut,
+}
+
+impl TxOutcome {
+    fn describe(self) -> &'static str {
+        match self {
+            TxOutcome::Committed => "committed",
+            TxOutcome::RolledBack => "rolled back",
+            TxOutcome::TimedOut => "rolled back after sitting idle",
+        }
+    }
+}
+
+struct OpenTransaction {
+    tx: Transaction<'static, Sqlite>,
+    last_used: Instant,
+}
+
+/// One transaction behind its own lock, so a slow query only holds up
+/// calls for that `tx_id`. Emptied when the transaction is finished.
+type TxSlot = Arc<Mutex<Option<OpenTransaction>>>;
+
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    open: HashMap<String, TxSlot>,
+    closed: VecDeque<(String, TxOutcome)>,
+}
+
+impl Registry {
+    /// Why `tx_id` can no longer be used, for ids that are not open.
+    fn unavailable(&self, tx_id: &str) -> AppError {
+        match self.closed.iter().find(|(id, _)| id == tx_id) {
+            Some((_, outcome)) => AppError::Validation(format!(
+                "Transaction {tx_id} has already been {}",
+                outcome.describe()
+            )),
+            None => AppError::NotFound(format!("Unknown transaction {tx_id}")),
+        }
+    }
+
+    fn slot(&self, tx_id: &str) -> AppResult<TxSlot> {
+        self.open
+            .get(tx_id)
+            .cloned()
+            .ok_or_else(|| self.unavailable(tx_id))
+    }
+
+    fn close(&mut self, tx_id: &str, outcome: TxOutcome) {
+        self.open.remove(tx_id);
+        if self.closed.len() == CLOSED_HISTORY {
+            self.closed.pop_front();
+        }
+        self.closed.push_back((tx_id.to_string(), outcome));
+    }
+}
+
+/// Proof that no `db_begin` transaction is open. `db_begin` waits while
+/// this is held, so none can start either.
+pub(crate) struct NoOpenTransactions<'a> {
+    _registry: MutexGuard<'a, Registry>,
+}
+
+/// Transactions opened by `db_begin`, keyed by the `tx_id` handed back to
+/// the frontend. The registry lock is never held across a query; a slot's
+/// lock may be, and is always taken before the registry's.
+#[derive(Default)]
+pub(crate) struct TransactionState {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl TransactionState {
+    async fn begin(&self, pool: &SqlitePool) -> AppResult<String> {
+        let tx = pool.begin().await?;
+        let mut registry = self.registry.lock().await;
+        registry.next_id += 1;
+        let tx_id = format!("tx-{}", registry.next_id);
+        registry.open.insert(
+            tx_id.clone(),
+            Arc::new(Mutex::new(Some(OpenTransaction {
+                tx,
+                last_used: Instant::now(),
+            }))),
+        );
+        Ok(tx_id)
+    }
+
+    /// Fails if any transaction is open; otherwise keeps new ones from
+    /// starting until the returned guard is dropped.
+    pub(crate) async fn exclusive(&self) -> AppResult<NoOpenTransactions<'_>> {
+        let registry = self.registry.lock().await;
+        if !registry.open.is_empty() {
+            return Err(AppError::Validation(format!(
+                "{} transaction(s) still open; commit or roll them back first",
+                registry.open.len()
+            )));
+        }
+        Ok(NoOpenTransactions {
+            _registry: registry,
+        })
+    }
+
+    /// Runs `query` on the connection behind `tx_id`, which also counts as
+    /// activity for the idle timeout.
+    pub(crate) async fn run(
+        &self,
+        tx_id: &str,
+        query: &str,
+        values: &[Value],
+    ) -> AppResult<QueryResult> {
+        let slot = self.registry.lock().await.slot(tx_id)?;
+        let mut slot = slot.lock().await;
+        let Some(open) = slot.as_mut() else {
+            return Err(self.registry.lock().await.unavailable(tx_id));
+        };
+        open.last_used = Instant::now();
+        Ok(db::run_query_on(&mut *open.tx, query, values).await?)
+    }
+
+    /// Finishes `tx_id` once any query on it is done. The slot stays locked
+    /// until the outcome is recorded, so a call waiting on it learns why the
+    /// id is gone.
+    async fn commit(&self, tx_id: &str) -> AppResult<()> {
+        let slot = self.registry.lock().await.slot(tx_id)?;
+        let mut slot = slot.lock().await;
+        let Some(open) = slot.take() else {
+            return Err(self.registry.lock().await.unavailable(tx_id));
+        };
+        let committed = open.tx.commit().await;
+        let outcome = match committed {
+            Ok(()) => TxOutcome::Committed,
+            Err(_) => TxOutcome::RolledBack,
+        };
+        self.registry.lock().await.close(tx_id, outcome);
+        Ok(committed?)
+    }
+
+    async fn rollback(&self, tx_id: &str) -> AppResult<()> {
+        let slot = self.registry.lock().await.slot(tx_id)?;
+        let mut slot = slot.lock().await;
+        let Some(open) = slot.take() else {
+            return Err(self.registry.lock().await.unavailable(tx_id));
+        };
+        self.registry
+            .lock()
+            .await
+            .close(tx_id, TxOutcome::RolledBack);
+        Ok(open.tx.rollback().await?)
+    }
+
+    /// Rolls back every open transaction, as at shutdown, waiting for any
+    /// query still running on one. Returns how many there were.
+    pub(crate) async fn rollback_all(&self) -> usize {
+        let slots: Vec<(String, TxSlot)> = self
+            .registry
+            .lock()
+            .await
+            .open
+            .iter()
+            .map(|(tx_id, slot)| (tx_id.clone(), Arc::clone(slot)))
+            .collect();
+        let mut count = 0;
+        for (tx_id, slot) in slots {
+            let mut slot = slot.lock().await;
+            if let Some(open) = slot.take() {
+                self.registry
+                    .lock()
+                    .await
+                    .close(&tx_id, TxOutcome::RolledBack);
+                let _ = open.tx.rollback().await;
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Rolls `tx_id` back if it has been idle for at least `idle`. A query
+    /// still running counts as activity. Returns whether the transaction is
+    /// still open afterwards.
+    async fn rollback_if_idle(&self, tx_id: &str, idle: Duration) -> bool {
+        let Ok(slot) = self.registry.lock().await.slot(tx_id) else {
+            return false;
+        };
+        let Ok(mut slot) = slot.try_lock() else {
+            return true;
+        };
+        match slot.as_ref() {
+            None => return false,
+            Some(open) if open.last_used.elapsed() < idle => return true,
+            Some(_) => {}
+        }
+        if let Some(open) = slot.take() {
+            self.registry.lock().await.close(tx_id, TxOutcome::TimedOut);
+            let _ = open.tx.rollback().await;
+        }
+        false
+    }
+
+    fn watch_idle(&self, tx_id: String) {
+        let state = TransactionState {
+            registry: Arc::clone(&self.registry),
+        };
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::time::sleep(TX_IDLE_CHECK).await;
+                if !state.rollback_if_idle(&tx_id, TX_IDLE_TIMEOUT).await {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+/// Opens a transaction that later `db_query` calls join by passing the
+/// returned `tx_id`. It stays open until `db_commit`, `db_rollback` or the
+/// idle timeout.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn db_begin(
+    app: AppHandle,
+    state: State<'_, TransactionState>,
+) -> AppResult<String> {
+    let pool = db::pool(&app).await?;
+    let tx_id = state.begin(&pool).await?;
+    state.watch_idle(tx_id.clone());
+    Ok(tx_id)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn db_commit(state: State<'_, TransactionState>, tx_id: String) -> AppResult<()> {
+    state.commit(&tx_id).await
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn db_rollback(
+    state: State<'_, TransactionState>,
+    tx_id: String,
+) -> AppResult<()> {
+    state.rollback(&tx_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    async fn jumps_pool() -> SqlitePool {
+        let pool = db::memory_pool().await;
+        db::run_query(&pool, "CREATE TABLE jumps (title TEXT)", &[])
+            .await
+            .unwrap();
+        pool
+    }
+
+    async fn count(pool: &SqlitePool) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM jumps")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn commit_and_rollback_span_calls() {
+        let pool = jumps_pool().await;
+        let state = TransactionState::default();
+        let insert = "INSERT INTO jumps (title) VALUES (?)";
+
+        let kept = state.begin(&pool).await.unwrap();
+        state.run(&kept, insert, &[json!("Naruto")]).await.unwrap();
+        state.run(&kept, insert, &[json!("Bleach")]).await.unwrap();
+        state.commit(&kept).await.unwrap();
+        assert_eq!(count(&pool).await, 2);
+
+        let dropped = state.begin(&pool).await.unwrap();
+        assert_ne!(dropped, kept);
+        state.run(&dropped, insert, &[json!("Worm")]).await.unwrap();
+        state.rollback(&dropped).await.unwrap();
+        assert_eq!(count(&pool).await, 2);
+
+        let reused = state
+            .run(&kept, insert, &[json!("Worm")])
+            .await
+            .unwrap_err();
+        assert_eq!(
+            reused.message(),
+            format!("Transaction {kept} has already been committed")
+        );
+        let unknown = state.commit("tx-missing").await.unwrap_err();
+        assert_eq!(unknown.code(), "not_found");
+        pool.close().await;
+    }
+
+    #[tokio::test]
+    async fn exclusive_requires_no_open_transactions() {
+        let pool = jumps_pool().await;
+        let state = TransactionState::default();
+        let tx_id = state.begin(&pool).await.unwrap();
+        let error = state.exclusive().await.err().unwrap();
+        assert_eq!(error.code(), "validation");
+
+        state.commit(&tx_id).await.unwrap();
+        assert!(state.exclusive().await.is_ok());
+        pool.close().await;
+    }
+
+    #[tokio::test]
+    async fn rollback_all_closes_every_open_transaction() {
+        let pool = jumps_pool().await;
+        let state = TransactionState::default();
+        assert_eq!(state.rollback_all().await, 0);
+
+        let tx_id = state.begin(&pool).await.unwrap();
+        state
+            .run(&tx_id, "INSERT INTO jumps (title) VALUES ('Naruto')", &[])
+            .await
+            .unwrap();
+        assert_eq!(state.rollback_all().await, 1);
+        assert_eq!(count(&pool).await, 0);
+        let error = state.commit(&tx_id).await.unwrap_err();
+        assert_eq!(
+            error.message(),
+            format!("Transaction {tx_id} has already been rolled back")
+        );
+        assert!(state.exclusive().await.is_ok());
+        pool.close().await;
+    }
+
+    #[tokio::test]
+    async fn idle_transactions_roll_back() {
+        let pool = jumps_pool().await;
+        let state = TransactionState::default();
+        let tx_id = state.begin(&pool).await.unwrap();
+        state
+            .run(&tx_id, "INSERT INTO jumps (title) VALUES ('Naruto')", &[])
+            .await
+            .unwrap();
+
+        assert!(state.rollback_if_idle(&tx_id, TX_IDLE_TIMEOUT).await);
+        assert!(!state.rollback_if_idle(&tx_id, Duration::ZERO).await);
+        assert_eq!(count(&pool).await, 0);
+        let error = state.rollback(&tx_id).await.unwrap_err();
+        assert!(error.message().ends_with("rolled back after sitting idle"));
+        pool.close().await;
+    }
+
+    #[tokio::test]
+    async fn a_busy_transaction_does_not_hold_the_registry() {
+        let pool = jumps_pool().await;
+        let state = TransactionState::default();
+        let tx_id = state.begin(&pool).await.unwrap();
+        // Stands in for a slow query running on the transaction.
+        let slot = state.registry.lock().await.slot(&tx_id).unwrap();
+        let busy = slot.lock().await;
+
+        let error = tokio::time::timeout(Duration::from_secs(1), state.exclusive())
+            .await
+            .expect("registry is free")
+            .err()
+            .unwrap();
+        assert_eq!(error.code(), "validation");
+        assert!(state.rollback_if_idle(&tx_id, Duration::ZERO).await);
+
+        drop(busy);
+        assert!(!state.rollback_if_idle(&tx_id, Duration::ZERO).await);
+        pool.close().await;
+    }
+
+    #[test]
+    fn closed_history_is_bounded() {
+        let mut registry = Registry::default();
+        for n in 0..=CLOSED_HISTORY {
+            registry.close(&format!("tx-{n}"), TxOutcome::Committed);
+        }
+        assert_eq!(registry.closed.len(), CLOSED_HISTORY);
+        assert_eq!(registry.unavailable("tx-0").code(), "not_found");
+        assert_eq!(
+            registry.unavailable(&format!("tx-{CLOSED_HISTORY}")).code(),
+            "validation"
+        );
+    }
+}
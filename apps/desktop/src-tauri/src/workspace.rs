@@ -0,0 +1,942 @@
+// Bloodawn
+//
+// Copyright (c) 2025 Bloodawn
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, Window};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::db;
+use crate::error::{AppError, AppResult};
+use crate::{migrations, thumbnails};
+
+const WORKSPACE_EXPORT_EVENT: &str = "workspace://export";
+/// Bumped whenever the archive layout changes, so an importer can tell
+/// which layout it is reading.
+const WORKSPACE_FORMAT_VERSION: u32 = 1;
+const MANIFEST_NAME: &str = "manifest.json";
+const DATABASE_ENTRY: &str = "database/jumpchain.sqlite";
+/// Where imported source PDFs are unpacked, under the app data directory.
+const LIBRARY_DIR: &str = "library";
+/// Holds what the last import replaced, under the app data directory, so
+/// it can be undone until the next import starts.
+const ROLLBACK_DIR: &str = "import-rollback";
+const JOURNAL_NAME: &str = "journal.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum EntryKind {
+    Database,
+    Thumbnail,
+    SourcePdf,
+}
+
+/// A file to archive under `name`. For source PDFs, `file_id` and
+/// `original_path` record which `files` row it belongs to and where it was
+/// read from, so the import can relink it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ArchiveEntry {
+    name: String,
+    kind: EntryKind,
+    bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    original_path: Option<String>,
+    #[serde(skip)]
+    source: PathBuf,
+}
+
+impl ArchiveEntry {
+    fn new(name: String, kind: EntryKind, source: PathBuf) -> Self {
+        let bytes = std::fs::metadata(&source).map_or(0, |meta| meta.len());
+        Self {
+            name,
+            kind,
+            bytes,
+            file_id: None,
+            original_path: None,
+            source,
+        }
+    }
+}
+
+/// Written first in the archive as `manifest.json`.
+#[derive(Debug, Serialize)]
+struct WorkspaceManifest {
+    format_version: u32,
+    app_version: String,
+    created_at_ms: u64,
+    /// Migration versions applied to the archived database.
+    schema_versions: Vec<i64>,
+    include_source_pdfs: bool,
+    entries: Vec<ArchiveEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum WorkspaceExportPayload {
+    Started {
+        total_files: usize,
+        total_bytes: u64,
+    },
+    File {
+        current: usize,
+        total: usize,
+        name: String,
+    },
+    Completed {
+        path: String,
+        bytes: u64,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct WorkspaceExport {
+    path: String,
+    bytes: u64,
+    files: usize,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Every file under `dir`, named `prefix/relative/path` with forward
+/// slashes and sorted by name. A missing directory yields nothing.
+fn dir_entries(dir: &Path, prefix: &str, kind: EntryKind) -> Vec<ArchiveEntry> {
+    let mut entries = Vec::new();
+    let Ok(listing) = std::fs::read_dir(dir) else {
+        return entries;
+    };
+    for entry in listing.flatten() {
+        let path = entry.path();
+        let name = format!("{prefix}/{}", entry.file_name().to_string_lossy());
+        if path.is_dir() {
+            entries.extend(dir_entries(&path, &name, kind));
+        } else if path.is_file() {
+            entries.push(ArchiveEntry::new(name, kind, path));
+        }
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries
+}
+
+/// Keeps a file id usable as a single archive path segment.
+fn archive_segment(text: &str) -> String {
+    text.chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_') {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Source PDFs that still exist on disk, one directory per file id.
+async fn source_pdf_entries(pool: &SqlitePool) -> AppResult<Vec<ArchiveEntry>> {
+    let rows: Vec<(String, String)> = sqlx::query_as(
+        "SELECT id, path FROM files WHERE kind = 'pdf' AND COALESCE(path, '') <> '' ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(file_id, path)| {
+            let source = PathBuf::from(&path);
+            let file_name = source.file_name()?.to_string_lossy().into_owned();
+            source.is_file().then(|| {
+                let name = format!("pdfs/{}/{file_name}", archive_segment(&file_id));
+                ArchiveEntry {
+                    file_id: Some(file_id),
+                    original_path: Some(path),
+                    ..ArchiveEntry::new(name, EntryKind::SourcePdf, source)
+                }
+            })
+        })
+        .collect())
+}
+
+/// Writes the zip itself; [`write_archive`] handles the partial file.
+fn write_zip(
+    path: &Path,
+    manifest: &[u8],
+    entries: &[ArchiveEntry],
+    on_entry: &mut impl FnMut(usize, &ArchiveEntry),
+) -> AppResult<()> {
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file(MANIFEST_NAME, deflated)?;
+    zip.write_all(manifest)?;
+
+    for (index, entry) in entries.iter().enumerate() {
+        on_entry(index + 1, entry);
+        let method = match entry.kind {
+            EntryKind::Database => CompressionMethod::Deflated,
+            EntryKind::Thumbnail | EntryKind::SourcePdf => CompressionMethod::Stored,
+        };
+        let options = SimpleFileOptions::default()
+            .compression_method(method)
+            .large_file(entry.bytes >= u64::from(u32::MAX));
+        zip.start_file(entry.name.as_str(), options)?;
+        std::io::copy(&mut File::open(&entry.source)?, &mut zip)?;
+    }
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+/// Zips `manifest` and `entries` into `destination` through a sibling
+/// `.partial` file, so a failed export never leaves a truncated archive in
+/// place. Thumbnails and PDFs are already compressed and are stored as-is.
+/// Returns the archive size.
+fn write_archive(
+    destination: &Path,
+    manifest: &[u8],
+    entries: &[ArchiveEntry],
+    mut on_entry: impl FnMut(usize, &ArchiveEntry),
+) -> AppResult<u64> {
+    let mut partial = destination.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    if let Err(err) = write_zip(&partial, manifest, entries, &mut on_entry) {
+        let _ = std::fs::remove_file(&partial);
+        return Err(err);
+    }
+
+    std::fs::rename(&partial, destination).map_err(|err| {
+        let _ = std::fs::remove_file(&partial);
+        AppError::from(err)
+    })?;
+    Ok(std::fs::metadata(destination)?.len())
+}
+
+/// Bundles a snapshot of the database, the cached thumbnails and, with
+/// `include_source_pdfs`, the indexed PDFs into one zip at `dest_path`,
+/// described by a `manifest.json`. Progress is reported on
+/// `workspace://export` as each file is added.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn export_workspace(
+    window: Window,
+    dest_path: String,
+    include_source_pdfs: Option<bool>,
+) -> AppResult<WorkspaceExport> {
+    let app = window.app_handle().clone();
+    let include_source_pdfs = include_source_pdfs.unwrap_or(false);
+    let pool = db::pool(&app).await?;
+
+    // Archive a VACUUM INTO snapshot rather than the live file, which may
+    // be mid-write and has its recent changes in the WAL.
+    let snapshot = std::env::temp_dir().join(format!(
+        "jumpchain-workspace-{}-{}.sqlite",
+        std::process::id(),
+        now_millis()
+    ));
+    db::backup_database(&pool, &db::db_path(&app)?, &snapshot).await?;
+
+    let mut entries = vec![ArchiveEntry::new(
+        DATABASE_ENTRY.into(),
+        EntryKind::Database,
+        snapshot.clone(),
+    )];
+    entries.extend(dir_entries(
+        &thumbnails::cache_root(&app)?,
+        "thumbnails",
+        EntryKind::Thumbnail,
+    ));
+    if include_source_pdfs {
+        entries.extend(source_pdf_entries(&pool).await?);
+    }
+    let schema_versions: Vec<i64> =
+        sqlx::query_scalar("SELECT version FROM _migrations ORDER BY version")
+            .fetch_all(&pool)
+            .await
+            .unwrap_or_default();
+
+    let manifest = serde_json::to_vec_pretty(&WorkspaceManifest {
+        format_version: WORKSPACE_FORMAT_VERSION,
+        app_version: app.package_info().version.to_string(),
+        created_at_ms: now_millis(),
+        schema_versions,
+        include_source_pdfs,
+        entries: entries.clone(),
+    })?;
+
+    let total = entries.len();
+    let _ = window.emit(
+        WORKSPACE_EXPORT_EVENT,
+        &WorkspaceExportPayload::Started {
+            total_files: total,
+            total_bytes: entries.iter().map(|entry| entry.bytes).sum(),
+        },
+    );
+    let progress_window = window.clone();
+    let destination = PathBuf::from(&dest_path);
+    let archived = tauri::async_runtime::spawn_blocking(move || {
+        write_archive(&destination, &manifest, &entries, |current, entry| {
+            let _ = progress_window.emit(
+                WORKSPACE_EXPORT_EVENT,
+                &WorkspaceExportPayload::File {
+                    current,
+                    total,
+                    name: entry.name.clone(),
+                },
+            );
+        })
+    })
+    .await;
+    let _ = std::fs::remove_file(&snapshot);
+    let bytes = archived??;
+
+    let _ = window.emit(
+        WORKSPACE_EXPORT_EVENT,
+        &WorkspaceExportPayload::Completed {
+            path: dest_path.clone(),
+            bytes,
+        },
+    );
+    Ok(WorkspaceExport {
+        path: dest_path,
+        bytes,
+        files: total,
+    })
+}
+
+/// The parts of `manifest.json` the import relies on.
+#[derive(Debug, Deserialize)]
+struct ImportManifest {
+    format_version: u32,
+    #[serde(default)]
+    schema_versions: Vec<i64>,
+    entries: Vec<ImportEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportEntry {
+    name: String,
+    kind: EntryKind,
+    #[serde(default)]
+    file_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct WorkspaceImport {
+    /// Snapshot of the database that was replaced, as `db_restore` returns.
+    safety_backup: String,
+    thumbnails: usize,
+    source_pdfs: usize,
+    /// `files` rows whose path now points at an unpacked PDF.
+    relinked: u64,
+}
+
+/// What an import changed outside the database: files it created, and
+/// files it overwrote along with where their previous contents were moved.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct ImportJournal {
+    safety_backup: Option<PathBuf>,
+    created: Vec<PathBuf>,
+    replaced: Vec<(PathBuf, PathBuf)>,
+}
+
+impl ImportJournal {
+    /// Records `target` before it is written, moving an existing file into
+    /// `stash_root` first. The journal in `stash_root` is saved before the
+    /// file is touched, so a crash part-way still leaves it complete.
+    fn claim(&mut self, target: &Path, stash_root: &Path) -> AppResult<()> {
+        if target.is_file() {
+            let stash = stash_root.join(self.replaced.len().to_string());
+            self.replaced.push((target.to_path_buf(), stash.clone()));
+            self.save(stash_root)?;
+            move_file(target, &stash)?;
+        } else {
+            self.created.push(target.to_path_buf());
+            self.save(stash_root)?;
+        }
+        Ok(())
+    }
+
+    fn load(root: &Path) -> AppResult<Option<Self>> {
+        match std::fs::read(root.join(JOURNAL_NAME)) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn save(&self, root: &Path) -> AppResult<()> {
+        std::fs::create_dir_all(root)?;
+        std::fs::write(root.join(JOURNAL_NAME), serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Removes the created files, and their directories once empty, and
+    /// moves replaced files back. Returns both counts.
+    fn undo_files(&self) -> AppResult<(usize, usize)> {
+        let mut removed = 0;
+        for path in self.created.iter().rev() {
+            match std::fs::remove_file(path) {
+                Ok(()) => removed += 1,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+            if let Some(parent) = path.parent() {
+                let _ = std::fs::remove_dir(parent);
+            }
+        }
+        let mut restored = 0;
+        for (target, stash) in self.replaced.iter().rev() {
+            if stash.is_file() {
+                move_file(stash, target)?;
+                restored += 1;
+            }
+        }
+        Ok((removed, restored))
+    }
+}
+
+/// Renames `from` to `to`, copying when they are on different volumes.
+fn move_file(from: &Path, to: &Path) -> AppResult<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::copy(from, to)?;
+    std::fs::remove_file(from)?;
+    Ok(())
+}
+
+/// What `rollback_last_import` put back.
+#[derive(Debug, Serialize)]
+pub(crate) struct ImportRollback {
+    /// The snapshot the database was restored from.
+    restored_database: Option<String>,
+    /// Snapshot of the database the rollback replaced, as `db_restore`
+    /// returns.
+    safety_backup: Option<String>,
+    removed_files: usize,
+    restored_files: usize,
+}
+
+/// Undoes the import recorded under `root` and forgets it. Undoing is
+/// idempotent, so a rollback that fails part-way can be run again.
+async fn roll_back(
+    app: &AppHandle,
+    root: &Path,
+    journal: ImportJournal,
+) -> AppResult<ImportRollback> {
+    let snapshot = journal.safety_backup.clone();
+    let (removed_files, restored_files) =
+        tauri::async_runtime::spawn_blocking(move || journal.undo_files()).await??;
+    let safety_backup = match &snapshot {
+        Some(snapshot) => Some(db::restore_from(app, snapshot).await?),
+        None => None,
+    };
+    let _ = std::fs::remove_dir_all(root);
+    Ok(ImportRollback {
+        restored_database: snapshot.map(|path| path.to_string_lossy().into_owned()),
+        safety_backup: safety_backup.map(|path| path.to_string_lossy().into_owned()),
+        removed_files,
+        restored_files,
+    })
+}
+
+/// Rejects archives this build cannot read: unknown layouts, a database
+/// migrated past what this build knows, or no database at all.
+fn check_manifest(manifest: &ImportManifest, latest_schema: i64) -> AppResult<()> {
+    if manifest.format_version == 0 || manifest.format_version > WORKSPACE_FORMAT_VERSION {
+        return Err(AppError::Validation(format!(
+            "Workspace format {} is not supported (this app reads up to {WORKSPACE_FORMAT_VERSION})",
+            manifest.format_version
+        )));
+    }
+    if let Some(newest) = manifest
+        .schema_versions
+        .iter()
+        .copied()
+        .max()
+        .filter(|newest| *newest > latest_schema)
+    {
+        return Err(AppError::Validation(format!(
+            "Workspace database is at schema version {newest}, newer than this app \
+             supports ({latest_schema}); update the app to import it"
+        )));
+    }
+    if !manifest
+        .entries
+        .iter()
+        .any(|entry| entry.kind == EntryKind::Database && entry.name == DATABASE_ENTRY)
+    {
+        return Err(AppError::Validation(
+            "Workspace archive does not contain a database".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Resolves an archive name below `root`, refusing anything that is not a
+/// plain relative path so an archive cannot write outside its directory.
+fn unpack_target(root: &Path, relative: &str) -> AppResult<PathBuf> {
+    let relative = Path::new(relative);
+    let plain = relative.components().next().is_some()
+        && relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if !plain {
+        return Err(AppError::Validation(format!(
+            "Archive entry {} has an unsafe path",
+            relative.display()
+        )));
+    }
+    Ok(root.join(relative))
+}
+
+fn read_manifest(archive: &mut ZipArchive<File>) -> AppResult<ImportManifest> {
+    let manifest = archive.by_name(MANIFEST_NAME).map_err(|_| {
+        AppError::Validation("Not a workspace archive: manifest.json is missing".into())
+    })?;
+    serde_json::from_reader(manifest)
+        .map_err(|err| AppError::Validation(format!("Invalid workspace manifest: {err}")))
+}
+
+fn unpack_entry(archive: &mut ZipArchive<File>, name: &str, target: &Path) -> AppResult<()> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|_| AppError::Validation(format!("Archive entry {name} is missing")))?;
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::io::copy(&mut entry, &mut File::create(target)?)?;
+    Ok(())
+}
+
+/// Opens the archive, checks its manifest and extracts the database to
+/// `database`, touching nothing else.
+fn open_workspace(
+    zip_path: &Path,
+    latest_schema: i64,
+    database: &Path,
+) -> AppResult<ImportManifest> {
+    let mut archive = ZipArchive::new(File::open(zip_path)?)
+        .map_err(|err| AppError::Validation(format!("Not a workspace archive: {err}")))?;
+    let manifest = read_manifest(&mut archive)?;
+    check_manifest(&manifest, latest_schema)?;
+    unpack_entry(&mut archive, DATABASE_ENTRY, database)?;
+    Ok(manifest)
+}
+
+/// Unpacks thumbnails under `thumbnail_root` and source PDFs under
+/// `library_root/<file id>/`, returning the thumbnail count and the new
+/// location of each PDF by file id. Every file is claimed in `journal`
+/// before it is written, so a failure part-way can be undone.
+fn unpack_files(
+    zip_path: &Path,
+    manifest: &ImportManifest,
+    thumbnail_root: &Path,
+    library_root: &Path,
+    journal: &mut ImportJournal,
+    stash_root: &Path,
+) -> AppResult<(usize, Vec<(String, PathBuf)>)> {
+    let mut archive = ZipArchive::new(File::open(zip_path)?)?;
+    let mut thumbnails = 0;
+    let mut pdfs = Vec::new();
+    for entry in &manifest.entries {
+        match entry.kind {
+            EntryKind::Database => {}
+            EntryKind::Thumbnail => {
+                let relative = entry.name.strip_prefix("thumbnails/").unwrap_or_default();
+                let target = unpack_target(thumbnail_root, relative)?;
+                journal.claim(&target, stash_root)?;
+                unpack_entry(&mut archive, &entry.name, &target)?;
+                thumbnails += 1;
+            }
+            EntryKind::SourcePdf => {
+                let Some(file_id) = &entry.file_id else {
+                    continue;
+                };
+                let file_name = entry.name.rsplit('/').next().unwrap_or_default();
+                let relative = format!("{}/{file_name}", archive_segment(file_id));
+                let target = unpack_target(library_root, &relative)?;
+                journal.claim(&target, stash_root)?;
+                unpack_entry(&mut archive, &entry.name, &target)?;
+                pdfs.push((file_id.clone(), target));
+            }
+        }
+    }
+    Ok((thumbnails, pdfs))
+}
+
+/// Restores a workspace written by `export_workspace`: the manifest is
+/// checked and the database extracted before anything is replaced, then the
+/// live database is restored (with a safety snapshot, as `db_restore`
+/// takes), thumbnails and PDFs are unpacked, and `files.path` is pointed at
+/// the unpacked PDFs. If any step after the restore fails, the import is
+/// rolled back before the error is returned; a completed import can be
+/// undone with `rollback_last_import` until the next one starts.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn import_workspace(
+    app: AppHandle,
+    zip_path: String,
+) -> AppResult<WorkspaceImport> {
+    let zip_path = PathBuf::from(zip_path);
+    let database = std::env::temp_dir().join(format!(
+        "jumpchain-workspace-import-{}-{}.sqlite",
+        std::process::id(),
+        now_millis()
+    ));
+
+    let opened = {
+        let (zip_path, database) = (zip_path.clone(), database.clone());
+        tauri::async_runtime::spawn_blocking(move || {
+            open_workspace(&zip_path, migrations::latest_version(), &database)
+        })
+        .await?
+    };
+    // Starting a new import lets go of what the previous one replaced.
+    let rollback_root = app.path().app_data_dir()?.join(ROLLBACK_DIR);
+    let restored = match opened {
+        Ok(manifest) => match std::fs::remove_dir_all(&rollback_root) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => db::restore_from(&app, &database)
+                .await
+                .map(|safety| (manifest, safety)),
+        },
+        Err(err) => Err(err),
+    };
+    let _ = std::fs::remove_file(&database);
+    let (manifest, safety_backup) = restored?;
+
+    let mut journal = ImportJournal {
+        safety_backup: Some(safety_backup.clone()),
+        ..ImportJournal::default()
+    };
+    match import_files(&app, &zip_path, manifest, &mut journal, &rollback_root).await {
+        Ok((thumbnails, source_pdfs, relinked)) => Ok(WorkspaceImport {
+            safety_backup: safety_backup.to_string_lossy().into_owned(),
+            thumbnails,
+            source_pdfs,
+            relinked,
+        }),
+        Err(err) => {
+            // The saved journal has every claim, even from an unpack that
+            // panicked before handing the in-memory one back.
+            let journal = ImportJournal::load(&rollback_root)
+                .ok()
+                .flatten()
+                .unwrap_or(journal);
+            if let Err(rollback) = roll_back(&app, &rollback_root, journal).await {
+                tracing::error!(
+                    error = %rollback,
+                    "rolling back the failed workspace import failed"
+                );
+            }
+            Err(err)
+        }
+    }
+}
+
+/// The steps of `import_workspace` after the database is restored:
+/// unpacks thumbnails and PDFs, recording them in `journal`, and relinks
+/// the PDFs. Returns the thumbnail, PDF and relinked row counts.
+async fn import_files(
+    app: &AppHandle,
+    zip_path: &Path,
+    manifest: ImportManifest,
+    journal: &mut ImportJournal,
+    rollback_root: &Path,
+) -> AppResult<(usize, usize, u64)> {
+    journal.save(rollback_root)?;
+    let thumbnail_root = thumbnails::cache_root(app)?;
+    let library_root = app.path().app_data_dir()?.join(LIBRARY_DIR);
+    // The snapshot stays behind in memory too, should the unpack panic.
+    let kept = ImportJournal {
+        safety_backup: journal.safety_backup.clone(),
+        ..ImportJournal::default()
+    };
+    let mut claimed = std::mem::replace(journal, kept);
+    let (zip_path, stash_root) = (zip_path.to_path_buf(), rollback_root.to_path_buf());
+    let (claimed, unpacked) = tauri::async_runtime::spawn_blocking(move || {
+        let unpacked = unpack_files(
+            &zip_path,
+            &manifest,
+            &thumbnail_root,
+            &library_root,
+            &mut claimed,
+            &stash_root,
+        );
+        (claimed, unpacked)
+    })
+    .await?;
+    *journal = claimed;
+    journal.save(rollback_root)?;
+    let (thumbnails, pdfs) = unpacked?;
+
+    let pool = db::pool(app).await?;
+    let mut relinked = 0;
+    for (file_id, path) in &pdfs {
+        relinked += sqlx::query("UPDATE files SET path = ? WHERE id = ?")
+            .bind(path.to_string_lossy().into_owned())
+            .bind(file_id)
+            .execute(&pool)
+            .await?
+            .rows_affected();
+    }
+    Ok((thumbnails, pdfs.len(), relinked))
+}
+
+/// Undoes the last `import_workspace`, for problems noticed after it
+/// finished: unpacked files are removed, files it overwrote are put back
+/// and the database is restored from the snapshot taken before it.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub(crate) async fn rollback_last_import(app: AppHandle) -> AppResult<ImportRollback> {
+    let root = app.path().app_data_dir()?.join(ROLLBACK_DIR);
+    let journal = ImportJournal::load(&root)?
+        .ok_or_else(|| AppError::NotFound("There is no workspace import to roll back".into()))?;
+    roll_back(&app, &root, journal).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    #[test]
+    fn archives_files_with_the_manifest_first() {
+        let dir = std::env::temp_dir().join(format!("jumpchain-workspace-{}", std::process::id()));
+        let thumbs = dir.join("thumbs");
+        std::fs::create_dir_all(thumbs.join("naruto")).unwrap();
+        std::fs::write(thumbs.join("naruto").join("page-1.png"), b"png").unwrap();
+        std::fs::write(thumbs.join("naruto").join("source.mtime"), b"1.0").unwrap();
+        let database = dir.join("snapshot.sqlite");
+        std::fs::write(&database, b"SQLite format 3\0").unwrap();
+
+        let mut entries = vec![ArchiveEntry::new(
+            DATABASE_ENTRY.into(),
+            EntryKind::Database,
+            database,
+        )];
+        entries.extend(dir_entries(&thumbs, "thumbnails", EntryKind::Thumbnail));
+        let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                DATABASE_ENTRY,
+                "thumbnails/naruto/page-1.png",
+                "thumbnails/naruto/source.mtime"
+            ]
+        );
+        assert!(dir_entries(&dir.join("missing"), "x", EntryKind::Thumbnail).is_empty());
+
+        let archive_path = dir.join("workspace.zip");
+        let mut seen = Vec::new();
+        let bytes = write_archive(
+            &archive_path,
+            b"{\"format_version\":1}",
+            &entries,
+            |current, _| seen.push(current),
+        )
+        .unwrap();
+        assert_eq!(seen, [1, 2, 3]);
+        assert_eq!(bytes, std::fs::metadata(&archive_path).unwrap().len());
+        assert!(!dir.join("workspace.zip.partial").exists());
+
+        let mut archive = ZipArchive::new(File::open(&archive_path).unwrap()).unwrap();
+        assert_eq!(archive.by_index(0).unwrap().name(), MANIFEST_NAME);
+        let mut png = String::new();
+        archive
+            .by_name("thumbnails/naruto/page-1.png")
+            .unwrap()
+            .read_to_string(&mut png)
+            .unwrap();
+        assert_eq!(png, "png");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn manifest(format_version: u32, schema_versions: Vec<i64>) -> ImportManifest {
+        ImportManifest {
+            format_version,
+            schema_versions,
+            entries: vec![ImportEntry {
+                name: DATABASE_ENTRY.into(),
+                kind: EntryKind::Database,
+                file_id: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn check_manifest_rejects_unsupported_archives() {
+        assert!(check_manifest(&manifest(1, vec![1, 4, 5]), 5).is_ok());
+        for (unsupported, expected) in [
+            (manifest(2, vec![1]), "Workspace format 2"),
+            (manifest(0, vec![1]), "Workspace format 0"),
+            (manifest(1, vec![1, 6]), "schema version 6"),
+        ] {
+            let error = check_manifest(&unsupported, 5).unwrap_err();
+            assert_eq!(error.code(), "validation");
+            assert!(error.message().contains(expected), "{error}");
+        }
+        let mut empty = manifest(1, vec![]);
+        empty.entries.clear();
+        assert!(check_manifest(&empty, 5).is_err());
+    }
+
+    #[test]
+    fn unpack_target_stays_inside_the_root() {
+        let root = Path::new("/cache/thumbs");
+        assert_eq!(
+            unpack_target(root, "naruto/page-1.png").unwrap(),
+            root.join("naruto").join("page-1.png")
+        );
+        for unsafe_name in ["../evil.png", "/etc/passwd", "a/../../b", ""] {
+            assert!(unpack_target(root, unsafe_name).is_err(), "{unsafe_name}");
+        }
+    }
+
+    #[test]
+    fn imports_what_the_export_wrote() {
+        let dir =
+            std::env::temp_dir().join(format!("jumpchain-workspace-import-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("thumbs").join("naruto")).unwrap();
+        std::fs::write(dir.join("thumbs").join("naruto").join("page-1.png"), b"png").unwrap();
+        std::fs::write(dir.join("snapshot.sqlite"), b"SQLite format 3\0").unwrap();
+        std::fs::write(dir.join("naruto.pdf"), b"%PDF-1.7").unwrap();
+
+        let mut entries = vec![ArchiveEntry::new(
+            DATABASE_ENTRY.into(),
+            EntryKind::Database,
+            dir.join("snapshot.sqlite"),
+        )];
+        entries.extend(dir_entries(
+            &dir.join("thumbs"),
+            "thumbnails",
+            EntryKind::Thumbnail,
+        ));
+        entries.push(ArchiveEntry {
+            file_id: Some("f1".into()),
+            ..ArchiveEntry::new(
+                "pdfs/f1/naruto.pdf".into(),
+                EntryKind::SourcePdf,
+                dir.join("naruto.pdf"),
+            )
+        });
+        let manifest = serde_json::to_vec(&serde_json::json!({
+            "format_version": WORKSPACE_FORMAT_VERSION,
+            "schema_versions": [1],
+            "entries": entries,
+        }))
+        .unwrap();
+        let archive = dir.join("workspace.zip");
+        write_archive(&archive, &manifest, &entries, |_, _| {}).unwrap();
+
+        let database = dir.join("restored.sqlite");
+        let opened = open_workspace(&archive, 1, &database).unwrap();
+        assert_eq!(std::fs::read(&database).unwrap(), b"SQLite format 3\0");
+        assert!(open_workspace(&archive, 0, &dir.join("never.sqlite")).is_err());
+        assert!(!dir.join("never.sqlite").exists());
+
+        let mut journal = ImportJournal::default();
+        let (thumbnails, pdfs) = unpack_files(
+            &archive,
+            &opened,
+            &dir.join("new-thumbs"),
+            &dir.join("library"),
+            &mut journal,
+            &dir.join("stash"),
+        )
+        .unwrap();
+        assert_eq!(thumbnails, 1);
+        assert!(dir
+            .join("new-thumbs")
+            .join("naruto")
+            .join("page-1.png")
+            .is_file());
+        assert_eq!(
+            pdfs,
+            vec![(
+                "f1".to_string(),
+                dir.join("library").join("f1").join("naruto.pdf")
+            )]
+        );
+        assert_eq!(std::fs::read(&pdfs[0].1).unwrap(), b"%PDF-1.7");
+        assert_eq!(journal.created.len(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn journal_undoes_created_and_replaced_files() {
+        let dir = std::env::temp_dir().join(format!(
+            "jumpchain-workspace-rollback-{}",
+            std::process::id()
+        ));
+        let thumbs = dir.join("thumbs").join("naruto");
+        std::fs::create_dir_all(&thumbs).unwrap();
+        let kept = thumbs.join("page-1.png");
+        std::fs::write(&kept, b"old").unwrap();
+        let stash = dir.join("stash");
+
+        let mut journal = ImportJournal::default();
+        let added = dir.join("library").join("f1").join("naruto.pdf");
+        for target in [&kept, &added] {
+            journal.claim(target, &stash).unwrap();
+            std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+            std::fs::write(target, b"new").unwrap();
+        }
+        assert_eq!(journal.created, vec![added.clone()]);
+        assert_eq!(journal.replaced, vec![(kept.clone(), stash.join("0"))]);
+
+        // Each claim was saved as it was made.
+        let loaded = ImportJournal::load(&stash).unwrap().unwrap();
+        assert_eq!(loaded, journal);
+        assert_eq!(loaded.undo_files().unwrap(), (1, 1));
+        assert_eq!(std::fs::read(&kept).unwrap(), b"old");
+        assert!(!dir.join("library").join("f1").exists());
+        // Undoing again finds nothing left to do.
+        assert_eq!(loaded.undo_files().unwrap(), (0, 0));
+        assert_eq!(ImportJournal::load(&dir).unwrap(), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn archive_segments_stay_single_components() {
+        assert_eq!(archive_segment("naruto-1_a"), "naruto-1_a");
+        assert_eq!(archive_segment("../etc/passwd"), "___etc_passwd");
+    }
+}
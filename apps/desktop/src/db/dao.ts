@@ -740,6 +740,9 @@ async function ensureLegacyColumns(db: Database): Promise<void> {
   await ensureColumn(db, "jumps", "cp_budget", "cp_budget INTEGER DEFAULT 0");
   await ensureColumn(db, "jumps", "cp_spent", "cp_spent INTEGER DEFAULT 0");
   await ensureColumn(db, "jumps", "cp_income", "cp_income INTEGER DEFAULT 0");
+  await ensureColumn(db, "jumps", "deleted_at", "deleted_at TEXT");
+  await ensureColumn(db, "jump_assets", "deleted_at", "deleted_at TEXT");
+  await ensureColumn(db, "character_profiles", "deleted_at", "deleted_at TEXT");
 }
 
 export async function ensureInitialized(): Promise<void> {
@@ -975,14 +978,17 @@ export async function updateJump(
 export async function listJumps(): Promise<JumpRecord[]> {
   return withInit(async (db) => {
     const rows = await db.select<JumpRecord[]>(
-      "SELECT * FROM jumps ORDER BY sort_order ASC, created_at DESC"
+      "SELECT * FROM jumps WHERE deleted_at IS NULL ORDER BY sort_order ASC, created_at DESC"
     );
     return rows as JumpRecord[];
   });
 }
 
+/** Moves the jump to the recycle bin; `restore_deleted` brings it back. */
 export async function deleteJump(id: string): Promise<void> {
-  await withInit((db) => db.execute("DELETE FROM jumps WHERE id = $1", [id]));
+  await withInit((db) =>
+    db.execute("UPDATE jumps SET deleted_at = $1 WHERE id = $2", [new Date().toISOString(), id])
+  );
 }
 
 export async function reorderJumps(orderedIds: string[]): Promise<void> {
@@ -1402,6 +1408,8 @@ export async function lookupAssetReferenceSummaries(
          FROM jump_assets a
          JOIN jumps j ON j.id = a.jump_id
         WHERE a.id IN (${placeholders})
+          AND a.deleted_at IS NULL
+          AND j.deleted_at IS NULL
         ORDER BY j.title COLLATE NOCASE, a.asset_type, a.name COLLATE NOCASE`,
       normalized
     )) as AssetReferenceRow[];
@@ -1425,6 +1433,8 @@ export async function listAssetReferenceSummaries(): Promise<AssetReferenceSumma
               j.title AS jump_title
          FROM jump_assets a
          JOIN jumps j ON j.id = a.jump_id
+        WHERE a.deleted_at IS NULL
+          AND j.deleted_at IS NULL
         ORDER BY j.title COLLATE NOCASE, a.asset_type, a.name COLLATE NOCASE`
     )) as AssetReferenceRow[];
     return rows.map((row) => ({
@@ -2531,7 +2541,7 @@ async function summarizeJumpBudgetWithDb(db: Database, jumpId: string): Promise<
   const assetRows = (await db.select<JumpAssetRecord[]>(
     `SELECT id, asset_type, name, cost, quantity, discounted, freebie, metadata
      FROM jump_assets
-     WHERE jump_id = $1`,
+     WHERE jump_id = $1 AND deleted_at IS NULL`,
     [jumpId]
   )) as JumpAssetRecord[];
 
@@ -2656,7 +2666,7 @@ export async function listJumpAssets(
       const placeholders = typeList.map((_, index) => `$${index + 2}`).join(", ");
       const rows = (await db.select<JumpAssetRow[]>(
         `SELECT * FROM jump_assets
-         WHERE jump_id = $1 AND asset_type IN (${placeholders})
+         WHERE jump_id = $1 AND asset_type IN (${placeholders}) AND deleted_at IS NULL
          ORDER BY sort_order ASC, created_at ASC`,
         [jumpId, ...typeList]
       )) as JumpAssetRow[];
@@ -2670,7 +2680,7 @@ export async function listJumpAssets(
     }
     const rows = (await db.select<JumpAssetRow[]>(
       `SELECT * FROM jump_assets
-       WHERE jump_id = $1
+       WHERE jump_id = $1 AND deleted_at IS NULL
        ORDER BY asset_type ASC, sort_order ASC, created_at ASC`,
       [jumpId]
     )) as JumpAssetRow[];
@@ -2820,7 +2830,10 @@ export async function deleteJumpAsset(id: string): Promise<void> {
       [id]
     );
     const jumpId = rows[0]?.jump_id;
-    await db.execute(`DELETE FROM jump_assets WHERE id = $1`, [id]);
+    await db.execute(`UPDATE jump_assets SET deleted_at = $1 WHERE id = $2`, [
+      new Date().toISOString(),
+      id,
+    ]);
     if (jumpId) {
       await updateJumpCostSummary(jumpId);
     }
@@ -3451,6 +3464,7 @@ export async function listCharacterProfiles(): Promise<CharacterProfileRecord[]>
     const rows = await db.select<CharacterProfileRecord[]>(
       `SELECT id, name, alias, species, homeland, biography, attributes_json, traits_json, alt_forms_json, notes, created_at, updated_at
        FROM character_profiles
+       WHERE deleted_at IS NULL
        ORDER BY created_at ASC`
     );
     return rows as CharacterProfileRecord[];
@@ -4589,6 +4603,7 @@ export async function loadPassportDerivedSnapshot(): Promise<PassportDerivedSnap
       `SELECT a.*, j.title AS jump_title
          FROM jump_assets a
          LEFT JOIN jumps j ON j.id = a.jump_id
+         WHERE a.deleted_at IS NULL AND j.deleted_at IS NULL
          ORDER BY j.sort_order ASC, a.asset_type ASC, a.sort_order ASC, a.created_at ASC`
     );
 
@@ -5054,7 +5069,8 @@ export async function loadStatisticsSnapshot(): Promise<StatisticsSnapshot> {
              END
            ), 0) AS earned
          FROM jumps j
-         LEFT JOIN jump_assets a ON a.jump_id = j.id
+         LEFT JOIN jump_assets a ON a.jump_id = j.id AND a.deleted_at IS NULL
+         WHERE j.deleted_at IS NULL
          GROUP BY j.id
          ORDER BY j.sort_order ASC, j.created_at ASC`
       ),
@@ -5080,6 +5096,8 @@ export async function loadStatisticsSnapshot(): Promise<StatisticsSnapshot> {
            COALESCE(SUM(CASE WHEN asset_type <> 'drawback' AND COALESCE(discounted, 0) = 1 AND COALESCE(freebie, 0) = 0 THEN 1 ELSE 0 END), 0) AS discounted_count,
            COALESCE(SUM(CASE WHEN COALESCE(freebie, 0) = 1 THEN 1 ELSE 0 END), 0) AS freebie_count
          FROM jump_assets
+         WHERE deleted_at IS NULL
+           AND jump_id NOT IN (SELECT id FROM jumps WHERE deleted_at IS NOT NULL)
          GROUP BY asset_type`
       ),
       db.select<InventoryCategoryAggregateRow[]>(
@@ -5121,8 +5139,9 @@ export async function loadStatisticsSnapshot(): Promise<StatisticsSnapshot> {
              COALESCE(a.discounted, 0) AS discounted,
              COALESCE(a.freebie, 0) AS freebie
            FROM jumps j
-           LEFT JOIN jump_assets a ON a.jump_id = j.id
-           WHERE LOWER(COALESCE(j.status, '')) LIKE '%gauntlet%'
+           LEFT JOIN jump_assets a ON a.jump_id = j.id AND a.deleted_at IS NULL
+           WHERE j.deleted_at IS NULL
+             AND LOWER(COALESCE(j.status, '')) LIKE '%gauntlet%'
          )
          SELECT
            id AS jump_id,
@@ -5152,7 +5171,7 @@ export async function loadStatisticsSnapshot(): Promise<StatisticsSnapshot> {
         [UNIVERSAL_DRAWBACK_SETTING_ID]
       ),
       db.select<BoosterSourceRow[]>(
-        `SELECT id, name, attributes_json, traits_json FROM character_profiles ORDER BY created_at ASC`
+        `SELECT id, name, attributes_json, traits_json FROM character_profiles WHERE deleted_at IS NULL ORDER BY created_at ASC`
       ),
     ]);
 
@@ -32,6 +32,7 @@ CREATE TABLE IF NOT EXISTS jumps (
     cp_budget INTEGER DEFAULT 0,
     cp_spent INTEGER DEFAULT 0,
     cp_income INTEGER DEFAULT 0,
+    deleted_at TEXT,
     created_at TEXT DEFAULT CURRENT_TIMESTAMP
 );
 
@@ -154,6 +155,7 @@ CREATE TABLE IF NOT EXISTS jump_assets (
     freebie INTEGER DEFAULT 0,
     notes TEXT,
     metadata TEXT,
+    deleted_at TEXT,
     sort_order INTEGER DEFAULT 0,
     created_at TEXT DEFAULT CURRENT_TIMESTAMP,
     updated_at TEXT DEFAULT CURRENT_TIMESTAMP
@@ -218,6 +220,7 @@ CREATE TABLE IF NOT EXISTS character_profiles (
     traits_json TEXT,
     alt_forms_json TEXT,
     notes TEXT,
+    deleted_at TEXT,
     created_at TEXT DEFAULT CURRENT_TIMESTAMP,
     updated_at TEXT DEFAULT CURRENT_TIMESTAMP
 );
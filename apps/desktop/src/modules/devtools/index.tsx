@@ -82,6 +82,18 @@ const createToastId = (() => {
 const formatTimestamp = (date: Date): string =>
   `${date.toLocaleTimeString([], { hour: "2-digit", minute: "2-digit", second: "2-digit" })}`;
 
+type CommandError = { code: string; message: string };
+
+const describeCommandError = (error: unknown): string => {
+  if (error instanceof Error) {
+    return error.message;
+  }
+  if (error && typeof error === "object" && "message" in error) {
+    return String((error as CommandError).message);
+  }
+  return String(error);
+};
+
 const DevToolsTestRunner: React.FC = () => {
   const [logs, setLogs] = useState<LogEntry[]>([]);
   const [isRunning, setIsRunning] = useState(false);
@@ -205,7 +217,7 @@ const DevToolsTestRunner: React.FC = () => {
       await invoke("run_full_test_suite");
     } catch (error) {
       setIsRunning(false);
-      const message = error instanceof Error ? error.message : String(error);
+      const message = describeCommandError(error);
       const entry: LogEntry = {
         id: nextLogId.current++,
         level: "error",
@@ -224,7 +236,7 @@ const DevToolsTestRunner: React.FC = () => {
     try {
       await invoke("cancel_full_test_suite");
     } catch (error) {
-      const message = error instanceof Error ? error.message : String(error);
+      const message = describeCommandError(error);
       const entry: LogEntry = {
         id: nextLogId.current++,
         level: "error",
@@ -0,0 +1,126 @@
+/*
+Bloodawn
+
+Copyright (c) 2025 Bloodawn
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+import React, { useEffect, useId, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+
+export type AppInfo = {
+  app_version: string;
+  tauri_version: string;
+  sqlite_version: string;
+  os: string;
+  arch: string;
+  webview_version: string | null;
+};
+
+const INFO_ROWS: Array<[keyof AppInfo, string]> = [
+  ["app_version", "App"],
+  ["tauri_version", "Tauri"],
+  ["sqlite_version", "SQLite"],
+  ["webview_version", "Webview"],
+  ["os", "OS"],
+  ["arch", "Architecture"],
+];
+
+export const formatAppInfo = (info: AppInfo): string =>
+  INFO_ROWS.map(([key, label]) => `${label}: ${info[key] ?? "unknown"}`).join("\n");
+
+interface AboutDialogProps {
+  onClose: () => void;
+}
+
+const AboutDialog: React.FC<AboutDialogProps> = ({ onClose }) => {
+  const titleId = useId();
+  const [info, setInfo] = useState<AppInfo | null>(null);
+  const [statusMessage, setStatusMessage] = useState<string | null>(null);
+
+  useEffect(() => {
+    let isMounted = true;
+    invoke<AppInfo>("app_info")
+      .then((result) => {
+        if (isMounted) {
+          setInfo(result);
+        }
+      })
+      .catch((error) => {
+        console.error("Failed to load app info", error);
+        if (isMounted) {
+          setStatusMessage("Could not read version details.");
+        }
+      });
+    return () => {
+      isMounted = false;
+    };
+  }, []);
+
+  const clipboardAvailable = typeof navigator !== "undefined" && Boolean(navigator.clipboard);
+
+  const handleCopy = async () => {
+    if (!info || !clipboardAvailable) {
+      setStatusMessage("Clipboard is unavailable in this environment.");
+      return;
+    }
+    try {
+      await navigator.clipboard.writeText(formatAppInfo(info));
+      setStatusMessage("Version details copied to clipboard");
+    } catch (error) {
+      console.error("Failed to copy to clipboard", error);
+      setStatusMessage("Could not copy to clipboard.");
+    }
+  };
+
+  return (
+    <div className="options__about-backdrop" role="dialog" aria-modal="true" aria-labelledby={titleId}>
+      <div className="options__about-panel">
+        <header className="options__card-header">
+          <h2 id={titleId}>About Jumpchain Nexus</h2>
+          <p>Include these details when reporting a bug.</p>
+        </header>
+        {info ? (
+          <dl className="options__about-list">
+            {INFO_ROWS.map(([key, label]) => (
+              <React.Fragment key={key}>
+                <dt>{label}</dt>
+                <dd>{info[key] ?? "unknown"}</dd>
+              </React.Fragment>
+            ))}
+          </dl>
+        ) : (
+          !statusMessage && <p className="options__hint">Loading version details…</p>
+        )}
+        {statusMessage && <p className="options__status">{statusMessage}</p>}
+        <div className="options__about-actions">
+          <button type="button" onClick={handleCopy} disabled={!info || !clipboardAvailable}>
+            Copy details
+          </button>
+          <button type="button" className="ghost" onClick={onClose}>
+            Close
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+};
+
+export default AboutDialog;
@@ -80,6 +80,7 @@ import {
 } from "../../db/dao";
 import { APPEARANCE_SETTINGS_QUERY_KEY } from "../../hooks/useAppearanceSettings";
 import { FORMATTER_PREFERENCES_QUERY_KEY, useFormatterPreferences } from "../../hooks/useFormatterPreferences";
+import AboutDialog from "./AboutDialog";
 
 const EXPORT_PRESET_FORMAT_LABELS = {
   markdown: "Markdown",
@@ -325,6 +326,7 @@ const JumpchainOptions: React.FC = () => {
     queryFn: loadUniversalDrawbackSettings,
   });
   const formatterSettingsQuery = useFormatterPreferences();
+  const [aboutOpen, setAboutOpen] = useState(false);
 
   const [appearanceSettings, setAppearanceSettings] = useState<AppearanceSettings>(
     DEFAULT_APPEARANCE_SETTINGS
@@ -1796,7 +1798,21 @@ const JumpchainOptions: React.FC = () => {
           {sectionMessage("category-presets")}
           <p className="options__hint">Saved categories appear in Warehouse filters and future perk editors.</p>
         </section>
+
+        <section className="options__card">
+          <header className="options__card-header">
+            <h2>About</h2>
+            <p>Version and platform details for bug reports.</p>
+          </header>
+          <div className="options__about-actions">
+            <button type="button" onClick={() => setAboutOpen(true)}>
+              Show version details
+            </button>
+          </div>
+        </section>
       </div>
+
+      {aboutOpen && <AboutDialog onClose={() => setAboutOpen(false)} />}
     </section>
   );
 };
@@ -26,7 +26,10 @@ import { QueryClient, QueryClientProvider } from "@tanstack/react-query";
 import { render, screen, waitFor, within } from "@testing-library/react";
 import userEvent from "@testing-library/user-event";
 
+import { invoke } from "@tauri-apps/api/core";
+
 import JumpchainOptions from "./index";
+import { formatAppInfo, type AppInfo } from "./AboutDialog";
 import InputFormatter from "../formatter";
 import {
   APPEARANCE_SETTING_KEY,
@@ -52,6 +55,11 @@ import type {
   UpsertEssentialBodyModEssenceInput,
 } from "../../db/dao";
 
+vi.mock("@tauri-apps/api/core", async (importOriginal) => ({
+  ...(await importOriginal<typeof import("@tauri-apps/api/core")>()),
+  invoke: vi.fn(),
+}));
+
 vi.mock("../../db/dao", async (importOriginal) => {
   const actual = await importOriginal<typeof import("../../db/dao")>();
 
@@ -508,3 +516,48 @@ describe("JumpchainOptions", () => {
     expect(within(updatedNotes).getByText("Markdown · Spoiler")).toBeInTheDocument();
   });
 });
+
+describe("AboutDialog", () => {
+  const info: AppInfo = {
+    app_version: "1.4.0",
+    tauri_version: "2.1.1",
+    sqlite_version: "3.46.0",
+    os: "windows",
+    arch: "x86_64",
+    webview_version: null,
+  };
+
+  it("formats version details for bug reports", () => {
+    expect(formatAppInfo(info)).toBe(
+      [
+        "App: 1.4.0",
+        "Tauri: 2.1.1",
+        "SQLite: 3.46.0",
+        "Webview: unknown",
+        "OS: windows",
+        "Architecture: x86_64",
+      ].join("\n")
+    );
+  });
+
+  it("opens from the options page with the backend's details", async () => {
+    vi.mocked(invoke).mockResolvedValue(info);
+    const queryClient = createTestQueryClient();
+    const user = userEvent.setup();
+
+    render(
+      <QueryClientProvider client={queryClient}>
+        <JumpchainOptions />
+      </QueryClientProvider>
+    );
+
+    await user.click(await screen.findByRole("button", { name: /show version details/i }));
+    const dialog = await screen.findByRole("dialog", { name: /about jumpchain nexus/i });
+    expect(await within(dialog).findByText("3.46.0")).toBeInTheDocument();
+    expect(invoke).toHaveBeenCalledWith("app_info");
+
+    await user.click(within(dialog).getByRole("button", { name: /close/i }));
+    expect(screen.queryByRole("dialog")).not.toBeInTheDocument();
+    queryClient.clear();
+  });
+});